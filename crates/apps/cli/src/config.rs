@@ -4,7 +4,8 @@ use std::path::{Path, PathBuf};
 
 use anyhow::{Context, anyhow};
 use cargo_metadata::MetadataCommand;
-use namu_proto::{TaskKind, TaskRuntime, TaskTrust};
+use namu_proto::{BatchPolicy, RetryPolicy, TaskKind, TaskLimits, TaskRuntime, TaskTrust};
+use serde::Deserialize;
 use serde_json::Value as JsonValue;
 
 #[derive(Clone, Debug)]
@@ -47,6 +48,30 @@ pub struct TaskConfig {
     pub output_arity: usize,
     pub input_schema: JsonValue,
     pub output_schema: JsonValue,
+    pub limits: TaskLimits,
+    pub retry: RetryPolicy,
+    pub batch: BatchOverrides,
+}
+
+/// `[tasks.<id>.batch]` settings. A batch task declares its policy with
+/// `#[task(batch, batch_size = .., max_wait_ms = ..)]`; these replace only
+/// the fields they set.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BatchOverrides {
+    pub max_size: Option<usize>,
+    pub linger_ms: Option<u64>,
+}
+
+impl BatchOverrides {
+    pub fn apply(&self, policy: &mut BatchPolicy) {
+        if let Some(max_size) = self.max_size {
+            policy.max_size = max_size;
+        }
+        if let Some(linger_ms) = self.linger_ms {
+            policy.linger_ms = linger_ms;
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -245,6 +270,9 @@ fn parse_tasks(doc: &toml::Value, root: &Path) -> anyhow::Result<HashMap<String,
             .get("output_schema")
             .map(toml_value_to_json)
             .unwrap_or(JsonValue::Null);
+        let limits = parse_task_limits(id, entry.get("limits"))?;
+        let retry = parse_retry_policy(id, entry.get("retry"))?;
+        let batch = parse_batch_overrides(id, entry.get("batch"))?;
 
         tasks.insert(
             id.to_string(),
@@ -264,6 +292,9 @@ fn parse_tasks(doc: &toml::Value, root: &Path) -> anyhow::Result<HashMap<String,
                 output_arity,
                 input_schema,
                 output_schema,
+                limits,
+                retry,
+                batch,
             },
         );
     }
@@ -342,6 +373,78 @@ fn parse_task_trust(raw: &str) -> anyhow::Result<TaskTrust> {
     }
 }
 
+fn parse_task_limits(id: &str, value: Option<&toml::Value>) -> anyhow::Result<TaskLimits> {
+    let Some(value) = value else {
+        return Ok(TaskLimits::default());
+    };
+    let table = value
+        .as_table()
+        .ok_or_else(|| anyhow!("tasks.{id}.limits must be a table"))?;
+    let get = |key: &str| get_u64(table, id, "limits", key);
+    Ok(TaskLimits {
+        fuel: get("fuel")?,
+        timeout_ms: get("timeout_ms")?,
+        max_memory_bytes: get("max_memory_bytes")?,
+        max_output_bytes: get("max_output_bytes")?,
+    })
+}
+
+fn parse_retry_policy(id: &str, value: Option<&toml::Value>) -> anyhow::Result<RetryPolicy> {
+    let mut policy = RetryPolicy::default();
+    let Some(value) = value else {
+        return Ok(policy);
+    };
+    let table = value
+        .as_table()
+        .ok_or_else(|| anyhow!("tasks.{id}.retry must be a table"))?;
+    let get = |key: &str| get_u64(table, id, "retry", key);
+    if let Some(max_attempts) = get("max_attempts")? {
+        policy.max_attempts = u32::try_from(max_attempts)
+            .map_err(|_| anyhow!("tasks.{id}.retry.max_attempts is too large"))?;
+    }
+    if let Some(backoff_ms) = get("backoff_ms")? {
+        policy.backoff_ms = backoff_ms;
+    }
+    if let Some(max_backoff_ms) = get("max_backoff_ms")? {
+        policy.max_backoff_ms = max_backoff_ms;
+    }
+    if let Some(fatal) = table.get("fatal_errors") {
+        let kinds = fatal
+            .as_array()
+            .and_then(|arr| arr.iter().map(|v| v.as_str()).collect::<Option<Vec<_>>>())
+            .ok_or_else(|| anyhow!("tasks.{id}.retry.fatal_errors must be an array of strings"))?;
+        policy.fatal_errors = kinds
+            .into_iter()
+            .map(|kind| {
+                kind.parse()
+                    .map_err(|err| anyhow!("tasks.{id}.retry.fatal_errors: {err}"))
+            })
+            .collect::<anyhow::Result<_>>()?;
+    }
+    Ok(policy)
+}
+
+fn parse_batch_overrides(id: &str, value: Option<&toml::Value>) -> anyhow::Result<BatchOverrides> {
+    let mut overrides = BatchOverrides::default();
+    let Some(value) = value else {
+        return Ok(overrides);
+    };
+    let table = value
+        .as_table()
+        .ok_or_else(|| anyhow!("tasks.{id}.batch must be a table"))?;
+    let get = |key: &str| get_u64(table, id, "batch", key);
+    if let Some(max_size) = get("max_size")? {
+        overrides.max_size = Some(
+            usize::try_from(max_size)
+                .ok()
+                .filter(|&size| size > 0)
+                .ok_or_else(|| anyhow!("tasks.{id}.batch.max_size must be at least 1"))?,
+        );
+    }
+    overrides.linger_ms = get("linger_ms")?;
+    Ok(overrides)
+}
+
 fn parse_workflow_export(raw: &str) -> anyhow::Result<WorkflowExport> {
     match raw {
         "auto" => Ok(WorkflowExport::Auto),
@@ -349,6 +452,23 @@ fn parse_workflow_export(raw: &str) -> anyhow::Result<WorkflowExport> {
     }
 }
 
+/// The non-negative integer at `key` of the `tasks.{id}.{section}` table.
+fn get_u64(
+    table: &toml::value::Table,
+    id: &str,
+    section: &str,
+    key: &str,
+) -> anyhow::Result<Option<u64>> {
+    match table.get(key) {
+        None => Ok(None),
+        Some(v) => v
+            .as_integer()
+            .and_then(|n| u64::try_from(n).ok())
+            .map(Some)
+            .ok_or_else(|| anyhow!("tasks.{id}.{section}.{key} must be a non-negative integer")),
+    }
+}
+
 fn get_string(table: &toml::value::Table, key: &str) -> anyhow::Result<String> {
     table
         .get(key)
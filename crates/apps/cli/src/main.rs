@@ -6,10 +6,17 @@ use std::fs;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::Duration;
 
+use anyhow::Context;
 use clap::{Parser, Subcommand};
-use namu_proto::{RunCreateRequest, TaskManifest, TaskRuntime, WorkflowUploadRequest};
+use namu_core::validate::ValidationError;
+use namu_proto::{
+    BatchPolicy, RunCreateRequest, TaskKind, TaskManifest, TaskRuntime, WorkflowUploadRequest,
+};
+use namu_worker::local::LocalRunner;
 use reqwest::multipart;
+use serde::Deserialize;
 use serde_json::Value as JsonValue;
 use sha2::Digest;
 use walkdir::WalkDir;
@@ -55,14 +62,30 @@ enum Commands {
     Run {
         workflow_id: String,
         version: String,
+        /// Workflow input as NAME=VALUE; VALUE is parsed as JSON, falling back to a string
+        #[arg(short, long = "input", value_name = "NAME=VALUE")]
+        inputs: Vec<String>,
+        /// Run in-process from built artifacts instead of on the orchestrator
+        #[arg(long)]
+        local: bool,
+        /// Output directory used by build, for --local (default: ./dist)
+        #[arg(short, long, default_value = "./dist")]
+        out_dir: PathBuf,
     },
     /// Check run status
     Status { run_id: String },
+    /// Fetch the values a run returned and the calls that failed
+    Result { run_id: String },
+    /// Cancel a run and drop its queued calls
+    Cancel { run_id: String },
     /// Fetch run events
     Logs {
         run_id: String,
         #[arg(short, long, default_value = "100")]
         limit: usize,
+        /// Stream events as they happen until the run ends
+        #[arg(short, long)]
+        follow: bool,
     },
     /// List workers
     Workers,
@@ -100,14 +123,35 @@ async fn main() {
         Commands::Run {
             workflow_id,
             version,
+            inputs,
+            local,
+            out_dir,
         } => {
-            run_workflow(&workflow_id, &version).await;
+            if local {
+                run_local(&out_dir, &workflow_id, &version, &inputs).await;
+            } else {
+                run_workflow(&workflow_id, &version, &inputs).await;
+            }
         }
         Commands::Status { run_id } => {
             run_status(&run_id).await;
         }
-        Commands::Logs { run_id, limit } => {
-            run_logs(&run_id, limit).await;
+        Commands::Result { run_id } => {
+            run_result(&run_id).await;
+        }
+        Commands::Cancel { run_id } => {
+            cancel_run(&run_id).await;
+        }
+        Commands::Logs {
+            run_id,
+            limit,
+            follow,
+        } => {
+            if follow {
+                follow_logs(&run_id).await;
+            } else {
+                run_logs(&run_id, limit).await;
+            }
         }
         Commands::Workers => {
             list_workers().await;
@@ -168,6 +212,12 @@ fn build_tasks(tasks_dir: &Path, out_dir: &Path) -> anyhow::Result<()> {
         let manifest_path = entry.path();
         let manifest_raw = fs::read_to_string(manifest_path)?;
         let mut manifest: namu_proto::TaskManifest = serde_json::from_str(&manifest_raw)?;
+        let manifest_json: serde_json::Value = serde_json::from_str(&manifest_raw)?;
+        let batch = match manifest_json.get("batch") {
+            Some(batch) => config::BatchOverrides::deserialize(batch)
+                .with_context(|| format!("invalid batch in {}", manifest_path.display()))?,
+            None => config::BatchOverrides::default(),
+        };
 
         if task_dir.join("Cargo.toml").exists() {
             let mut cmd = Command::new("cargo");
@@ -186,6 +236,7 @@ fn build_tasks(tasks_dir: &Path, out_dir: &Path) -> anyhow::Result<()> {
         }
 
         let artifact_path = find_artifact(task_dir, &manifest.runtime)?;
+        manifest.batch = batch_policy(&manifest, &artifact_path, &batch)?;
         let artifact_bytes = fs::read(&artifact_path)?;
         let checksum = sha2::Sha256::digest(&artifact_bytes);
         manifest.checksum = format!("sha256:{:x}", checksum);
@@ -222,6 +273,7 @@ fn build_tasks_from_config(cfg: &config::NamuConfig, out_dir: &Path) -> anyhow::
         }
 
         let artifact_path = find_artifact(task_dir, &task.runtime)?;
+        manifest.batch = batch_policy(&manifest, &artifact_path, &task.batch)?;
         let artifact_bytes = fs::read(&artifact_path)?;
         let checksum = sha2::Sha256::digest(&artifact_bytes);
         manifest.checksum = format!("sha256:{:x}", checksum);
@@ -251,9 +303,32 @@ fn task_manifest_from_config(task: &config::TaskConfig) -> TaskManifest {
         abi_version: "1".to_string(),
         build_toolchain: "unknown".to_string(),
         created_at: "unknown".to_string(),
+        limits: task.limits.clone(),
+        retry: task.retry.clone(),
+        batch: BatchPolicy::default(),
     }
 }
 
+/// The policy a batch task's artifact declares, with the project's
+/// overrides applied.
+fn batch_policy(
+    manifest: &TaskManifest,
+    artifact_path: &Path,
+    overrides: &config::BatchOverrides,
+) -> anyhow::Result<BatchPolicy> {
+    let mut policy = match manifest.task_kind {
+        TaskKind::Batch => {
+            namu_worker::artifact::declared_batch_policy(artifact_path, &manifest.runtime)
+                .with_context(|| {
+                    format!("failed to read the batch policy of {}", manifest.task_id)
+                })?
+        }
+        _ => BatchPolicy::default(),
+    };
+    overrides.apply(&mut policy);
+    Ok(policy)
+}
+
 fn find_artifact(task_dir: &Path, runtime: &TaskRuntime) -> anyhow::Result<PathBuf> {
     match runtime {
         TaskRuntime::Native => find_library(task_dir),
@@ -337,6 +412,11 @@ fn build_workflows(workflows_dir: &Path, out_dir: &Path) -> anyhow::Result<()> {
         .filter(|e| e.file_type().is_file())
         .filter(|e| e.file_name().to_string_lossy().ends_with(".workflow.json"))
     {
+        let raw = fs::read_to_string(entry.path())?;
+        let req: WorkflowUploadRequest = serde_json::from_str(&raw)?;
+        let workflow: namu_core::ir::Workflow = serde_json::from_value(req.ir)?;
+        validate_workflow(&workflow)?;
+
         let out_path = out_dir.join(entry.file_name());
         fs::copy(entry.path(), &out_path)?;
         println!("Copied workflow {}", out_path.display());
@@ -361,6 +441,7 @@ fn build_workflows_from_config(cfg: &config::NamuConfig, out_dir: &Path) -> anyh
 
         let raw = fs::read_to_string(&path)?;
         let workflow: namu_core::ir::Workflow = serde_json::from_str(&raw)?;
+        validate_workflow(&workflow)?;
         let id = workflow.name.clone();
 
         let wf_cfg = cfg
@@ -391,6 +472,17 @@ fn build_workflows_from_config(cfg: &config::NamuConfig, out_dir: &Path) -> anyh
     Ok(())
 }
 
+fn validate_workflow(workflow: &namu_core::ir::Workflow) -> anyhow::Result<()> {
+    workflow.validate().map_err(|errors| {
+        let details = errors
+            .iter()
+            .map(|err| format!("  - {err}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        anyhow::anyhow!("workflow {} is invalid:\n{details}", workflow.name)
+    })
+}
+
 fn task_versions_for_workflow(
     cfg: &config::NamuConfig,
     workflow: &namu_core::ir::Workflow,
@@ -486,11 +578,17 @@ async fn publish(out_dir: &Path) {
                     println!("Published workflow: {}", path.display());
                 }
                 Ok(resp) => {
-                    eprintln!(
-                        "Failed to publish workflow {}: {}",
-                        path.display(),
-                        resp.status()
-                    );
+                    let status = resp.status();
+                    let body = resp.json::<JsonValue>().await.unwrap_or_default();
+                    eprintln!("Failed to publish workflow {}: {}", path.display(), status);
+                    if let Some(errors) = body.get("errors").and_then(|e| e.as_array()) {
+                        for err in errors {
+                            match serde_json::from_value::<ValidationError>(err.clone()) {
+                                Ok(err) => eprintln!("  - {err}"),
+                                Err(_) => eprintln!("  - {err}"),
+                            }
+                        }
+                    }
                 }
                 Err(err) => {
                     eprintln!("Failed to publish workflow {}: {}", path.display(), err);
@@ -500,7 +598,7 @@ async fn publish(out_dir: &Path) {
     }
 }
 
-async fn run_workflow(workflow_id: &str, version: &str) {
+async fn run_workflow(workflow_id: &str, version: &str, inputs: &[String]) {
     let master_url = match get_master_url() {
         Ok(url) => url,
         Err(e) => {
@@ -508,11 +606,19 @@ async fn run_workflow(workflow_id: &str, version: &str) {
             std::process::exit(1);
         }
     };
+    let inputs = match parse_inputs(inputs) {
+        Ok(inputs) => inputs,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
 
     let client = reqwest::Client::new();
     let req = RunCreateRequest {
         workflow_id: workflow_id.to_string(),
         version: version.to_string(),
+        inputs,
     };
     let url = format!("{}/runs", master_url.trim_end_matches('/'));
     match client.post(url).json(&req).send().await {
@@ -529,6 +635,45 @@ async fn run_workflow(workflow_id: &str, version: &str) {
     }
 }
 
+/// Run a workflow from `out_dir` in-process and print its result; exits
+/// with an error unless every call succeeded.
+async fn run_local(out_dir: &Path, workflow_id: &str, version: &str, inputs: &[String]) {
+    let result = async {
+        let inputs = parse_inputs(inputs)?;
+        let runner = LocalRunner::load(out_dir, &out_dir.join(".local"))?;
+        runner.run(workflow_id, version, &inputs).await
+    }
+    .await;
+    match result {
+        Ok(result) => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&result).unwrap_or_default()
+            );
+            if result.status != "succeeded" {
+                std::process::exit(1);
+            }
+        }
+        Err(err) => {
+            eprintln!("Local run failed: {err:#}");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn parse_inputs(raw: &[String]) -> anyhow::Result<serde_json::Map<String, JsonValue>> {
+    let mut inputs = serde_json::Map::new();
+    for item in raw {
+        let (name, value) = item
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("invalid input '{item}', expected NAME=VALUE"))?;
+        let value =
+            serde_json::from_str(value).unwrap_or_else(|_| JsonValue::String(value.to_string()));
+        inputs.insert(name.to_string(), value);
+    }
+    Ok(inputs)
+}
+
 async fn run_status(run_id: &str) {
     let master_url = match get_master_url() {
         Ok(url) => url,
@@ -556,6 +701,71 @@ async fn run_status(run_id: &str) {
     }
 }
 
+async fn run_result(run_id: &str) {
+    let master_url = match get_master_url() {
+        Ok(url) => url,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let client = reqwest::Client::new();
+    let url = format!(
+        "{}/runs/{}/result",
+        master_url.trim_end_matches('/'),
+        run_id
+    );
+    match client.get(url).send().await {
+        Ok(resp) if resp.status().is_success() => {
+            let json: JsonValue = resp.json().await.unwrap_or_default();
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&json).unwrap_or_default()
+            );
+        }
+        Ok(resp) => {
+            eprintln!("Failed to fetch result: {}", resp.status());
+        }
+        Err(err) => {
+            eprintln!("Failed to fetch result: {}", err);
+        }
+    }
+}
+
+async fn cancel_run(run_id: &str) {
+    let master_url = match get_master_url() {
+        Ok(url) => url,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let client = reqwest::Client::new();
+    let url = format!(
+        "{}/runs/{}/cancel",
+        master_url.trim_end_matches('/'),
+        run_id
+    );
+    match client.post(url).send().await {
+        Ok(resp) if resp.status().is_success() => {
+            let json: JsonValue = resp.json().await.unwrap_or_default();
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&json).unwrap_or_default()
+            );
+        }
+        Ok(resp) if resp.status() == reqwest::StatusCode::CONFLICT => {
+            eprintln!("Run {run_id} has already finished");
+        }
+        Ok(resp) => {
+            eprintln!("Failed to cancel run: {}", resp.status());
+        }
+        Err(err) => {
+            eprintln!("Failed to cancel run: {}", err);
+        }
+    }
+}
+
 async fn run_logs(run_id: &str, limit: usize) {
     let master_url = match get_master_url() {
         Ok(url) => url,
@@ -588,6 +798,80 @@ async fn run_logs(run_id: &str, limit: usize) {
     }
 }
 
+/// Print run events one JSON object per line until the run ends,
+/// reconnecting from the last event seen if the stream drops.
+async fn follow_logs(run_id: &str) {
+    let master_url = match get_master_url() {
+        Ok(url) => url,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let client = reqwest::Client::new();
+    let url = format!(
+        "{}/runs/{}/events/stream",
+        master_url.trim_end_matches('/'),
+        run_id
+    );
+    let mut last_id: Option<String> = None;
+    loop {
+        let mut req = client.get(&url);
+        if let Some(id) = &last_id {
+            req = req.header("Last-Event-ID", id);
+        }
+        let mut resp = match req.send().await {
+            Ok(resp) if resp.status().is_success() => resp,
+            Ok(resp) => {
+                eprintln!("Failed to follow logs: {}", resp.status());
+                return;
+            }
+            Err(err) => {
+                eprintln!("Failed to follow logs: {}", err);
+                return;
+            }
+        };
+
+        let mut buffer: Vec<u8> = Vec::new();
+        loop {
+            match resp.chunk().await {
+                Ok(Some(chunk)) => {
+                    buffer.extend_from_slice(&chunk);
+                    while let Some(end) = buffer.windows(2).position(|w| w == b"\n\n") {
+                        let frame = buffer.drain(..end + 2).collect::<Vec<_>>();
+                        if let Some(id) = print_sse_frame(&String::from_utf8_lossy(&frame)) {
+                            last_id = Some(id);
+                        }
+                    }
+                }
+                Ok(None) => return,
+                Err(err) => {
+                    eprintln!("Event stream interrupted, reconnecting: {}", err);
+                    break;
+                }
+            }
+        }
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+/// Print the data of one server-sent event and return its id.
+fn print_sse_frame(frame: &str) -> Option<String> {
+    let mut id = None;
+    let mut data = Vec::new();
+    for line in frame.lines() {
+        if let Some(value) = line.strip_prefix("id:") {
+            id = Some(value.trim_start().to_string());
+        } else if let Some(value) = line.strip_prefix("data:") {
+            data.push(value.strip_prefix(' ').unwrap_or(value));
+        }
+    }
+    if !data.is_empty() {
+        println!("{}", data.join("\n"));
+    }
+    id
+}
+
 async fn list_workers() {
     let master_url = match get_master_url() {
         Ok(url) => url,
@@ -0,0 +1,143 @@
+//! `namu build` and `namu run --local` on the fixtures in `tests/e2e`.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use namu_proto::RunResultResponse;
+use namu_worker::artifact;
+use namu_worker::batcher::Batcher;
+use namu_worker::executor::WorkerExecutor;
+use serde_json::json;
+
+/// A scratch directory removed when dropped.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("namu-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn e2e_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../../../tests/e2e")
+}
+
+fn namu(cwd: &Path, args: &[&str]) -> std::process::Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_namu"))
+        .current_dir(cwd)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "namu {args:?} failed\nstdout:\n{}\nstderr:\n{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr),
+    );
+    output
+}
+
+#[test]
+fn local_run_calls_the_built_native_task() {
+    let e2e = e2e_dir();
+    let tasks_dir = e2e.join("tasks");
+    let workflows_dir = e2e.join("workflows");
+    let dir = TempDir::new("local-run");
+    let out_dir = dir.0.join("dist");
+    let out_dir = out_dir.to_str().unwrap();
+
+    namu(
+        &dir.0,
+        &[
+            "build",
+            "--tasks-dir",
+            tasks_dir.to_str().unwrap(),
+            "--workflows-dir",
+            workflows_dir.to_str().unwrap(),
+            "--out-dir",
+            out_dir,
+        ],
+    );
+    let output = namu(
+        &dir.0,
+        &[
+            "run",
+            "add_workflow",
+            "0.1.0",
+            "--local",
+            "--out-dir",
+            out_dir,
+        ],
+    );
+
+    let result: RunResultResponse = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(result.status, "succeeded");
+    assert!(result.failures.is_empty());
+    let values = result
+        .results
+        .iter()
+        .map(|leaf| leaf.value.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(values, vec![json!(3)]);
+}
+
+#[test]
+fn declared_batch_size_caps_worker_batches() {
+    let e2e = e2e_dir();
+    let tasks_dir = e2e.join("tasks/batch_len");
+    let workflows_dir = e2e.join("workflows");
+    let dir = TempDir::new("batch-policy");
+    let out_dir = dir.0.join("dist");
+
+    namu(
+        &dir.0,
+        &[
+            "build",
+            "--tasks-dir",
+            tasks_dir.to_str().unwrap(),
+            "--workflows-dir",
+            workflows_dir.to_str().unwrap(),
+            "--out-dir",
+            out_dir.to_str().unwrap(),
+        ],
+    );
+
+    let archive = std::fs::read(out_dir.join("tasks/batch_len-0.1.0.tar.zst")).unwrap();
+    let manifest = artifact::read_manifest(&archive).unwrap();
+    // `batch_size` comes from the task; `manifest.json` overrides `max_wait_ms`.
+    assert_eq!(manifest.batch.max_size, 2);
+    assert_eq!(manifest.batch.linger_ms, 200);
+
+    let artifact_path = artifact::extract_artifact(&archive, &dir.0, &manifest.runtime).unwrap();
+    let executor = WorkerExecutor::new(1, Duration::from_secs(60)).unwrap();
+    let batcher = Batcher::new();
+    let call = |input: i64| {
+        batcher.call("batch_len@0.1.0", &manifest.batch, json!(input), |inputs| {
+            executor.execute_batch(&manifest, &artifact_path, inputs)
+        })
+    };
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let results = runtime.block_on(async {
+        let (a, b, c, d, e) = tokio::join!(call(1), call(2), call(3), call(4), call(5));
+        [a, b, c, d, e]
+    });
+
+    let sizes = results
+        .into_iter()
+        .map(|result| result.unwrap().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        sizes,
+        vec![json!(2), json!(2), json!(2), json!(2), json!(1)]
+    );
+}
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
 use namu_core::ir::Workflow;
-use namu_proto::TaskManifest;
+use namu_proto::{TaskError, TaskManifest};
 use serde_json::Value as JsonValue;
 use sqlx_core::row::Row;
 use sqlx_postgres::{PgPool, Postgres};
@@ -55,6 +55,8 @@ CREATE TABLE IF NOT EXISTS contexts (
   PRIMARY KEY (run_id, id)
 );
 
+ALTER TABLE contexts ADD COLUMN IF NOT EXISTS result_json JSONB;
+
 CREATE TABLE IF NOT EXISTS run_nodes (
   id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
   run_id UUID NOT NULL,
@@ -226,6 +228,38 @@ pub async fn set_run_status(pool: &PgPool, run_id: Uuid, status: &str) -> anyhow
     Ok(())
 }
 
+/// Mark a queued or running run cancelled, along with its active contexts
+/// and its unfinished nodes. Returns `false` when the run already ended.
+pub async fn cancel_run(pool: &PgPool, run_id: Uuid) -> anyhow::Result<bool> {
+    let updated = sqlx_core::query::query::<Postgres>(
+        "UPDATE runs SET status = 'cancelled', updated_at = now() WHERE id = $1 AND status IN ('queued', 'running')",
+    )
+    .bind(run_id)
+    .execute(pool)
+    .await?;
+    if updated.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    sqlx_core::query::query::<Postgres>(
+        "UPDATE contexts SET status = 'cancelled' WHERE run_id = $1 AND status = 'active'",
+    )
+    .bind(run_id)
+    .execute(pool)
+    .await?;
+    sqlx_core::query::query::<Postgres>(
+        r#"
+        UPDATE run_nodes
+        SET status = 'cancelled', lease_expires_at = NULL, updated_at = now()
+        WHERE run_id = $1 AND status IN ('queued', 'running', 'retrying')
+        "#,
+    )
+    .bind(run_id)
+    .execute(pool)
+    .await?;
+    Ok(true)
+}
+
 pub async fn upsert_run_node(
     pool: &PgPool,
     run_id: Uuid,
@@ -252,19 +286,57 @@ pub async fn upsert_run_node(
     Ok(())
 }
 
-pub async fn update_run_node(
+/// Take the lease on a node for `attempt`; returns `false`, changing
+/// nothing, when the node settled or belongs to another attempt.
+pub async fn start_node(
+    pool: &PgPool,
+    run_id: Uuid,
+    op_id: usize,
+    ctx_id: usize,
+    attempt: u32,
+    lease_expires_at: chrono::DateTime<chrono::Utc>,
+) -> anyhow::Result<bool> {
+    let updated = sqlx_core::query::query::<Postgres>(
+        r#"
+        UPDATE run_nodes
+        SET status = 'running', lease_expires_at = $1, updated_at = now()
+        WHERE run_id = $2 AND op_id = $3 AND ctx_id = $4
+          AND status IN ('queued', 'running') AND retries = $5
+        "#,
+    )
+    .bind(lease_expires_at)
+    .bind(run_id)
+    .bind(op_id as i32)
+    .bind(ctx_id as i32)
+    .bind(attempt as i32 - 1)
+    .execute(pool)
+    .await?;
+    Ok(updated.rows_affected() == 1)
+}
+
+/// Settle the node owned by `attempt` as `status`; returns `false`, changing
+/// nothing, when it already settled or belongs to another attempt, so a late
+/// or repeated report of a call is applied at most once.
+///
+/// A node still `retrying` is owned by the retry waiting to be enqueued, which
+/// fails here when it cannot be.
+pub async fn settle_node(
     pool: &PgPool,
     run_id: Uuid,
     op_id: usize,
     ctx_id: usize,
+    attempt: u32,
     status: &str,
-    error: Option<&str>,
-) -> anyhow::Result<()> {
-    sqlx_core::query::query::<Postgres>(
+    error: Option<&TaskError>,
+) -> anyhow::Result<bool> {
+    let error = error.map(serde_json::to_string).transpose()?;
+    let settled = sqlx_core::query::query::<Postgres>(
         r#"
         UPDATE run_nodes
-        SET status = $1, last_error = $2, updated_at = now()
+        SET status = $1, last_error = $2, lease_expires_at = NULL, updated_at = now()
         WHERE run_id = $3 AND op_id = $4 AND ctx_id = $5
+          AND status IN ('queued', 'running', 'retrying') AND retries = $6
+        RETURNING op_id
         "#,
     )
     .bind(status)
@@ -272,9 +344,72 @@ pub async fn update_run_node(
     .bind(run_id)
     .bind(op_id as i32)
     .bind(ctx_id as i32)
-    .execute(pool)
+    .bind(attempt as i32 - 1)
+    .fetch_optional(pool)
     .await?;
-    Ok(())
+    Ok(settled.is_some())
+}
+
+/// Cancel the unfinished nodes of `op_ids` in `ctx_id`, returning the op id
+/// and `retries` of each one cancelled.
+pub async fn cancel_nodes(
+    pool: &PgPool,
+    run_id: Uuid,
+    ctx_id: usize,
+    op_ids: &[usize],
+) -> anyhow::Result<Vec<(usize, u32)>> {
+    let op_ids = op_ids.iter().map(|&op_id| op_id as i32).collect::<Vec<_>>();
+    let rows = sqlx_core::query::query::<Postgres>(
+        r#"
+        UPDATE run_nodes
+        SET status = 'cancelled', lease_expires_at = NULL, updated_at = now()
+        WHERE run_id = $1 AND ctx_id = $2 AND op_id = ANY($3)
+          AND status IN ('queued', 'running', 'retrying')
+        RETURNING op_id, retries
+        "#,
+    )
+    .bind(run_id)
+    .bind(ctx_id as i32)
+    .bind(op_ids)
+    .fetch_all(pool)
+    .await?;
+    let mut out = Vec::with_capacity(rows.len());
+    for row in rows {
+        let op_id: i32 = row.try_get("op_id")?;
+        let retries: i32 = row.try_get("retries")?;
+        out.push((op_id as usize, retries as u32));
+    }
+    Ok(out)
+}
+
+/// Park the node owned by the failed `attempt` until its retry is enqueued,
+/// counting the retry in `retries`; returns `false` like [`settle_node`].
+pub async fn schedule_node_retry(
+    pool: &PgPool,
+    run_id: Uuid,
+    op_id: usize,
+    ctx_id: usize,
+    attempt: u32,
+    error: &TaskError,
+) -> anyhow::Result<bool> {
+    let error = serde_json::to_string(error)?;
+    let scheduled = sqlx_core::query::query::<Postgres>(
+        r#"
+        UPDATE run_nodes
+        SET status = 'retrying', retries = $1, last_error = $2, lease_expires_at = NULL, updated_at = now()
+        WHERE run_id = $3 AND op_id = $4 AND ctx_id = $5
+          AND status IN ('queued', 'running', 'retrying') AND retries = $1 - 1
+        RETURNING op_id
+        "#,
+    )
+    .bind(attempt as i32)
+    .bind(error)
+    .bind(run_id)
+    .bind(op_id as i32)
+    .bind(ctx_id as i32)
+    .fetch_optional(pool)
+    .await?;
+    Ok(scheduled.is_some())
 }
 
 pub async fn create_context(
@@ -309,6 +444,64 @@ pub async fn finish_context(pool: &PgPool, run_id: Uuid, ctx_id: usize) -> anyho
     Ok(())
 }
 
+/// Finish a leaf context that reached a `Return`, keeping the returned value.
+pub async fn finish_context_with_result(
+    pool: &PgPool,
+    run_id: Uuid,
+    ctx_id: usize,
+    result: &JsonValue,
+) -> anyhow::Result<()> {
+    sqlx_core::query::query::<Postgres>(
+        "UPDATE contexts SET status = 'done', result_json = $1 WHERE run_id = $2 AND id = $3",
+    )
+    .bind(result)
+    .bind(run_id)
+    .bind(ctx_id as i32)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Values returned by the leaf contexts of a run, by context id.
+pub async fn run_results(pool: &PgPool, run_id: Uuid) -> anyhow::Result<Vec<(usize, JsonValue)>> {
+    let rows = sqlx_core::query::query::<Postgres>(
+        "SELECT id, result_json FROM contexts WHERE run_id = $1 AND result_json IS NOT NULL ORDER BY id",
+    )
+    .bind(run_id)
+    .fetch_all(pool)
+    .await?;
+    let mut out = Vec::with_capacity(rows.len());
+    for row in rows {
+        let id: i32 = row.try_get("id")?;
+        let result: JsonValue = row.try_get("result_json")?;
+        out.push((id as usize, result));
+    }
+    Ok(out)
+}
+
+/// Failed nodes of a run as `(ctx_id, op_id, last_error)`, by context id.
+pub async fn failed_nodes(
+    pool: &PgPool,
+    run_id: Uuid,
+) -> anyhow::Result<Vec<(usize, usize, Option<TaskError>)>> {
+    let rows = sqlx_core::query::query::<Postgres>(
+        "SELECT ctx_id, op_id, last_error FROM run_nodes WHERE run_id = $1 AND status = 'failed' ORDER BY ctx_id, op_id",
+    )
+    .bind(run_id)
+    .fetch_all(pool)
+    .await?;
+    let mut out = Vec::with_capacity(rows.len());
+    for row in rows {
+        let ctx_id: i32 = row.try_get("ctx_id")?;
+        let op_id: i32 = row.try_get("op_id")?;
+        let last_error: Option<String> = row.try_get("last_error")?;
+        // Rows written before errors were structured hold plain messages.
+        let last_error = last_error.as_deref().map(TaskError::parse);
+        out.push((ctx_id as usize, op_id as usize, last_error));
+    }
+    Ok(out)
+}
+
 pub async fn run_progress(pool: &PgPool, run_id: Uuid) -> anyhow::Result<(usize, usize)> {
     let row = sqlx_core::query::query::<Postgres>(
         "SELECT COUNT(*) FILTER (WHERE status = 'succeeded') AS done, COUNT(*) AS total FROM run_nodes WHERE run_id = $1",
@@ -427,9 +620,65 @@ pub async fn register_worker(
     Ok(())
 }
 
-pub async fn expired_leases(pool: &PgPool) -> anyhow::Result<Vec<(Uuid, i32, i32)>> {
+/// Running nodes whose lease ran out, with the attempt that held the lease.
+pub async fn expired_leases(pool: &PgPool) -> anyhow::Result<Vec<(Uuid, i32, i32, u32)>> {
+    let rows = sqlx_core::query::query::<Postgres>(
+        "SELECT run_id, op_id, ctx_id, retries FROM run_nodes WHERE status = 'running' AND lease_expires_at IS NOT NULL AND lease_expires_at < now()",
+    )
+    .fetch_all(pool)
+    .await?;
+    let mut out = Vec::with_capacity(rows.len());
+    for row in rows {
+        let run_id: Uuid = row.try_get("run_id")?;
+        let op_id: i32 = row.try_get("op_id")?;
+        let ctx_id: i32 = row.try_get("ctx_id")?;
+        let retries: i32 = row.try_get("retries")?;
+        out.push((run_id, op_id, ctx_id, retries as u32 + 1));
+    }
+    Ok(out)
+}
+
+/// Runs still executing, as `(run_id, workflow_id, workflow_version)`.
+pub async fn running_runs(pool: &PgPool) -> anyhow::Result<Vec<(Uuid, String, String)>> {
+    let rows = sqlx_core::query::query::<Postgres>(
+        "SELECT id, workflow_id, workflow_version FROM runs WHERE status = 'running'",
+    )
+    .fetch_all(pool)
+    .await?;
+    let mut out = Vec::with_capacity(rows.len());
+    for row in rows {
+        let id: Uuid = row.try_get("id")?;
+        let workflow_id: String = row.try_get("workflow_id")?;
+        let workflow_version: String = row.try_get("workflow_version")?;
+        out.push((id, workflow_id, workflow_version));
+    }
+    Ok(out)
+}
+
+/// Highest context id allocated in a run.
+pub async fn max_context_id(pool: &PgPool, run_id: Uuid) -> anyhow::Result<Option<usize>> {
+    let row = sqlx_core::query::query::<Postgres>(
+        "SELECT MAX(id) AS max_id FROM contexts WHERE run_id = $1",
+    )
+    .bind(run_id)
+    .fetch_one(pool)
+    .await?;
+    let max_id: Option<i32> = row.try_get("max_id")?;
+    Ok(max_id.map(|id| id as usize))
+}
+
+/// Queued and retrying nodes of running runs, as
+/// `(run_id, op_id, ctx_id, status, attempt)`.
+pub async fn waiting_nodes(
+    pool: &PgPool,
+) -> anyhow::Result<Vec<(Uuid, usize, usize, String, u32)>> {
     let rows = sqlx_core::query::query::<Postgres>(
-        "SELECT run_id, op_id, ctx_id FROM run_nodes WHERE status = 'running' AND lease_expires_at IS NOT NULL AND lease_expires_at < now()",
+        r#"
+        SELECT n.run_id, n.op_id, n.ctx_id, n.status, n.retries
+        FROM run_nodes n
+        JOIN runs r ON r.id = n.run_id
+        WHERE r.status = 'running' AND n.status IN ('queued', 'retrying')
+        "#,
     )
     .fetch_all(pool)
     .await?;
@@ -438,7 +687,15 @@ pub async fn expired_leases(pool: &PgPool) -> anyhow::Result<Vec<(Uuid, i32, i32
         let run_id: Uuid = row.try_get("run_id")?;
         let op_id: i32 = row.try_get("op_id")?;
         let ctx_id: i32 = row.try_get("ctx_id")?;
-        out.push((run_id, op_id, ctx_id));
+        let status: String = row.try_get("status")?;
+        let retries: i32 = row.try_get("retries")?;
+        out.push((
+            run_id,
+            op_id as usize,
+            ctx_id as usize,
+            status,
+            retries as u32 + 1,
+        ));
     }
     Ok(out)
 }
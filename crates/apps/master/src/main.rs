@@ -5,6 +5,8 @@ use std::time::Duration;
 
 use axum::Router;
 use axum::routing::{get, post};
+use namu_engine::runtime::replay::RecordDir;
+use namu_engine::traits::observer::RunObserver;
 use redis::aio::ConnectionManager;
 use sqlx_postgres::PgPool;
 use tokio::sync::RwLock;
@@ -14,14 +16,17 @@ use uuid::Uuid;
 
 mod db;
 mod object_store;
+mod observer;
 mod planner;
+mod recovery;
 mod redis_store;
 mod routes;
 mod storage;
 
 use crate::routes::{
-    create_run, get_artifact, get_run_status, get_run_values, get_task_manifest, get_workers,
-    register_worker, run_events, start_node, submit_task, upload_tasks, upload_workflows,
+    cancel_run, create_run, get_artifact, get_run_result, get_run_status, get_run_values,
+    get_task_manifest, get_workers, register_worker, run_events, start_node, stream_run_events,
+    submit_task, upload_tasks, upload_workflows,
 };
 
 #[derive(Clone)]
@@ -35,10 +40,26 @@ pub struct RunState {
 pub struct AppState {
     pub db: PgPool,
     pub redis: ConnectionManager,
+    /// For connections that block, such as event streams tailing Redis.
+    pub redis_client: redis::Client,
     pub artifacts_dir: PathBuf,
     pub runs: Arc<RwLock<HashMap<Uuid, RunState>>>,
     pub inline_input_limit: usize,
     pub object_store: Option<object_store::ObjectStore>,
+    /// How long a finished run's state stays in Redis.
+    pub run_retention: Duration,
+    pub observers: Arc<[Arc<dyn RunObserver<Uuid>>]>,
+    /// Where the task outputs of runs are recorded for replay, if anywhere.
+    pub recordings: Option<Arc<RecordDir>>,
+}
+
+impl AppState {
+    /// Notify every observer of a run's progress.
+    pub fn observe(&self, notify: impl Fn(&dyn RunObserver<Uuid>)) {
+        for observer in self.observers.iter() {
+            notify(observer.as_ref());
+        }
+    }
 }
 
 #[tokio::main]
@@ -55,25 +76,39 @@ async fn main() -> anyhow::Result<()> {
         .ok()
         .and_then(|v| v.parse::<usize>().ok())
         .unwrap_or(262_144);
+    let run_retention = std::env::var("NAMU_RUN_RETENTION_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(86_400);
+    let recordings = std::env::var("NAMU_RECORD_DIR")
+        .ok()
+        .map(|dir| Arc::new(RecordDir::new(dir)));
     let object_store = object_store::ObjectStore::from_env().await?;
 
     let db = PgPool::connect(&database_url).await?;
     db::init_db(&db).await?;
 
     let redis_client = redis::Client::open(redis_url)?;
-    let redis = ConnectionManager::new(redis_client).await?;
+    let redis = ConnectionManager::new(redis_client.clone()).await?;
 
     let state = AppState {
         db,
         redis,
+        redis_client,
         artifacts_dir: PathBuf::from(artifacts_dir),
         runs: Arc::new(RwLock::new(HashMap::new())),
         inline_input_limit,
         object_store,
+        run_retention: Duration::from_secs(run_retention),
+        observers: Arc::new([Arc::new(observer::LogObserver) as Arc<dyn RunObserver<Uuid>>]),
+        recordings,
     };
 
+    recovery::recover(&state).await?;
+
     let lease_state = state.clone();
     tokio::spawn(lease_monitor_task(lease_state));
+    tokio::spawn(retry_monitor_task(state.clone()));
 
     let app = Router::new()
         .route("/healthz", get(routes::healthz))
@@ -83,8 +118,11 @@ async fn main() -> anyhow::Result<()> {
         .route("/workflows", post(upload_workflows))
         .route("/runs", post(create_run))
         .route("/runs/{run_id}", get(get_run_status))
+        .route("/runs/{run_id}/result", get(get_run_result))
+        .route("/runs/{run_id}/cancel", post(cancel_run))
         .route("/runs/{run_id}/values", get(get_run_values))
         .route("/runs/{run_id}/events", get(run_events))
+        .route("/runs/{run_id}/events/stream", get(stream_run_events))
         .route("/runs/{run_id}/nodes/start", post(start_node))
         .route("/runs/{run_id}/nodes/complete", post(submit_task))
         .route("/workers", get(get_workers))
@@ -109,3 +147,13 @@ async fn lease_monitor_task(state: AppState) {
         }
     }
 }
+
+async fn retry_monitor_task(state: AppState) {
+    let mut interval = tokio::time::interval(Duration::from_secs(1));
+    loop {
+        interval.tick().await;
+        if let Err(err) = planner::enqueue_due_retries(&state).await {
+            tracing::error!("retry monitor error: {err}");
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use namu_core::{ContextId, OpId, TaskError};
+use namu_engine::traits::observer::{RunObserver, RunOutcome};
+use tracing::debug;
+use uuid::Uuid;
+
+/// Logs the progress of runs at debug level.
+pub struct LogObserver;
+
+impl RunObserver<Uuid> for LogObserver {
+    fn on_dispatch(&self, run_id: Uuid, op_id: OpId, ctx_id: ContextId, task_id: &str) {
+        debug!(%run_id, op_id, ctx_id, task_id, "call dispatched");
+    }
+
+    fn on_output(&self, run_id: Uuid, op_id: OpId, ctx_id: ContextId, task_id: &str) {
+        debug!(%run_id, op_id, ctx_id, task_id, "call output");
+    }
+
+    fn on_error(
+        &self,
+        run_id: Uuid,
+        op_id: OpId,
+        ctx_id: ContextId,
+        task_id: &str,
+        error: &TaskError,
+    ) {
+        debug!(%run_id, op_id, ctx_id, task_id, kind = %error.kind, error = %format_args!("{error:#}"), "call failed");
+    }
+
+    fn on_context_created(&self, run_id: Uuid, ctx_id: ContextId, parent: Option<ContextId>) {
+        debug!(%run_id, ctx_id, ?parent, "context created");
+    }
+
+    fn on_context_finished(&self, run_id: Uuid, ctx_id: ContextId) {
+        debug!(%run_id, ctx_id, "context finished");
+    }
+
+    fn on_run_finished(&self, run_id: Uuid, outcome: RunOutcome) {
+        debug!(%run_id, ?outcome, "run finished");
+    }
+}
@@ -1,7 +1,13 @@
+use std::collections::HashSet;
+
 use async_trait::async_trait;
+use itertools::Itertools;
+use namu_core::ir::{Call, Next};
 use namu_engine::kernel::{CallSpec, EngineKernel, JsonRuntime, KernelPlan, ValueStore};
+use namu_engine::runtime::replay::{CallRecord, CtxPath, PathStep, RecordedOutput, input_hash};
 use namu_engine::traits::engine::OrchestratorEngine;
-use namu_proto::{QueueMessage, TaskKind, TaskRuntime, TaskTrust, ValueRef};
+use namu_engine::traits::observer::RunOutcome;
+use namu_proto::{QueueMessage, TaskError, TaskKind, TaskRuntime, TaskTrust, ValueRef};
 use redis::aio::ConnectionManager;
 use serde_json::Value as JsonValue;
 use sha2::Digest;
@@ -15,30 +21,208 @@ pub async fn drive_until_call(
     ctx_id: usize,
     start_op: usize,
     pred_op: Option<usize>,
+    done: &[usize],
 ) -> anyhow::Result<()> {
     let run_state = get_run_state(state, run_id).await?;
     let workflow = run_state.workflow.clone();
     let kernel = EngineKernel::new(JsonRuntime);
     let store = RedisValueStore::new(state.redis.clone(), run_id);
 
-    match kernel
-        .drive_until_action(&workflow, &store, ctx_id, start_op, pred_op)
-        .await?
-    {
-        KernelPlan::Dispatch {
-            op_id,
-            ctx_id,
-            call,
-        } => {
-            enqueue_call(state, &run_state, run_id, ctx_id, op_id, &call).await?;
-        }
-        KernelPlan::Return { ctx_id, .. } => {
-            db::finish_context(&state.db, run_id, ctx_id).await?;
+    let mut done = done.to_vec();
+    let plans = kernel
+        .drive_until_actions(&workflow, &store, ctx_id, start_op, pred_op, &mut done)
+        .await?;
+    dispatch_plans(state, &run_state, run_id, ctx_id, plans, &done).await
+}
+
+async fn dispatch_plans(
+    state: &AppState,
+    run_state: &RunState,
+    run_id: Uuid,
+    ctx_id: usize,
+    plans: Vec<KernelPlan>,
+    done: &[usize],
+) -> anyhow::Result<()> {
+    let mut redis = state.redis.clone();
+    if plans.len() > 1 {
+        // Independent calls are queued together and joined in `apply_task_output`.
+        let op_ids = plans
+            .iter()
+            .filter_map(|plan| match plan {
+                KernelPlan::Dispatch { op_id, .. } => Some(*op_id),
+                KernelPlan::Return { .. } | KernelPlan::Collect { .. } => None,
+            })
+            .collect::<Vec<_>>();
+        let done = done.iter().chain(&op_ids).copied().collect::<Vec<_>>();
+        redis_store::create_join(&mut redis, run_id, ctx_id, &op_ids, &done).await?;
+    } else if let Some(KernelPlan::Dispatch { ctx_id, .. }) = plans.first() {
+        // Resuming after the call must not rerun calls a join already covered.
+        redis_store::set_done_ops(&mut redis, run_id, *ctx_id, done).await?;
+    }
+
+    for plan in plans {
+        match plan {
+            KernelPlan::Dispatch {
+                op_id,
+                ctx_id,
+                call,
+            } => {
+                enqueue_call(state, run_state, run_id, ctx_id, op_id, &call, 1).await?;
+            }
+            KernelPlan::Return { ctx_id, return_var } => {
+                finish_with_result(state, run_id, ctx_id, return_var).await?;
+            }
+            KernelPlan::Collect { op_id, ctx_id, var } => {
+                arrive_at_collect(state, run_state, run_id, op_id, ctx_id, var).await?;
+            }
         }
     }
     Ok(())
 }
 
+/// Hand `var` of `ctx_id` to the collect at `op_id`; the last item of the
+/// fan-out continues after it.
+async fn arrive_at_collect(
+    state: &AppState,
+    run_state: &RunState,
+    run_id: Uuid,
+    op_id: usize,
+    ctx_id: usize,
+    var: usize,
+) -> anyhow::Result<()> {
+    let mut redis = state.redis.clone();
+    let from = run_state.workflow.operations[op_id]
+        .collect
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("op {op_id} has no collect"))?
+        .from;
+    let (item_ctx, fan_ctx) = redis_store::find_fan_in(&mut redis, run_id, ctx_id, from)
+        .await?
+        .ok_or_else(|| {
+            anyhow::anyhow!("context {ctx_id} reached a collect outside the fan-out of op {from}")
+        })?;
+    let value = redis_store::get_value(&mut redis, run_id, ctx_id, var)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("missing collected value {var}"))?;
+    let pending =
+        redis_store::record_fan_in_value(&mut redis, run_id, fan_ctx, item_ctx, &value).await?;
+    if pending == 0 {
+        let values = redis_store::take_fan_in_values(&mut redis, run_id, fan_ctx).await?;
+        // Boxed: continuing after the collect drives again.
+        Box::pin(gather(state, run_id, fan_ctx, op_id, values)).await?;
+    }
+    finish_context(state, run_id, ctx_id).await
+}
+
+/// Continue after the collect at `op_id` in a new child of `fan_ctx`, with
+/// the values in context order.
+async fn gather(
+    state: &AppState,
+    run_id: Uuid,
+    fan_ctx: usize,
+    op_id: usize,
+    values: Vec<JsonValue>,
+) -> anyhow::Result<()> {
+    let run_state = get_run_state(state, run_id).await?;
+    let kernel = EngineKernel::new(JsonRuntime);
+    let store = RedisValueStore::new(state.redis.clone(), run_id);
+
+    let ctx_id = run_state
+        .next_ctx_id
+        .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    let mut redis = state.redis.clone();
+    let path = redis_store::get_context_path(&mut redis, run_id, fan_ctx).await?;
+    create_context(state, run_id, ctx_id, Some(fan_ctx), &path).await?;
+    kernel
+        .bind_collected(&run_state.workflow, &store, ctx_id, op_id, values)
+        .await?;
+
+    let next = &run_state.workflow.operations[op_id].next;
+    match kernel.resolve_next(&store, ctx_id, next).await? {
+        Some(next_op) => drive_until_call(state, run_id, ctx_id, next_op, Some(op_id), &[]).await,
+        None => finish_with_result(state, run_id, ctx_id, return_var(next)).await,
+    }
+}
+
+fn return_var(next: &Next) -> Option<usize> {
+    match next {
+        Next::Return { var } => *var,
+        _ => None,
+    }
+}
+
+/// Create `ctx_id` at `path` below `parent`, or as the root of the run.
+pub async fn create_context(
+    state: &AppState,
+    run_id: Uuid,
+    ctx_id: usize,
+    parent: Option<usize>,
+    path: &CtxPath,
+) -> anyhow::Result<()> {
+    db::create_context(&state.db, run_id, ctx_id, parent).await?;
+    let mut redis = state.redis.clone();
+    redis_store::create_context(&mut redis, run_id, ctx_id, parent, path).await?;
+    state.observe(|observer| observer.on_context_created(run_id, ctx_id, parent));
+    Ok(())
+}
+
+/// Finish a context that reached a return, recording the returned value.
+async fn finish_with_result(
+    state: &AppState,
+    run_id: Uuid,
+    ctx_id: usize,
+    return_var: Option<usize>,
+) -> anyhow::Result<()> {
+    let mut redis = state.redis.clone();
+    let result = match return_var {
+        Some(var) => redis_store::get_value(&mut redis, run_id, ctx_id, var)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("missing return value {var}"))?,
+        None => JsonValue::Null,
+    };
+    db::finish_context_with_result(&state.db, run_id, ctx_id, &result).await?;
+    redis_store::release_context(&mut redis, run_id, ctx_id).await?;
+    state.observe(|observer| observer.on_context_finished(run_id, ctx_id));
+    Ok(())
+}
+
+/// Mark `ctx_id` done; its Redis state goes once no context below it runs.
+async fn finish_context(state: &AppState, run_id: Uuid, ctx_id: usize) -> anyhow::Result<()> {
+    db::finish_context(&state.db, run_id, ctx_id).await?;
+    let mut redis = state.redis.clone();
+    redis_store::release_context(&mut redis, run_id, ctx_id).await?;
+    state.observe(|observer| observer.on_context_finished(run_id, ctx_id));
+    Ok(())
+}
+
+/// Forget a run that reached a terminal status, and expire its Redis state
+/// after the configured retention.
+pub async fn expire_run(state: &AppState, run_id: Uuid) -> anyhow::Result<()> {
+    let contexts = match state.runs.write().await.remove(&run_id) {
+        Some(run_state) => run_state
+            .next_ctx_id
+            .load(std::sync::atomic::Ordering::Relaxed),
+        None => 0,
+    };
+    let mut redis = state.redis.clone();
+    redis_store::expire_run(&mut redis, run_id, contexts, state.run_retention).await
+}
+
+pub async fn seed_inputs(
+    state: &AppState,
+    run_id: Uuid,
+    ctx_id: usize,
+    inputs: Vec<JsonValue>,
+) -> anyhow::Result<()> {
+    let run_state = get_run_state(state, run_id).await?;
+    let kernel = EngineKernel::new(JsonRuntime);
+    let store = RedisValueStore::new(state.redis.clone(), run_id);
+    kernel
+        .seed_inputs(&run_state.workflow, &store, ctx_id, inputs)
+        .await?;
+    Ok(())
+}
+
 async fn enqueue_call(
     state: &AppState,
     run_state: &RunState,
@@ -46,6 +230,7 @@ async fn enqueue_call(
     ctx_id: usize,
     op_id: usize,
     call: &CallSpec,
+    attempt: u32,
 ) -> anyhow::Result<()> {
     let task_version = run_state
         .task_versions
@@ -95,6 +280,7 @@ async fn enqueue_call(
         task_version,
         input_ids: call.inputs.clone(),
         lease_ms,
+        attempt,
         input_values,
         input_hashes: Some(input_hashes),
         input_refs,
@@ -108,13 +294,225 @@ async fn enqueue_call(
             "op_id": op_id,
             "ctx_id": ctx_id,
             "task_id": call.task_id,
+            "attempt": attempt,
         }),
     )
     .await?;
+    state.observe(|observer| observer.on_dispatch(run_id, op_id, ctx_id, &call.task_id));
 
     Ok(())
 }
 
+/// Retry a failed call according to its task's policy, or fail its context.
+///
+/// A failure of an attempt that no longer owns the node is ignored.
+pub async fn fail_call(
+    state: &AppState,
+    run_id: Uuid,
+    op_id: usize,
+    ctx_id: usize,
+    attempt: u32,
+    error: &TaskError,
+) -> anyhow::Result<()> {
+    let run_state = get_run_state(state, run_id).await?;
+    let call = run_state
+        .workflow
+        .operations
+        .get(op_id)
+        .and_then(|operation| operation.call.as_ref())
+        .ok_or_else(|| anyhow::anyhow!("op {op_id} has no call"))?;
+    let task_version = run_state
+        .task_versions
+        .get(&call.task_id)
+        .ok_or_else(|| anyhow::anyhow!("missing task version for {}", call.task_id))?;
+    let manifest = db::get_task_manifest(&state.db, &call.task_id, task_version).await?;
+    let mut redis = state.redis.clone();
+
+    if manifest.retry.should_retry(attempt, error.kind) {
+        if !db::schedule_node_retry(&state.db, run_id, op_id, ctx_id, attempt, error).await? {
+            return Ok(());
+        }
+        state.observe(|observer| observer.on_error(run_id, op_id, ctx_id, &call.task_id, error));
+        let delay = manifest.retry.backoff(attempt);
+        let due_ms = chrono::Utc::now().timestamp_millis() + delay.as_millis() as i64;
+        let retry = redis_store::PendingRetry {
+            run_id,
+            op_id,
+            ctx_id,
+            attempt: attempt + 1,
+        };
+        redis_store::schedule_retry(&mut redis, &retry, due_ms).await?;
+        redis_store::add_event(
+            &mut redis,
+            run_id,
+            &serde_json::json!({
+                "event": "retry_scheduled",
+                "op_id": op_id,
+                "ctx_id": ctx_id,
+                "attempt": attempt,
+                "kind": error.kind,
+                "error": error,
+                "delay_ms": delay.as_millis() as u64,
+            }),
+        )
+        .await?;
+        return Ok(());
+    }
+
+    if !db::settle_node(
+        &state.db,
+        run_id,
+        op_id,
+        ctx_id,
+        attempt,
+        "failed",
+        Some(error),
+    )
+    .await?
+    {
+        return Ok(());
+    }
+    state.observe(|observer| observer.on_error(run_id, op_id, ctx_id, &call.task_id, error));
+    let failed = vec![RecordedOutput::Error(error.message.clone())];
+    record_call(state, run_id, op_id, ctx_id, call, failed).await?;
+    if let Some((op_ids, _)) = redis_store::get_join(&mut redis, run_id, ctx_id).await?
+        && op_ids.contains(&op_id)
+    {
+        // The join can no longer complete: drop it with the outputs it
+        // collected and cancel the members still unfinished, whose reports
+        // are then ignored.
+        redis_store::drop_join(&mut redis, run_id, ctx_id).await?;
+        for (member, retries) in db::cancel_nodes(&state.db, run_id, ctx_id, &op_ids).await? {
+            let retry = redis_store::PendingRetry {
+                run_id,
+                op_id: member,
+                ctx_id,
+                attempt: retries + 1,
+            };
+            redis_store::drop_retry(&mut redis, &retry).await?;
+        }
+    }
+    finish_context(state, run_id, ctx_id).await?;
+    redis_store::add_event(
+        &mut redis,
+        run_id,
+        &serde_json::json!({
+            "event": "failed",
+            "op_id": op_id,
+            "ctx_id": ctx_id,
+            "attempt": attempt,
+            "kind": error.kind,
+            "error": error,
+        }),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Enqueue every retry whose backoff has elapsed.
+///
+/// A retry that cannot be enqueued counts as another failed attempt.
+pub async fn enqueue_due_retries(state: &AppState) -> anyhow::Result<()> {
+    let mut redis = state.redis.clone();
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    for retry in redis_store::take_due_retries(&mut redis, now_ms).await? {
+        if let Err(err) = requeue_call(
+            state,
+            retry.run_id,
+            retry.op_id,
+            retry.ctx_id,
+            retry.attempt,
+        )
+        .await
+        {
+            tracing::error!(
+                "retry of op {} in ctx {} of run {} failed: {err}",
+                retry.op_id,
+                retry.ctx_id,
+                retry.run_id
+            );
+            if let Err(err) = fail_call(
+                state,
+                retry.run_id,
+                retry.op_id,
+                retry.ctx_id,
+                retry.attempt,
+                &TaskError::retryable(format!("retry could not be enqueued: {err}")),
+            )
+            .await
+            {
+                tracing::error!("failed to record retry failure: {err}");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Queue the call of `op_id` in `ctx_id` again as `attempt`.
+pub async fn requeue_call(
+    state: &AppState,
+    run_id: Uuid,
+    op_id: usize,
+    ctx_id: usize,
+    attempt: u32,
+) -> anyhow::Result<()> {
+    let run_state = get_run_state(state, run_id).await?;
+    let call = run_state
+        .workflow
+        .operations
+        .get(op_id)
+        .and_then(|operation| operation.call.as_ref())
+        .ok_or_else(|| anyhow::anyhow!("op {op_id} has no call"))?;
+    let call = CallSpec {
+        task_id: call.task_id.clone(),
+        inputs: call.inputs.clone(),
+        outputs: call.outputs.clone(),
+    };
+    enqueue_call(state, &run_state, run_id, ctx_id, op_id, &call, attempt).await
+}
+
+/// Cancel a run: mark it and its unfinished work cancelled, then drop its
+/// scheduled retries and the messages still waiting in its queues.
+///
+/// Returns `false` when the run already ended. Workers poll the run's status
+/// while a call runs: a wasm call is stopped, a native call runs to
+/// completion, and neither result is reported.
+pub async fn cancel_run(state: &AppState, run_id: Uuid) -> anyhow::Result<bool> {
+    if !db::cancel_run(&state.db, run_id).await? {
+        return Ok(false);
+    }
+
+    let mut redis = state.redis.clone();
+    let dropped_retries = redis_store::drop_retries(&mut redis, run_id).await?;
+    let mut dropped_messages = 0;
+    if let Ok(run_state) = get_run_state(state, run_id).await {
+        let mut streams = HashSet::new();
+        for (task_id, task_version) in &run_state.task_versions {
+            let manifest = db::get_task_manifest(&state.db, task_id, task_version).await?;
+            streams.insert((pool_for_manifest(&manifest), manifest.resource_class));
+        }
+        for (pool, resource_class) in streams {
+            dropped_messages +=
+                redis_store::drop_queued_messages(&mut redis, &pool, &resource_class, run_id)
+                    .await?;
+        }
+    }
+
+    redis_store::add_event(
+        &mut redis,
+        run_id,
+        &serde_json::json!({
+            "event": "cancelled",
+            "dropped_messages": dropped_messages,
+            "dropped_retries": dropped_retries,
+        }),
+    )
+    .await?;
+    state.observe(|observer| observer.on_run_finished(run_id, RunOutcome::Cancelled));
+    expire_run(state, run_id).await?;
+    Ok(true)
+}
+
 async fn resolve_inputs_for_message(
     redis: &mut ConnectionManager,
     run_id: Uuid,
@@ -175,7 +573,7 @@ async fn resolve_inputs_for_message(
     Ok((inline_values, input_hashes, input_refs))
 }
 
-fn pool_for_manifest(manifest: &namu_proto::TaskManifest) -> String {
+pub fn pool_for_manifest(manifest: &namu_proto::TaskManifest) -> String {
     if manifest.requires_gpu {
         return "gpu".to_string();
     }
@@ -228,39 +626,88 @@ pub async fn apply_task_output(
         .ok_or_else(|| anyhow::anyhow!("missing task version for {}", call.task_id))?
         .to_string();
     let manifest = db::get_task_manifest(&state.db, &call.task_id, &task_version).await?;
+    let outputs = match manifest.task_kind {
+        TaskKind::Stream => output_json.as_array().map_or(0, Vec::len),
+        _ => 1,
+    };
+    for _ in 0..outputs {
+        state.observe(|observer| observer.on_output(run_id, op_id, ctx_id, &call.task_id));
+    }
+    if state.recordings.is_some() {
+        let outputs = match manifest.task_kind {
+            TaskKind::Stream => output_json
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|item| RecordedOutput::from_json(&call.outputs, item))
+                .collect::<anyhow::Result<Vec<_>>>()?,
+            _ => vec![RecordedOutput::from_json(&call.outputs, &output_json)?],
+        };
+        record_call(state, run_id, op_id, ctx_id, call, outputs).await?;
+    }
 
     let mut redis = state.redis.clone();
     let kernel = EngineKernel::new(JsonRuntime);
     let store = RedisValueStore::new(state.redis.clone(), run_id);
 
+    if let Some((op_ids, done)) = redis_store::get_join(&mut redis, run_id, ctx_id).await?
+        && op_ids.contains(&op_id)
+    {
+        let pending =
+            redis_store::record_join_output(&mut redis, run_id, ctx_id, op_id, &output_json)
+                .await?;
+        if pending == 0 {
+            join_outputs(state, &run_state, run_id, ctx_id, &op_ids, &done).await?;
+        }
+        return Ok(());
+    }
+    let done = redis_store::get_done_ops(&mut redis, run_id, ctx_id).await?;
+    let return_var = return_var(&operation.next);
+
     match manifest.task_kind {
         TaskKind::Stream => {
             let items = output_json
                 .as_array()
                 .ok_or_else(|| anyhow::anyhow!("stream task output must be array"))?;
 
-            for item in items {
+            let collect_op = workflow.collect_of(op_id);
+            if let Some(collect_op) = collect_op {
+                if items.is_empty() {
+                    gather(state, run_id, ctx_id, collect_op, Vec::new()).await?;
+                } else {
+                    redis_store::create_fan_in(&mut redis, run_id, ctx_id, op_id, items.len())
+                        .await?;
+                }
+            }
+
+            let path = redis_store::get_context_path(&mut redis, run_id, ctx_id).await?;
+            for (index, item) in items.iter().enumerate() {
                 let child_ctx = run_state
                     .next_ctx_id
                     .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-                db::create_context(&state.db, run_id, child_ctx, Some(ctx_id)).await?;
-                redis_store::create_context(&mut redis, run_id, child_ctx, Some(ctx_id)).await?;
+                let child_path = path.push(PathStep::Output { op_id, index });
+                create_context(state, run_id, child_ctx, Some(ctx_id), &child_path).await?;
+                if collect_op.is_some() {
+                    redis_store::set_fan_in_item(&mut redis, run_id, child_ctx, ctx_id).await?;
+                }
                 store_outputs(&mut redis, run_id, child_ctx, &call.outputs, item).await?;
                 if let Some(next) = kernel
                     .resolve_next(&store, child_ctx, &operation.next)
                     .await?
                 {
-                    drive_until_call(state, run_id, child_ctx, next, Some(op_id)).await?;
+                    drive_until_call(state, run_id, child_ctx, next, Some(op_id), &done).await?;
+                } else {
+                    finish_with_result(state, run_id, child_ctx, return_var).await?;
                 }
             }
-            db::finish_context(&state.db, run_id, ctx_id).await?;
+            finish_context(state, run_id, ctx_id).await?;
         }
         _ => {
             store_outputs(&mut redis, run_id, ctx_id, &call.outputs, &output_json).await?;
             if let Some(next) = kernel.resolve_next(&store, ctx_id, &operation.next).await? {
-                drive_until_call(state, run_id, ctx_id, next, Some(op_id)).await?;
+                drive_until_call(state, run_id, ctx_id, next, Some(op_id), &done).await?;
             } else {
-                db::finish_context(&state.db, run_id, ctx_id).await?;
+                finish_with_result(state, run_id, ctx_id, return_var).await?;
             }
         }
     }
@@ -268,6 +715,108 @@ pub async fn apply_task_output(
     Ok(())
 }
 
+/// Append the call of `op_id` in `ctx_id`, which ended with `outputs`, to
+/// the run's recording if the master records runs.
+async fn record_call(
+    state: &AppState,
+    run_id: Uuid,
+    op_id: usize,
+    ctx_id: usize,
+    call: &Call,
+    outputs: Vec<RecordedOutput>,
+) -> anyhow::Result<()> {
+    let Some(recordings) = &state.recordings else {
+        return Ok(());
+    };
+    let mut redis = state.redis.clone();
+    let path = redis_store::get_context_path(&mut redis, run_id, ctx_id).await?;
+    let mut inputs = Vec::with_capacity(call.inputs.len());
+    for &input in &call.inputs {
+        let value = redis_store::get_value(&mut redis, run_id, ctx_id, input)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("missing input value {input}"))?;
+        inputs.push(value);
+    }
+    recordings.record(
+        run_id,
+        CallRecord {
+            op_id,
+            path,
+            input_hash: input_hash(&inputs),
+            task_id: call.task_id.clone(),
+            outputs,
+        },
+    )
+}
+
+/// Continue after every call of a join has completed, once per combination of
+/// their outputs (stream members contribute one entry per item).
+async fn join_outputs(
+    state: &AppState,
+    run_state: &RunState,
+    run_id: Uuid,
+    ctx_id: usize,
+    op_ids: &[usize],
+    done: &[usize],
+) -> anyhow::Result<()> {
+    let mut redis = state.redis.clone();
+    let outputs = redis_store::take_join_outputs(&mut redis, run_id, ctx_id, op_ids).await?;
+
+    let mut members = Vec::with_capacity(op_ids.len());
+    let mut streamed = false;
+    for (&op_id, output) in op_ids.iter().zip(outputs) {
+        let call = run_state.workflow.operations[op_id]
+            .call
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("op has no call"))?;
+        let task_version = run_state
+            .task_versions
+            .get(&call.task_id)
+            .ok_or_else(|| anyhow::anyhow!("missing task version for {}", call.task_id))?;
+        let manifest = db::get_task_manifest(&state.db, &call.task_id, task_version).await?;
+        let items = if manifest.task_kind == TaskKind::Stream {
+            streamed = true;
+            output
+                .as_array()
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("stream task output must be array"))?
+        } else {
+            vec![output]
+        };
+        members.push((call.outputs.clone(), items));
+    }
+
+    if !streamed {
+        for (outputs, items) in &members {
+            store_outputs(&mut redis, run_id, ctx_id, outputs, &items[0]).await?;
+        }
+        return drive_until_call(state, run_id, ctx_id, op_ids[0], None, done).await;
+    }
+
+    let combinations = members
+        .iter()
+        .map(|(_, items)| items.iter())
+        .multi_cartesian_product()
+        .collect::<Vec<_>>();
+    let path = redis_store::get_context_path(&mut redis, run_id, ctx_id).await?;
+    for (index, combination) in combinations.into_iter().enumerate() {
+        let child_ctx = run_state
+            .next_ctx_id
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        let step = PathStep::Join {
+            op_id: op_ids[0],
+            index,
+        };
+        create_context(state, run_id, child_ctx, Some(ctx_id), &path.push(step)).await?;
+        for ((outputs, _), item) in members.iter().zip(combination) {
+            store_outputs(&mut redis, run_id, child_ctx, outputs, item).await?;
+        }
+        drive_until_call(state, run_id, child_ctx, op_ids[0], None, done).await?;
+    }
+    finish_context(state, run_id, ctx_id).await?;
+    Ok(())
+}
+
 async fn store_outputs(
     redis: &mut ConnectionManager,
     run_id: Uuid,
@@ -325,29 +874,26 @@ impl OrchestratorEngine for MasterOrchestrator {
         ctx_id: usize,
         start_op: usize,
         pred_op: Option<usize>,
-    ) -> anyhow::Result<KernelPlan> {
+        done: &mut Vec<usize>,
+    ) -> anyhow::Result<Vec<KernelPlan>> {
         let run_state = get_run_state(&self.state, run_id).await?;
         let workflow = run_state.workflow.clone();
         let kernel = EngineKernel::new(JsonRuntime);
         let store = RedisValueStore::new(self.state.redis.clone(), run_id);
         kernel
-            .drive_until_action(&workflow, &store, ctx_id, start_op, pred_op)
+            .drive_until_actions(&workflow, &store, ctx_id, start_op, pred_op, done)
             .await
     }
 
-    async fn dispatch(&self, run_id: Uuid, action: KernelPlan) -> anyhow::Result<()> {
+    async fn dispatch(
+        &self,
+        run_id: Uuid,
+        ctx_id: usize,
+        actions: Vec<KernelPlan>,
+        done: &[usize],
+    ) -> anyhow::Result<()> {
         let run_state = get_run_state(&self.state, run_id).await?;
-        match action {
-            KernelPlan::Dispatch {
-                op_id,
-                ctx_id,
-                call,
-            } => enqueue_call(&self.state, &run_state, run_id, ctx_id, op_id, &call).await,
-            KernelPlan::Return { ctx_id, .. } => {
-                db::finish_context(&self.state.db, run_id, ctx_id).await?;
-                Ok(())
-            }
-        }
+        dispatch_plans(&self.state, &run_state, run_id, ctx_id, actions, done).await
     }
 
     async fn apply_task_output(
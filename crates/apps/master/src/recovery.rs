@@ -0,0 +1,115 @@
+//! Rebuild in-memory run state after a restart.
+//!
+//! Postgres holds every run, context and node, and Redis holds values, joins,
+//! queues and pending retries. Only [`RunState`] lives in memory, so a
+//! restarted master reloads it and then requeues calls whose queue message
+//! was lost while it was down.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::{AppState, RunState, db, planner, redis_store};
+
+/// `(run_id, op_id, ctx_id, attempt)` of a queued call.
+type CallAttempt = (Uuid, usize, usize, u32);
+
+pub async fn recover(state: &AppState) -> anyhow::Result<()> {
+    let runs = db::running_runs(&state.db).await?;
+    let count = runs.len();
+    for (run_id, workflow_id, version) in runs {
+        let (workflow, task_versions) = db::get_workflow(&state.db, &workflow_id, &version).await?;
+        let next_ctx_id = db::max_context_id(&state.db, run_id)
+            .await?
+            .map_or(0, |id| id + 1);
+        state.runs.write().await.insert(
+            run_id,
+            RunState {
+                workflow,
+                task_versions,
+                next_ctx_id: Arc::new(AtomicUsize::new(next_ctx_id)),
+            },
+        );
+    }
+    if count > 0 {
+        info!("recovered {count} running runs");
+    }
+    reconcile_nodes(state).await
+}
+
+/// Requeue queued nodes missing from their stream and reschedule retrying
+/// nodes missing from the retry set.
+///
+/// Running nodes are left to the lease monitor.
+async fn reconcile_nodes(state: &AppState) -> anyhow::Result<()> {
+    let mut redis = state.redis.clone();
+    // Keyed by `(pool, resource_class)`, filled the first time a stream is needed.
+    let mut in_flight: HashMap<(String, String), HashSet<CallAttempt>> = HashMap::new();
+    let mut requeued = 0usize;
+
+    for (run_id, op_id, ctx_id, status, attempt) in db::waiting_nodes(&state.db).await? {
+        if status == "retrying" {
+            let retry = redis_store::PendingRetry {
+                run_id,
+                op_id,
+                ctx_id,
+                attempt: attempt + 1,
+            };
+            if !redis_store::is_retry_scheduled(&mut redis, &retry).await? {
+                let now_ms = chrono::Utc::now().timestamp_millis();
+                redis_store::schedule_retry(&mut redis, &retry, now_ms).await?;
+                requeued += 1;
+            }
+            continue;
+        }
+
+        let runs = state.runs.read().await;
+        let Some(run_state) = runs.get(&run_id) else {
+            continue;
+        };
+        let Some(call) = run_state
+            .workflow
+            .operations
+            .get(op_id)
+            .and_then(|operation| operation.call.as_ref())
+        else {
+            continue;
+        };
+        let Some(task_version) = run_state.task_versions.get(&call.task_id) else {
+            continue;
+        };
+        let manifest = db::get_task_manifest(&state.db, &call.task_id, task_version).await?;
+        drop(runs);
+
+        let stream = (
+            planner::pool_for_manifest(&manifest),
+            manifest.resource_class.clone(),
+        );
+        if !in_flight.contains_key(&stream) {
+            let messages = redis_store::in_flight_messages(&mut redis, &stream.0, &stream.1)
+                .await?
+                .into_iter()
+                .map(|msg| (msg.run_id, msg.op_id, msg.ctx_id, msg.attempt))
+                .collect();
+            in_flight.insert(stream.clone(), messages);
+        }
+        if in_flight[&stream].contains(&(run_id, op_id, ctx_id, attempt)) {
+            continue;
+        }
+
+        match planner::requeue_call(state, run_id, op_id, ctx_id, attempt).await {
+            Ok(()) => requeued += 1,
+            Err(err) => {
+                error!("failed to requeue op {op_id} in ctx {ctx_id} of run {run_id}: {err}")
+            }
+        }
+    }
+
+    if requeued > 0 {
+        info!("requeued {requeued} calls lost while the master was down");
+    }
+    Ok(())
+}
@@ -1,10 +1,52 @@
+use std::collections::HashMap;
+
+use namu_engine::runtime::replay::CtxPath;
 use namu_proto::QueueMessage;
 use redis::AsyncCommands;
 use redis::aio::ConnectionManager;
+use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use uuid::Uuid;
 
 const MAX_PARENT_HOPS: usize = 10_000;
+const RETRIES_KEY: &str = "retries";
+
+/// Mark a context finished, then drop it and each finished ancestor left
+/// without children. The root is kept for `GET /runs/{id}/values` and goes
+/// with the rest of the run once its retention runs out.
+const RELEASE_CONTEXT: &str = r#"
+local run = ARGV[1]
+local ctx = ARGV[2]
+local key = 'context:' .. run .. ':' .. ctx
+if redis.call('EXISTS', key) == 0 then
+  return 0
+end
+redis.call('HSET', key, 'finished', 1)
+local released = 0
+while true do
+  key = 'context:' .. run .. ':' .. ctx
+  local state = redis.call('HMGET', key, 'finished', 'children', 'parent')
+  local parent = state[3]
+  if state[1] ~= '1' or tonumber(state[2] or '0') > 0 or not parent or parent == '-1' then
+    break
+  end
+  redis.call('DEL', key, 'values:' .. run .. ':' .. ctx, 'join:' .. run .. ':' .. ctx,
+    'fanin:' .. run .. ':' .. ctx)
+  released = released + 1
+  ctx = parent
+  redis.call('HINCRBY', 'context:' .. run .. ':' .. ctx, 'children', -1)
+end
+return released
+"#;
+
+/// A call waiting in `retries` to be enqueued again.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PendingRetry {
+    pub run_id: Uuid,
+    pub op_id: usize,
+    pub ctx_id: usize,
+    pub attempt: u32,
+}
 
 fn values_key(run_id: Uuid, ctx_id: usize) -> String {
     format!("values:{run_id}:{ctx_id}")
@@ -14,17 +56,84 @@ fn context_key(run_id: Uuid, ctx_id: usize) -> String {
     format!("context:{run_id}:{ctx_id}")
 }
 
+fn join_key(run_id: Uuid, ctx_id: usize) -> String {
+    format!("join:{run_id}:{ctx_id}")
+}
+
+fn fan_in_key(run_id: Uuid, ctx_id: usize) -> String {
+    format!("fanin:{run_id}:{ctx_id}")
+}
+
 pub async fn create_context(
     conn: &mut ConnectionManager,
     run_id: Uuid,
     ctx_id: usize,
     parent_ctx_id: Option<usize>,
+    path: &CtxPath,
 ) -> anyhow::Result<()> {
     let key = context_key(run_id, ctx_id);
     let parent = parent_ctx_id
         .map(|id| id.to_string())
         .unwrap_or_else(|| "-1".to_string());
-    let _: () = conn.hset(key, "parent", parent).await?;
+    let mut pipe = redis::pipe();
+    pipe.atomic()
+        .hset_multiple(key, &[("parent", parent.as_str()), ("path", path.as_str())])
+        .ignore();
+    if let Some(parent_ctx_id) = parent_ctx_id {
+        pipe.hincr(context_key(run_id, parent_ctx_id), "children", 1)
+            .ignore();
+    }
+    let _: () = pipe.query_async(conn).await?;
+    Ok(())
+}
+
+/// Where `ctx_id` sits in its run, see [`CtxPath`].
+pub async fn get_context_path(
+    conn: &mut ConnectionManager,
+    run_id: Uuid,
+    ctx_id: usize,
+) -> anyhow::Result<CtxPath> {
+    let raw: Option<String> = conn.hget(context_key(run_id, ctx_id), "path").await?;
+    Ok(raw.map(CtxPath::from).unwrap_or_default())
+}
+
+/// Finish `ctx_id` and drop its state, and that of its finished ancestors,
+/// once no child context is left; returns how many contexts were dropped.
+pub async fn release_context(
+    conn: &mut ConnectionManager,
+    run_id: Uuid,
+    ctx_id: usize,
+) -> anyhow::Result<usize> {
+    let released: usize = redis::Script::new(RELEASE_CONTEXT)
+        .arg(run_id.to_string())
+        .arg(ctx_id)
+        .invoke_async(conn)
+        .await?;
+    Ok(released)
+}
+
+/// Expire everything a run left in Redis after `retention`: the state of its
+/// `contexts` context ids and its event stream.
+pub async fn expire_run(
+    conn: &mut ConnectionManager,
+    run_id: Uuid,
+    contexts: usize,
+    retention: std::time::Duration,
+) -> anyhow::Result<()> {
+    let seconds = retention.as_secs() as i64;
+    let mut pipe = redis::pipe();
+    for ctx_id in 0..contexts {
+        for key in [
+            values_key(run_id, ctx_id),
+            context_key(run_id, ctx_id),
+            join_key(run_id, ctx_id),
+            fan_in_key(run_id, ctx_id),
+        ] {
+            pipe.expire(key, seconds).ignore();
+        }
+    }
+    pipe.expire(format!("events:{run_id}"), seconds).ignore();
+    let _: () = pipe.query_async(conn).await?;
     Ok(())
 }
 
@@ -101,6 +210,223 @@ pub async fn get_value_raw(
     Ok(None)
 }
 
+/// Remember the calls of the current segment that already ran in `ctx_id`.
+pub async fn set_done_ops(
+    conn: &mut ConnectionManager,
+    run_id: Uuid,
+    ctx_id: usize,
+    done: &[usize],
+) -> anyhow::Result<()> {
+    let key = context_key(run_id, ctx_id);
+    let _: () = conn.hset(key, "done", serde_json::to_string(done)?).await?;
+    Ok(())
+}
+
+pub async fn get_done_ops(
+    conn: &mut ConnectionManager,
+    run_id: Uuid,
+    ctx_id: usize,
+) -> anyhow::Result<Vec<usize>> {
+    let key = context_key(run_id, ctx_id);
+    let raw: Option<String> = conn.hget(key, "done").await?;
+    match raw {
+        Some(raw) => Ok(serde_json::from_str(&raw)?),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Record calls dispatched together from `ctx_id` so their outputs can be joined.
+///
+/// `done` lists the calls of the segment that have run once the join completes.
+pub async fn create_join(
+    conn: &mut ConnectionManager,
+    run_id: Uuid,
+    ctx_id: usize,
+    op_ids: &[usize],
+    done: &[usize],
+) -> anyhow::Result<()> {
+    let key = join_key(run_id, ctx_id);
+    let ops = serde_json::to_string(op_ids)?;
+    let done = serde_json::to_string(done)?;
+    let _: () = conn
+        .hset_multiple(
+            key,
+            &[
+                ("ops", ops),
+                ("done", done),
+                ("pending", op_ids.len().to_string()),
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+/// Members and done set of the join waiting in `ctx_id`, if any.
+pub async fn get_join(
+    conn: &mut ConnectionManager,
+    run_id: Uuid,
+    ctx_id: usize,
+) -> anyhow::Result<Option<(Vec<usize>, Vec<usize>)>> {
+    let key = join_key(run_id, ctx_id);
+    let (ops, done): (Option<String>, Option<String>) = conn.hget(key, &["ops", "done"]).await?;
+    match (ops, done) {
+        (Some(ops), Some(done)) => Ok(Some((
+            serde_json::from_str(&ops)?,
+            serde_json::from_str(&done)?,
+        ))),
+        _ => Ok(None),
+    }
+}
+
+/// Store one member's output and return how many members are still pending.
+pub async fn record_join_output(
+    conn: &mut ConnectionManager,
+    run_id: Uuid,
+    ctx_id: usize,
+    op_id: usize,
+    output: &JsonValue,
+) -> anyhow::Result<i64> {
+    let key = join_key(run_id, ctx_id);
+    let payload = serde_json::to_string(output)?;
+    let _: () = conn.hset(&key, format!("out:{op_id}"), payload).await?;
+    let pending: i64 = conn.hincr(&key, "pending", -1).await?;
+    Ok(pending)
+}
+
+/// Read every member's output, in `op_ids` order, and drop the join.
+pub async fn take_join_outputs(
+    conn: &mut ConnectionManager,
+    run_id: Uuid,
+    ctx_id: usize,
+    op_ids: &[usize],
+) -> anyhow::Result<Vec<JsonValue>> {
+    let key = join_key(run_id, ctx_id);
+    let mut outputs = Vec::with_capacity(op_ids.len());
+    for op_id in op_ids {
+        let raw: Option<String> = conn.hget(&key, format!("out:{op_id}")).await?;
+        let raw = raw.ok_or_else(|| anyhow::anyhow!("missing join output for op {op_id}"))?;
+        outputs.push(serde_json::from_str(&raw)?);
+    }
+    let _: () = conn.del(&key).await?;
+    Ok(outputs)
+}
+
+/// Drop the join waiting in `ctx_id` along with the outputs it collected.
+pub async fn drop_join(
+    conn: &mut ConnectionManager,
+    run_id: Uuid,
+    ctx_id: usize,
+) -> anyhow::Result<()> {
+    let _: () = conn.del(join_key(run_id, ctx_id)).await?;
+    Ok(())
+}
+
+/// Wait for the `items` contexts the stream at `from_op` fanned out of
+/// `ctx_id` to reach its collect.
+pub async fn create_fan_in(
+    conn: &mut ConnectionManager,
+    run_id: Uuid,
+    ctx_id: usize,
+    from_op: usize,
+    items: usize,
+) -> anyhow::Result<()> {
+    let key = fan_in_key(run_id, ctx_id);
+    let _: () = conn
+        .hset_multiple(
+            key,
+            &[
+                ("from", from_op.to_string()),
+                ("pending", items.to_string()),
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+/// Mark `ctx_id` as an item of the fan-out waiting in `fan_ctx_id`.
+pub async fn set_fan_in_item(
+    conn: &mut ConnectionManager,
+    run_id: Uuid,
+    ctx_id: usize,
+    fan_ctx_id: usize,
+) -> anyhow::Result<()> {
+    let key = context_key(run_id, ctx_id);
+    let _: () = conn.hset(key, "fan_in", fan_ctx_id).await?;
+    Ok(())
+}
+
+/// The item of the fan-out by `from_op` that `ctx_id` belongs to, and the
+/// context that fan-out waits in.
+pub async fn find_fan_in(
+    conn: &mut ConnectionManager,
+    run_id: Uuid,
+    ctx_id: usize,
+    from_op: usize,
+) -> anyhow::Result<Option<(usize, usize)>> {
+    let mut current_ctx = Some(ctx_id);
+    let mut hops = 0usize;
+
+    while let Some(ctx) = current_ctx {
+        let fan_ctx: Option<usize> = conn.hget(context_key(run_id, ctx), "fan_in").await?;
+        if let Some(fan_ctx) = fan_ctx {
+            let from: Option<usize> = conn.hget(fan_in_key(run_id, fan_ctx), "from").await?;
+            if from == Some(from_op) {
+                return Ok(Some((ctx, fan_ctx)));
+            }
+        }
+        current_ctx = get_parent(conn, run_id, ctx).await?;
+        hops = hops.saturating_add(1);
+        if hops > MAX_PARENT_HOPS {
+            return Err(anyhow::anyhow!(
+                "context parent chain exceeded {MAX_PARENT_HOPS} hops"
+            ));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Store the value one item hands in and return how many items are still
+/// pending.
+pub async fn record_fan_in_value(
+    conn: &mut ConnectionManager,
+    run_id: Uuid,
+    fan_ctx_id: usize,
+    item_ctx_id: usize,
+    value: &JsonValue,
+) -> anyhow::Result<i64> {
+    let key = fan_in_key(run_id, fan_ctx_id);
+    let payload = serde_json::to_string(value)?;
+    let _: () = conn
+        .hset(&key, format!("item:{item_ctx_id}"), payload)
+        .await?;
+    let pending: i64 = conn.hincr(&key, "pending", -1).await?;
+    Ok(pending)
+}
+
+/// Read the handed-in values in context order and drop the fan-in.
+pub async fn take_fan_in_values(
+    conn: &mut ConnectionManager,
+    run_id: Uuid,
+    fan_ctx_id: usize,
+) -> anyhow::Result<Vec<JsonValue>> {
+    let key = fan_in_key(run_id, fan_ctx_id);
+    let fields: HashMap<String, String> = conn.hgetall(&key).await?;
+    let mut items = Vec::new();
+    for (field, raw) in fields {
+        let Some(item_ctx) = field.strip_prefix("item:") else {
+            continue;
+        };
+        let item_ctx = item_ctx
+            .parse::<usize>()
+            .map_err(|_| anyhow::anyhow!("invalid fan-in item {field}"))?;
+        items.push((item_ctx, serde_json::from_str::<JsonValue>(&raw)?));
+    }
+    items.sort_by_key(|(item_ctx, _)| *item_ctx);
+    let _: () = conn.del(&key).await?;
+    Ok(items.into_iter().map(|(_, value)| value).collect())
+}
+
 pub async fn queue_task(
     conn: &mut ConnectionManager,
     pool: &str,
@@ -119,6 +445,179 @@ pub async fn queue_task(
     Ok(())
 }
 
+/// Schedule `retry` for `due_ms` (Unix milliseconds).
+pub async fn schedule_retry(
+    conn: &mut ConnectionManager,
+    retry: &PendingRetry,
+    due_ms: i64,
+) -> anyhow::Result<()> {
+    let member = serde_json::to_string(retry)?;
+    let _: () = conn.zadd(RETRIES_KEY, member, due_ms).await?;
+    Ok(())
+}
+
+pub async fn is_retry_scheduled(
+    conn: &mut ConnectionManager,
+    retry: &PendingRetry,
+) -> anyhow::Result<bool> {
+    let member = serde_json::to_string(retry)?;
+    let score: Option<f64> = conn.zscore(RETRIES_KEY, member).await?;
+    Ok(score.is_some())
+}
+
+/// Remove and return the retries due by `now_ms`.
+///
+/// An entry is only returned to the caller that removed it, so concurrent
+/// callers never enqueue the same retry twice.
+pub async fn take_due_retries(
+    conn: &mut ConnectionManager,
+    now_ms: i64,
+) -> anyhow::Result<Vec<PendingRetry>> {
+    let members: Vec<String> = conn.zrangebyscore(RETRIES_KEY, "-inf", now_ms).await?;
+    let mut due = Vec::with_capacity(members.len());
+    for member in members {
+        let removed: i64 = conn.zrem(RETRIES_KEY, &member).await?;
+        if removed == 1 {
+            due.push(serde_json::from_str(&member)?);
+        }
+    }
+    Ok(due)
+}
+
+/// Drop `retry` if it is scheduled.
+pub async fn drop_retry(conn: &mut ConnectionManager, retry: &PendingRetry) -> anyhow::Result<()> {
+    let member = serde_json::to_string(retry)?;
+    let _: () = conn.zrem(RETRIES_KEY, member).await?;
+    Ok(())
+}
+
+/// Drop the retries scheduled for `run_id`, returning how many were removed.
+pub async fn drop_retries(conn: &mut ConnectionManager, run_id: Uuid) -> anyhow::Result<usize> {
+    let members: Vec<String> = conn.zrange(RETRIES_KEY, 0, -1).await?;
+    let mut dropped = 0;
+    for member in members {
+        let retry: PendingRetry = serde_json::from_str(&member)?;
+        if retry.run_id == run_id {
+            let removed: i64 = conn.zrem(RETRIES_KEY, &member).await?;
+            dropped += removed as usize;
+        }
+    }
+    Ok(dropped)
+}
+
+/// Delete the messages of `run_id` from `queue:{pool}:{resource_class}`,
+/// returning how many were removed.
+///
+/// Messages a worker already read stay pending for that worker, which
+/// acknowledges them as usual.
+pub async fn drop_queued_messages(
+    conn: &mut ConnectionManager,
+    pool: &str,
+    resource_class: &str,
+    run_id: Uuid,
+) -> anyhow::Result<usize> {
+    let stream = format!("queue:{pool}:{resource_class}");
+    let reply: redis::Value = redis::cmd("XRANGE")
+        .arg(&stream)
+        .arg("-")
+        .arg("+")
+        .query_async(conn)
+        .await?;
+    let mut ids = Vec::new();
+    for (id, payload) in parse_stream_entries(reply)? {
+        let msg: QueueMessage = serde_json::from_value(payload)?;
+        if msg.run_id == run_id {
+            ids.push(id);
+        }
+    }
+    if ids.is_empty() {
+        return Ok(0);
+    }
+    let removed: i64 = redis::cmd("XDEL")
+        .arg(&stream)
+        .arg(&ids)
+        .query_async(conn)
+        .await?;
+    Ok(removed as usize)
+}
+
+/// Messages in `queue:{pool}:{resource_class}` that no worker is done with:
+/// not yet delivered to the worker group, or delivered and not acknowledged.
+pub async fn in_flight_messages(
+    conn: &mut ConnectionManager,
+    pool: &str,
+    resource_class: &str,
+) -> anyhow::Result<Vec<QueueMessage>> {
+    let stream = format!("queue:{pool}:{resource_class}");
+    let group = format!("workers:{pool}:{resource_class}");
+    let exists: bool = conn.exists(&stream).await?;
+    if !exists {
+        return Ok(Vec::new());
+    }
+
+    let groups: Vec<HashMap<String, redis::Value>> = redis::cmd("XINFO")
+        .arg("GROUPS")
+        .arg(&stream)
+        .query_async(conn)
+        .await?;
+    let last_delivered = groups
+        .iter()
+        .find(|info| {
+            info.get("name")
+                .and_then(|name| redis::from_redis_value::<String>(name).ok())
+                .is_some_and(|name| name == group)
+        })
+        .and_then(|info| info.get("last-delivered-id"))
+        .map(redis::from_redis_value::<String>)
+        .transpose()?;
+
+    let mut payloads = Vec::new();
+    let undelivered: redis::Value = match &last_delivered {
+        Some(id) => {
+            redis::cmd("XRANGE")
+                .arg(&stream)
+                .arg(format!("({id}"))
+                .arg("+")
+                .query_async(conn)
+                .await?
+        }
+        None => {
+            redis::cmd("XRANGE")
+                .arg(&stream)
+                .arg("-")
+                .arg("+")
+                .query_async(conn)
+                .await?
+        }
+    };
+    payloads.extend(parse_stream_payloads(undelivered)?);
+
+    if last_delivered.is_some() {
+        let pending: Vec<(String, String, i64, i64)> = redis::cmd("XPENDING")
+            .arg(&stream)
+            .arg(&group)
+            .arg("-")
+            .arg("+")
+            .arg(i64::MAX)
+            .query_async(conn)
+            .await?;
+        for (id, ..) in pending {
+            let entry: redis::Value = redis::cmd("XRANGE")
+                .arg(&stream)
+                .arg(&id)
+                .arg(&id)
+                .query_async(conn)
+                .await?;
+            payloads.extend(parse_stream_payloads(entry)?);
+        }
+    }
+
+    payloads
+        .into_iter()
+        .map(|payload| Ok(serde_json::from_value(payload)?))
+        .collect()
+}
+
 pub async fn add_event(
     conn: &mut ConnectionManager,
     run_id: Uuid,
@@ -153,7 +652,53 @@ pub async fn read_events(
     parse_stream_payloads(reply)
 }
 
+/// Events of `run_id` after the entry `after` (`0` for all of them) as
+/// `(entry_id, payload)`, waiting up to `block_ms` for one to arrive.
+pub async fn tail_events<C: redis::aio::ConnectionLike>(
+    conn: &mut C,
+    run_id: Uuid,
+    after: &str,
+    block_ms: u64,
+) -> anyhow::Result<Vec<(String, JsonValue)>> {
+    let key = format!("events:{run_id}");
+    let reply: redis::Value = redis::cmd("XREAD")
+        .arg("COUNT")
+        .arg(100)
+        .arg("BLOCK")
+        .arg(block_ms)
+        .arg("STREAMS")
+        .arg(&key)
+        .arg(after)
+        .query_async(conn)
+        .await?;
+
+    let mut out = Vec::new();
+    let redis::Value::Array(streams) = reply else {
+        return Ok(out);
+    };
+    for stream in streams {
+        let redis::Value::Array(mut parts) = stream else {
+            continue;
+        };
+        if parts.len() != 2 {
+            continue;
+        }
+        if let Some(entries) = parts.pop() {
+            out.extend(parse_stream_entries(entries)?);
+        }
+    }
+    Ok(out)
+}
+
 fn parse_stream_payloads(value: redis::Value) -> anyhow::Result<Vec<JsonValue>> {
+    Ok(parse_stream_entries(value)?
+        .into_iter()
+        .map(|(_, payload)| payload)
+        .collect())
+}
+
+/// Stream entries as `(entry_id, payload)`.
+fn parse_stream_entries(value: redis::Value) -> anyhow::Result<Vec<(String, JsonValue)>> {
     let mut out = Vec::new();
     let entries = match value {
         redis::Value::Array(entries) => entries,
@@ -167,6 +712,7 @@ fn parse_stream_payloads(value: redis::Value) -> anyhow::Result<Vec<JsonValue>>
         if parts.len() != 2 {
             continue;
         }
+        let id = redis::from_redis_value::<String>(&parts[0])?;
         let redis::Value::Array(kv) = &parts[1] else {
             continue;
         };
@@ -177,7 +723,7 @@ fn parse_stream_payloads(value: redis::Value) -> anyhow::Result<Vec<JsonValue>>
             let field = redis::from_redis_value::<String>(key)?;
             if field == "payload" {
                 let payload = redis::from_redis_value::<String>(val)?;
-                out.push(serde_json::from_str(&payload)?);
+                out.push((id.clone(), serde_json::from_str(&payload)?));
             }
             i += 2;
         }
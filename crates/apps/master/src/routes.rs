@@ -1,16 +1,24 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, VecDeque};
+use std::convert::Infallible;
 use std::io::{Cursor, Read};
 
 use axum::extract::{Multipart, Path, Query, State};
-use axum::http::StatusCode;
+use axum::http::{HeaderMap, StatusCode};
 use axum::response::Json;
+use axum::response::sse::{Event, KeepAlive, Sse};
 use bytes::Bytes;
 use chrono::Utc;
+use futures::Stream;
+use namu_engine::kernel::JsonRuntime;
+use namu_engine::runtime::replay::CtxPath;
+use namu_engine::traits::observer::RunOutcome;
 use namu_proto::{
-    Progress, RunCreateRequest, RunCreateResponse, RunStatusResponse, TaskCompleteRequest,
-    TaskManifest, TaskStartRequest, WorkflowUploadRequest,
+    LeafFailure, LeafResult, Progress, RunCreateRequest, RunCreateResponse, RunResultResponse,
+    RunStatusResponse, TaskCompleteRequest, TaskError, TaskManifest, TaskStartRequest,
+    WorkflowUploadRequest,
 };
 use redis::AsyncCommands;
+use redis::aio::MultiplexedConnection;
 use serde::Deserialize;
 use serde_json::Value as JsonValue;
 use sha2::Digest;
@@ -25,6 +33,16 @@ pub struct EventsQuery {
     pub limit: Option<usize>,
 }
 
+#[derive(Deserialize)]
+pub struct EventStreamQuery {
+    /// Resume after this event id; a `Last-Event-ID` header takes precedence.
+    pub after: Option<String>,
+}
+
+/// Events after which a run emits nothing more.
+const FINAL_EVENTS: [&str; 2] = ["run_finished", "cancelled"];
+const EVENT_BLOCK_MS: u64 = 15_000;
+
 pub async fn healthz() -> (StatusCode, Json<JsonValue>) {
     (StatusCode::OK, Json(serde_json::json!({"status": "ok"})))
 }
@@ -101,39 +119,76 @@ pub async fn get_artifact(
 pub async fn upload_workflows(
     State(state): State<AppState>,
     Json(req): Json<WorkflowUploadRequest>,
-) -> Result<Json<JsonValue>, StatusCode> {
-    let _workflow: namu_core::ir::Workflow =
-        serde_json::from_value(req.ir.clone()).map_err(|_| StatusCode::BAD_REQUEST)?;
+) -> (StatusCode, Json<JsonValue>) {
+    let workflow: namu_core::ir::Workflow = match serde_json::from_value(req.ir.clone()) {
+        Ok(workflow) => workflow,
+        Err(err) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({"status": "error", "message": err.to_string()})),
+            );
+        }
+    };
+    if let Err(errors) = workflow.validate() {
+        return (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(serde_json::json!({
+                "status": "error",
+                "message": "invalid workflow IR",
+                "errors": errors,
+            })),
+        );
+    }
     let task_versions = req.task_versions.clone();
 
-    db::insert_workflow(&state.db, &req.id, &req.version, &req.ir, &task_versions)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if let Err(err) =
+        db::insert_workflow(&state.db, &req.id, &req.version, &req.ir, &task_versions).await
+    {
+        tracing::error!("upload_workflows: insert_workflow failed: {err}");
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(serde_json::json!({"status": "error", "message": "failed to store workflow"})),
+        );
+    }
 
-    Ok(Json(serde_json::json!({
-        "id": req.id,
-        "version": req.version
-    })))
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "id": req.id,
+            "version": req.version
+        })),
+    )
 }
 
 pub async fn create_run(
     State(state): State<AppState>,
     Json(req): Json<RunCreateRequest>,
 ) -> Result<Json<RunCreateResponse>, StatusCode> {
-    let run_id = db::create_run(&state.db, &req.workflow_id, &req.version)
+    let (workflow, task_versions) = db::get_workflow(&state.db, &req.workflow_id, &req.version)
         .await
         .map_err(|err| {
-            tracing::error!("create_run: create_run failed: {err}");
-            StatusCode::INTERNAL_SERVER_ERROR
+            tracing::error!("create_run: get_workflow failed: {err}");
+            StatusCode::NOT_FOUND
         })?;
+    let inputs = JsonRuntime::bind_inputs(&workflow, &req.inputs).map_err(|err| {
+        tracing::error!("create_run: invalid inputs: {err}");
+        StatusCode::BAD_REQUEST
+    })?;
 
-    let (workflow, task_versions) = db::get_workflow(&state.db, &req.workflow_id, &req.version)
+    let run_id = db::create_run(&state.db, &req.workflow_id, &req.version)
         .await
         .map_err(|err| {
-            tracing::error!("create_run: get_workflow failed: {err}");
-            StatusCode::NOT_FOUND
+            tracing::error!("create_run: create_run failed: {err}");
+            StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
+    if let Some(recordings) = &state.recordings
+        && let Err(err) = recordings.start(run_id, &workflow, inputs.clone())
+    {
+        tracing::error!("create_run: starting the recording failed: {err}");
+        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
     let run_state = RunState {
         workflow,
         task_versions,
@@ -147,21 +202,20 @@ pub async fn create_run(
             tracing::error!("create_run: set_run_status failed: {err}");
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
-    db::create_context(&state.db, run_id, 0, None)
+    planner::create_context(&state, run_id, 0, None, &CtxPath::root())
         .await
         .map_err(|err| {
             tracing::error!("create_run: create_context failed: {err}");
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
-    let mut redis = state.redis.clone();
-    redis_store::create_context(&mut redis, run_id, 0, None)
+    planner::seed_inputs(&state, run_id, 0, inputs)
         .await
         .map_err(|err| {
-            tracing::error!("create_run: redis create_context failed: {err}");
+            tracing::error!("create_run: seed_inputs failed: {err}");
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
-    planner::drive_until_call(&state, run_id, 0, 0, None)
+    planner::drive_until_call(&state, run_id, 0, 0, None, &[])
         .await
         .map_err(|err| {
             tracing::error!("create_run: drive_until_call failed: {err}");
@@ -193,6 +247,53 @@ pub async fn get_run_status(
     }))
 }
 
+pub async fn get_run_result(
+    State(state): State<AppState>,
+    Path(run_id): Path<Uuid>,
+) -> Result<Json<RunResultResponse>, StatusCode> {
+    let status = db::get_run_status(&state.db, run_id)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    let results = db::run_results(&state.db, run_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .into_iter()
+        .map(|(ctx_id, value)| LeafResult { ctx_id, value })
+        .collect();
+    let failures = db::failed_nodes(&state.db, run_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .into_iter()
+        .map(|(ctx_id, op_id, error)| LeafFailure {
+            ctx_id,
+            op_id,
+            error,
+        })
+        .collect();
+    Ok(Json(RunResultResponse {
+        status,
+        results,
+        failures,
+    }))
+}
+
+pub async fn cancel_run(
+    State(state): State<AppState>,
+    Path(run_id): Path<Uuid>,
+) -> Result<Json<JsonValue>, StatusCode> {
+    db::get_run_status(&state.db, run_id)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    let cancelled = planner::cancel_run(&state, run_id).await.map_err(|err| {
+        tracing::error!("cancel_run failed: {err}");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    if !cancelled {
+        return Err(StatusCode::CONFLICT);
+    }
+    Ok(Json(serde_json::json!({"status": "cancelled"})))
+}
+
 pub async fn get_run_values(
     State(state): State<AppState>,
     Path(run_id): Path<Uuid>,
@@ -219,19 +320,140 @@ pub async fn run_events(
     Ok(Json(serde_json::json!({"events": events})))
 }
 
+/// Tail the events of a run as server-sent events until the run ends.
+///
+/// Each event carries its Redis stream entry id, so a client that
+/// reconnects with `Last-Event-ID` picks up where it left off.
+pub async fn stream_run_events(
+    State(state): State<AppState>,
+    Path(run_id): Path<Uuid>,
+    Query(query): Query<EventStreamQuery>,
+    headers: HeaderMap,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+    db::get_run_status(&state.db, run_id)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    let after = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .or(query.after)
+        .unwrap_or_else(|| "0".to_string());
+    // `XREAD BLOCK` holds its connection, so each stream gets its own.
+    let conn = state
+        .redis_client
+        .get_multiplexed_async_connection()
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let tail = EventTail {
+        state,
+        run_id,
+        conn,
+        after,
+        pending: VecDeque::new(),
+        finished: false,
+    };
+    let events = futures::stream::unfold(tail, |mut tail| async move {
+        tail.next().await.map(|event| (Ok(event), tail))
+    });
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+struct EventTail {
+    state: AppState,
+    run_id: Uuid,
+    conn: MultiplexedConnection,
+    after: String,
+    pending: VecDeque<Event>,
+    finished: bool,
+}
+
+impl EventTail {
+    async fn next(&mut self) -> Option<Event> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(event);
+            }
+            if self.finished {
+                return None;
+            }
+
+            let entries = match redis_store::tail_events(
+                &mut self.conn,
+                self.run_id,
+                &self.after,
+                EVENT_BLOCK_MS,
+            )
+            .await
+            {
+                Ok(entries) => entries,
+                Err(err) => {
+                    tracing::error!("event stream of run {} failed: {err}", self.run_id);
+                    return None;
+                }
+            };
+            if entries.is_empty() {
+                // Runs that ended before they recorded a final event.
+                match db::get_run_status(&self.state.db, self.run_id).await {
+                    Ok(status) if status == "queued" || status == "running" => continue,
+                    _ => return None,
+                }
+            }
+
+            for (id, payload) in entries {
+                let name = payload["event"].as_str().unwrap_or("message").to_string();
+                self.finished = FINAL_EVENTS.contains(&name.as_str());
+                self.pending.push_back(
+                    Event::default()
+                        .id(&id)
+                        .event(name)
+                        .data(payload.to_string()),
+                );
+                self.after = id;
+                if self.finished {
+                    break;
+                }
+            }
+        }
+    }
+}
+
 pub async fn start_node(
     State(state): State<AppState>,
     Path(run_id): Path<Uuid>,
     Json(req): Json<TaskStartRequest>,
 ) -> Result<Json<JsonValue>, StatusCode> {
+    // Tells the worker to skip a call it picked up before the run was cancelled.
+    if is_cancelled(&state, run_id).await? {
+        return Ok(Json(serde_json::json!({"status": "cancelled"})));
+    }
     let lease_expires_at = Utc::now() + chrono::Duration::milliseconds(req.lease_ms as i64);
-    db::upsert_run_node(
+    // A redelivered message of a call that settled, or of an older attempt,
+    // must not take the node back.
+    let started = db::start_node(
         &state.db,
         run_id,
         req.op_id,
         req.ctx_id,
-        "running",
-        Some(lease_expires_at),
+        req.attempt,
+        lease_expires_at,
+    )
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !started {
+        return Ok(Json(serde_json::json!({"status": "ignored"})));
+    }
+    let mut redis = state.redis.clone();
+    redis_store::add_event(
+        &mut redis,
+        run_id,
+        &serde_json::json!({
+            "event": "started",
+            "op_id": req.op_id,
+            "ctx_id": req.ctx_id,
+            "attempt": req.attempt,
+        }),
     )
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -243,43 +465,58 @@ pub async fn submit_task(
     Path(run_id): Path<Uuid>,
     Json(req): Json<TaskCompleteRequest>,
 ) -> Result<Json<JsonValue>, StatusCode> {
+    // A run that ended is forgotten; what its workers still report is moot.
+    if is_cancelled(&state, run_id).await? || !state.runs.read().await.contains_key(&run_id) {
+        return Ok(Json(serde_json::json!({"status": "ignored"})));
+    }
+
     if req.success {
         let output = req.output_json.ok_or(StatusCode::BAD_REQUEST)?;
-        planner::apply_task_output(&state, run_id, req.op_id, req.ctx_id, output)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-        db::update_run_node(&state.db, run_id, req.op_id, req.ctx_id, "succeeded", None)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    } else {
-        db::update_run_node(
+        // A worker whose lease expired may report after the call was retried
+        // or failed, and a report may arrive twice; only the first report of
+        // the attempt owning the node counts.
+        let settled = db::settle_node(
             &state.db,
             run_id,
             req.op_id,
             req.ctx_id,
-            "failed",
-            req.error.as_deref(),
+            req.attempt,
+            "succeeded",
+            None,
         )
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-        db::finish_context(&state.db, run_id, req.ctx_id)
+        if !settled {
+            return Ok(Json(serde_json::json!({"status": "ignored"})));
+        }
+        planner::apply_task_output(&state, run_id, req.op_id, req.ctx_id, output)
             .await
             .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let mut redis = state.redis.clone();
+        redis_store::add_event(
+            &mut redis,
+            run_id,
+            &serde_json::json!({
+                "event": "completed",
+                "op_id": req.op_id,
+                "ctx_id": req.ctx_id,
+                "attempt": req.attempt,
+            }),
+        )
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    } else {
+        let error = req
+            .error
+            .unwrap_or_else(|| TaskError::retryable("task failed"));
+        planner::fail_call(&state, run_id, req.op_id, req.ctx_id, req.attempt, &error)
+            .await
+            .map_err(|err| {
+                tracing::error!("submit_task: fail_call failed: {err}");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
     }
 
-    let mut redis = state.redis.clone();
-    redis_store::add_event(
-        &mut redis,
-        run_id,
-        &serde_json::json!({
-            "event": if req.success {"completed"} else {"failed"},
-            "op_id": req.op_id,
-            "ctx_id": req.ctx_id
-        }),
-    )
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
     update_run_status_if_complete(&state, run_id)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -287,26 +524,42 @@ pub async fn submit_task(
     Ok(Json(serde_json::json!({"status": "ok"})))
 }
 
+async fn is_cancelled(state: &AppState, run_id: Uuid) -> Result<bool, StatusCode> {
+    let status = db::get_run_status(&state.db, run_id)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    Ok(status == "cancelled")
+}
+
 async fn update_run_status_if_complete(state: &AppState, run_id: Uuid) -> anyhow::Result<()> {
+    let current = db::get_run_status(&state.db, run_id).await?;
+    if current != "queued" && current != "running" {
+        return Ok(());
+    }
     let (done, total) = db::run_progress(&state.db, run_id).await?;
     let failed = db::count_nodes_by_status(&state.db, run_id, "failed").await?;
+    // Members of a join that another member failed.
+    let cancelled = db::count_nodes_by_status(&state.db, run_id, "cancelled").await?;
     let active = db::count_contexts_by_status(&state.db, run_id, "active").await?;
 
-    if active == 0 {
-        if total == 0 {
-            db::set_run_status(&state.db, run_id, "succeeded").await?;
-            return Ok(());
-        }
-        if done + failed >= total {
-            let status = if failed > 0 {
-                "partial_failed"
-            } else {
-                "succeeded"
-            };
-            db::set_run_status(&state.db, run_id, status).await?;
-        }
+    if active > 0 || done + failed + cancelled < total {
+        return Ok(());
     }
-    Ok(())
+    let status = if failed > 0 {
+        "partial_failed"
+    } else {
+        "succeeded"
+    };
+    db::set_run_status(&state.db, run_id, status).await?;
+    let mut redis = state.redis.clone();
+    redis_store::add_event(
+        &mut redis,
+        run_id,
+        &serde_json::json!({ "event": "run_finished", "status": status }),
+    )
+    .await?;
+    state.observe(|observer| observer.on_run_finished(run_id, RunOutcome::Finished));
+    planner::expire_run(state, run_id).await
 }
 
 pub async fn get_workers(State(state): State<AppState>) -> Result<Json<JsonValue>, StatusCode> {
@@ -348,23 +601,29 @@ fn is_valid_pool(pool: &str) -> bool {
 
 pub async fn expire_leases(state: &AppState) -> anyhow::Result<()> {
     let expired = db::expired_leases(&state.db).await?;
-    for (run_id, op_id, ctx_id) in expired {
-        db::update_run_node(
-            &state.db,
-            run_id,
-            op_id as usize,
-            ctx_id as usize,
-            "failed",
-            Some("lease expired"),
-        )
-        .await?;
+    for (run_id, op_id, ctx_id, attempt) in expired {
         let mut redis = state.redis.clone();
         redis_store::add_event(
             &mut redis,
             run_id,
-            &serde_json::json!({"event": "lease_expired", "op_id": op_id, "ctx_id": ctx_id}),
+            &serde_json::json!({
+                "event": "lease_expired",
+                "op_id": op_id,
+                "ctx_id": ctx_id,
+                "attempt": attempt,
+            }),
+        )
+        .await?;
+        planner::fail_call(
+            state,
+            run_id,
+            op_id as usize,
+            ctx_id as usize,
+            attempt,
+            &TaskError::timeout("lease expired"),
         )
         .await?;
+        update_run_status_if_complete(state, run_id).await?;
     }
     Ok(())
 }
@@ -0,0 +1,80 @@
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+
+use namu_proto::{BatchPolicy, TaskManifest, TaskRuntime};
+
+use crate::{native_pool, wasm_executor};
+
+/// Unpack the library or module of a `.tar.zst` task artifact into `dir`.
+pub fn extract_artifact(
+    archive: &[u8],
+    dir: &Path,
+    runtime: &TaskRuntime,
+) -> anyhow::Result<PathBuf> {
+    let mut decoder = zstd::stream::read::Decoder::new(Cursor::new(archive))?;
+    let mut archive = tar::Archive::new(&mut decoder);
+    let mut artifact_path = None;
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_path_buf();
+        if path.file_name().is_some() {
+            let name = path.file_name().unwrap().to_string_lossy();
+            let is_match = match runtime {
+                TaskRuntime::Native => {
+                    name.ends_with(".so") || name.ends_with(".dylib") || name.ends_with(".dll")
+                }
+                TaskRuntime::Wasm => name.ends_with(".wasm"),
+            };
+            if is_match {
+                let out_path = dir.join(name.as_ref());
+                entry.unpack(&out_path)?;
+                artifact_path = Some(out_path);
+            }
+        }
+    }
+
+    let kind = match runtime {
+        TaskRuntime::Native => "native library",
+        TaskRuntime::Wasm => "wasm module",
+    };
+    artifact_path.ok_or_else(|| anyhow::anyhow!("{kind} not found in artifact"))
+}
+
+/// Read the `manifest.json` packed into a `.tar.zst` task artifact.
+pub fn read_manifest(archive: &[u8]) -> anyhow::Result<TaskManifest> {
+    let mut decoder = zstd::stream::read::Decoder::new(Cursor::new(archive))?;
+    let mut archive = tar::Archive::new(&mut decoder);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let is_manifest = entry
+            .path()?
+            .file_name()
+            .is_some_and(|name| name == "manifest.json");
+        if is_manifest {
+            let mut contents = String::new();
+            entry.read_to_string(&mut contents)?;
+            return Ok(serde_json::from_str(&contents)?);
+        }
+    }
+    Err(anyhow::anyhow!("manifest.json not found in artifact"))
+}
+
+/// The batch policy the library or module of a batch task declares with
+/// `#[task(batch, batch_size = .., max_wait_ms = ..)]`; see
+/// `namu_core::ffi`. A `max_wait_ms` left unset keeps the default linger.
+pub fn declared_batch_policy(path: &Path, runtime: &TaskRuntime) -> anyhow::Result<BatchPolicy> {
+    let names = ["namu_task_batch_size", "namu_task_max_wait_ms"];
+    let declared = match runtime {
+        TaskRuntime::Native => native_pool::read_u64_exports(path, &names)?,
+        TaskRuntime::Wasm => wasm_executor::read_u64_exports(path, &names)?,
+    };
+    let mut policy = BatchPolicy::default();
+    if let Some(max_size) = declared[0] {
+        policy.max_size = usize::try_from(max_size)?.max(1);
+    }
+    if let Some(linger_ms) = declared[1] {
+        policy.linger_ms = linger_ms;
+    }
+    Ok(policy)
+}
@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use namu_proto::{BatchPolicy, TaskError};
+use serde_json::Value as JsonValue;
+use tokio::sync::{Notify, oneshot};
+
+/// The result of one call, as [`crate::executor::WorkerExecutor`] returns it.
+pub type CallResult = Result<JsonValue, TaskError>;
+
+type Waiter = oneshot::Sender<Result<CallResult, String>>;
+
+/// Groups concurrent calls of the same batch task, keyed by
+/// `task_id@version`, so they reach the artifact as one call.
+///
+/// The first call of a batch leads it: it waits up to `linger_ms`, or until
+/// `max_size` calls have joined, then runs the whole batch and hands every
+/// call its own result.
+#[derive(Default)]
+pub struct Batcher {
+    open: Mutex<HashMap<String, Arc<OpenBatch>>>,
+}
+
+#[derive(Default)]
+struct OpenBatch {
+    calls: Mutex<Vec<(JsonValue, Waiter)>>,
+    full: Notify,
+}
+
+impl Batcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add one call to the open batch for `key` and wait for its result.
+    ///
+    /// Only the leading call runs `run`, with every input of the batch; it
+    /// must return one result per input. An error from `run` is returned to
+    /// every call of the batch.
+    pub async fn call<F, Fut>(
+        &self,
+        key: &str,
+        policy: &BatchPolicy,
+        input: JsonValue,
+        run: F,
+    ) -> anyhow::Result<CallResult>
+    where
+        F: FnOnce(Vec<JsonValue>) -> Fut,
+        Fut: Future<Output = anyhow::Result<Vec<CallResult>>>,
+    {
+        let (tx, rx) = oneshot::channel();
+        let (batch, leader) = {
+            let mut open = self.open.lock().expect("open batches poisoned");
+            let (batch, leader) = match open.get(key) {
+                Some(batch) => (batch.clone(), false),
+                None => {
+                    let batch = Arc::new(OpenBatch::default());
+                    open.insert(key.to_string(), batch.clone());
+                    (batch, true)
+                }
+            };
+            let mut calls = batch.calls.lock().expect("batch calls poisoned");
+            calls.push((input, tx));
+            if calls.len() >= policy.max_size {
+                open.remove(key);
+                batch.full.notify_one();
+            }
+            drop(calls);
+            (batch, leader)
+        };
+
+        if leader {
+            let linger = Duration::from_millis(policy.linger_ms);
+            let _ = tokio::time::timeout(linger, batch.full.notified()).await;
+            {
+                let mut open = self.open.lock().expect("open batches poisoned");
+                if open.get(key).is_some_and(|open| Arc::ptr_eq(open, &batch)) {
+                    open.remove(key);
+                }
+            }
+
+            let calls = std::mem::take(&mut *batch.calls.lock().expect("batch calls poisoned"));
+            let (inputs, waiters): (Vec<_>, Vec<_>) = calls.into_iter().unzip();
+            match run(inputs).await {
+                Ok(results) => {
+                    for (waiter, result) in waiters.into_iter().zip(results) {
+                        let _ = waiter.send(Ok(result));
+                    }
+                }
+                Err(err) => {
+                    let message = format!("{err:#}");
+                    for waiter in waiters {
+                        let _ = waiter.send(Err(message.clone()));
+                    }
+                }
+            }
+        }
+
+        match rx.await {
+            Ok(Ok(result)) => Ok(result),
+            Ok(Err(message)) => Err(anyhow::anyhow!(message)),
+            Err(_) => Err(anyhow::anyhow!("batch for {key} was dropped")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// Call `batcher` with `input` under `key`, recording the size of each
+    /// batch run in `sizes`; every call gets its input times ten.
+    async fn call(
+        batcher: &Batcher,
+        key: &str,
+        policy: &BatchPolicy,
+        input: i64,
+        sizes: &Mutex<Vec<usize>>,
+    ) -> anyhow::Result<CallResult> {
+        batcher
+            .call(key, policy, json!(input), |inputs| async move {
+                sizes.lock().unwrap().push(inputs.len());
+                Ok(inputs
+                    .iter()
+                    .map(|input| Ok(json!(input.as_i64().unwrap() * 10)))
+                    .collect())
+            })
+            .await
+    }
+
+    #[tokio::test]
+    async fn full_batch_runs_without_lingering() {
+        let batcher = Batcher::new();
+        let policy = BatchPolicy {
+            max_size: 3,
+            linger_ms: 60_000,
+        };
+        let sizes = Mutex::new(Vec::new());
+        let results = tokio::time::timeout(Duration::from_secs(5), async {
+            tokio::join!(
+                call(&batcher, "add@1", &policy, 1, &sizes),
+                call(&batcher, "add@1", &policy, 2, &sizes),
+                call(&batcher, "add@1", &policy, 3, &sizes),
+            )
+        })
+        .await
+        .expect("a full batch waited for linger_ms");
+
+        assert_eq!(results.0.unwrap(), Ok(json!(10)));
+        assert_eq!(results.1.unwrap(), Ok(json!(20)));
+        assert_eq!(results.2.unwrap(), Ok(json!(30)));
+        assert_eq!(*sizes.lock().unwrap(), vec![3]);
+    }
+
+    #[tokio::test]
+    async fn calls_beyond_max_size_start_another_batch() {
+        let batcher = Batcher::new();
+        let policy = BatchPolicy {
+            max_size: 2,
+            linger_ms: 20,
+        };
+        let sizes = Mutex::new(Vec::new());
+        let results = tokio::join!(
+            call(&batcher, "add@1", &policy, 1, &sizes),
+            call(&batcher, "add@1", &policy, 2, &sizes),
+            call(&batcher, "add@1", &policy, 3, &sizes),
+            call(&batcher, "add@1", &policy, 4, &sizes),
+            call(&batcher, "add@1", &policy, 5, &sizes),
+        );
+
+        let results = [results.0, results.1, results.2, results.3, results.4];
+        for (input, result) in (1..=5).zip(results) {
+            assert_eq!(result.unwrap(), Ok(json!(input * 10)));
+        }
+        assert_eq!(*sizes.lock().unwrap(), vec![2, 2, 1]);
+    }
+
+    #[tokio::test]
+    async fn lone_call_runs_after_linger() {
+        let batcher = Batcher::new();
+        let policy = BatchPolicy {
+            max_size: 16,
+            linger_ms: 20,
+        };
+        let sizes = Mutex::new(Vec::new());
+        let started = tokio::time::Instant::now();
+        let result = call(&batcher, "add@1", &policy, 4, &sizes).await.unwrap();
+
+        assert_eq!(result, Ok(json!(40)));
+        assert!(started.elapsed() >= Duration::from_millis(20));
+        // The batch closed, so the next call leads a new one.
+        call(&batcher, "add@1", &policy, 5, &sizes)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(*sizes.lock().unwrap(), vec![1, 1]);
+    }
+
+    #[tokio::test]
+    async fn calls_of_other_tasks_are_batched_apart() {
+        let batcher = Batcher::new();
+        let policy = BatchPolicy {
+            max_size: 2,
+            linger_ms: 20,
+        };
+        let sizes = Mutex::new(Vec::new());
+        let (a, b) = tokio::join!(
+            call(&batcher, "add@1", &policy, 1, &sizes),
+            call(&batcher, "add@2", &policy, 2, &sizes),
+        );
+
+        assert_eq!(a.unwrap(), Ok(json!(10)));
+        assert_eq!(b.unwrap(), Ok(json!(20)));
+        assert_eq!(*sizes.lock().unwrap(), vec![1, 1]);
+    }
+
+    #[tokio::test]
+    async fn run_error_reaches_every_call() {
+        let batcher = Batcher::new();
+        let policy = BatchPolicy {
+            max_size: 2,
+            linger_ms: 60_000,
+        };
+        let run = |_| async { Err(anyhow::anyhow!("artifact crashed")) };
+        let (a, b) = tokio::join!(
+            batcher.call("add@1", &policy, json!(1), run),
+            batcher.call("add@1", &policy, json!(2), run),
+        );
+
+        assert_eq!(a.unwrap_err().to_string(), "artifact crashed");
+        assert_eq!(b.unwrap_err().to_string(), "artifact crashed");
+    }
+}
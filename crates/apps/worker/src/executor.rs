@@ -0,0 +1,165 @@
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use namu_engine::engine::WorkerEngine;
+use namu_proto::{TaskError, TaskManifest, TaskRuntime};
+use serde_json::Value as JsonValue;
+
+use crate::native_pool::NativePool;
+use crate::wasm_executor::WasmRuntime;
+
+/// Runs task calls on the blocking pool so slow tasks do not stall the
+/// messages running next to them.
+pub struct WorkerExecutor {
+    native: Mutex<NativePool>,
+    wasm: Arc<WasmRuntime>,
+}
+
+impl WorkerExecutor {
+    /// `native_pool_size` and `native_idle` bound the native libraries kept
+    /// loaded; see [`NativePool`].
+    pub fn new(native_pool_size: usize, native_idle: Duration) -> anyhow::Result<Self> {
+        Ok(Self {
+            native: Mutex::new(NativePool::new(native_pool_size, native_idle)),
+            wasm: Arc::new(WasmRuntime::new()?),
+        })
+    }
+
+    /// Run the calls of a batch task as one artifact call, returning one
+    /// result per input.
+    pub async fn execute_batch(
+        &self,
+        manifest: &TaskManifest,
+        artifact_path: &Path,
+        inputs: Vec<JsonValue>,
+    ) -> anyhow::Result<Vec<Result<JsonValue, TaskError>>> {
+        let count = inputs.len();
+        let output = self
+            .execute(manifest, artifact_path, &JsonValue::Array(inputs))
+            .await?;
+        Ok(split_batch_output(output, count))
+    }
+
+    /// Run one call that stops early once `cancelled` is set. Only wasm
+    /// calls can be stopped; a native call runs to completion either way.
+    pub async fn execute_cancellable(
+        &self,
+        manifest: &TaskManifest,
+        artifact_path: &Path,
+        input: &JsonValue,
+        cancelled: Arc<AtomicBool>,
+    ) -> anyhow::Result<Result<JsonValue, TaskError>> {
+        let key = format!("{}@{}", manifest.task_id, manifest.version);
+        let input = input.clone();
+        match manifest.runtime {
+            TaskRuntime::Native => {
+                let task = self
+                    .native
+                    .lock()
+                    .expect("native pool poisoned")
+                    .get_or_load(&key, artifact_path)?;
+                let max_output_bytes = manifest.limits.max_output_bytes;
+                tokio::task::spawn_blocking(move || task.call(&input, max_output_bytes)).await?
+            }
+            TaskRuntime::Wasm => {
+                let wasm = self.wasm.clone();
+                let path = artifact_path.to_path_buf();
+                let limits = manifest.limits.clone();
+                tokio::task::spawn_blocking(move || {
+                    wasm.call(&key, &path, &limits, &input, &cancelled)
+                })
+                .await?
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl WorkerEngine for WorkerExecutor {
+    type Value = JsonValue;
+
+    async fn execute(
+        &self,
+        manifest: &TaskManifest,
+        artifact_path: &Path,
+        input: &Self::Value,
+    ) -> anyhow::Result<Result<Self::Value, TaskError>> {
+        self.execute_cancellable(manifest, artifact_path, input, Arc::default())
+            .await
+    }
+}
+
+/// Shape call inputs the way the task's exported ABI decodes them.
+pub fn build_input_json(manifest: &TaskManifest, inputs: Vec<JsonValue>) -> JsonValue {
+    if manifest.input_arity == 1 {
+        inputs.into_iter().next().unwrap_or(JsonValue::Null)
+    } else {
+        JsonValue::Array(inputs)
+    }
+}
+
+/// Split a batch call's output into `count` results. A failure of the whole
+/// call fails every input.
+fn split_batch_output(
+    output: Result<JsonValue, TaskError>,
+    count: usize,
+) -> Vec<Result<JsonValue, TaskError>> {
+    let items = match output {
+        Ok(JsonValue::Array(items)) if items.len() == count => items,
+        Ok(_) => {
+            let err = TaskError::fatal(format!(
+                "batch task output must be an array of {count} results"
+            ));
+            return vec![Err(err); count];
+        }
+        Err(err) => return vec![Err(err); count],
+    };
+    items
+        .into_iter()
+        .map(|mut item| match item.get_mut("ok") {
+            Some(output) => Ok(output.take()),
+            None => Err(TaskError::from_envelope(&item)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use namu_proto::TaskErrorKind;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn splits_envelopes_into_results() {
+        let output = json!([
+            {"ok": 1},
+            {"error": {"kind": "invalid_input", "message": "negative"}},
+        ]);
+        let results = split_batch_output(Ok(output), 2);
+
+        assert_eq!(results[0], Ok(json!(1)));
+        assert_eq!(results[1], Err(TaskError::invalid_input("negative")),);
+    }
+
+    #[test]
+    fn wrong_result_count_fails_every_call() {
+        let results = split_batch_output(Ok(json!([{"ok": 1}])), 2);
+
+        assert_eq!(results.len(), 2);
+        for result in results {
+            assert_eq!(result.unwrap_err().kind, TaskErrorKind::Fatal);
+        }
+    }
+
+    #[test]
+    fn batch_error_fails_every_call() {
+        let error = TaskError::retryable("artifact crashed");
+        let results = split_batch_output(Err(error.clone()), 3);
+
+        assert_eq!(results, vec![Err(error); 3]);
+    }
+}
@@ -0,0 +1,8 @@
+//! Task execution shared by the `namu-worker` binary and local runs.
+
+pub mod artifact;
+pub mod batcher;
+pub mod executor;
+pub mod local;
+pub mod native_pool;
+pub mod wasm_executor;
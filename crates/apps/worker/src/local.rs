@@ -0,0 +1,531 @@
+//! Run workflows from a `namu build` output directory in-process.
+//!
+//! Values live in an [`InMemoryStore`] and calls go through the same
+//! [`WorkerExecutor`] `namu-worker` uses, so built artifacts can be exercised
+//! without Postgres, Redis or `namu-master`.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Context;
+use itertools::Itertools;
+use namu_core::ContextId;
+use namu_core::ir::{Next, Workflow};
+use namu_engine::engine::WorkerEngine;
+use namu_engine::kernel::{CallSpec, EngineKernel, JsonRuntime, KernelPlan, ValueStore};
+use namu_engine::runtime::graph::ContextGraph;
+use namu_engine::store::InMemoryStore;
+use namu_proto::{
+    LeafFailure, LeafResult, RunResultResponse, TaskError, TaskKind, TaskManifest,
+    WorkflowUploadRequest,
+};
+use serde_json::Value as JsonValue;
+
+use crate::artifact;
+use crate::executor::{WorkerExecutor, build_input_json};
+
+/// How long an unused native library stays loaded during a local run.
+const NATIVE_IDLE: Duration = Duration::from_secs(600);
+
+pub struct LocalRunner {
+    executor: WorkerExecutor,
+    /// By `task_id@version`.
+    tasks: HashMap<String, LocalTask>,
+    /// By `workflow_id@version`.
+    workflows: HashMap<String, LocalWorkflow>,
+}
+
+struct LocalTask {
+    manifest: TaskManifest,
+    artifact_path: PathBuf,
+}
+
+struct LocalWorkflow {
+    workflow: Workflow,
+    task_versions: HashMap<String, String>,
+}
+
+/// Where to continue driving a context, as the master does after a call.
+struct Resume {
+    ctx_id: ContextId,
+    op_id: usize,
+    pred_op: Option<usize>,
+    done: Vec<usize>,
+}
+
+/// State of one local run.
+struct LocalRun<'a> {
+    workflow: &'a LocalWorkflow,
+    kernel: EngineKernel<JsonRuntime>,
+    store: InMemoryStore<JsonValue>,
+    pending: VecDeque<Resume>,
+    results: Vec<LeafResult>,
+    failures: Vec<LeafFailure>,
+    /// Items of collected stream calls: item context to the context the
+    /// stream ran in and the stream's op.
+    fan_items: HashMap<ContextId, (ContextId, usize)>,
+    /// Collects waiting for items, by the context the stream ran in.
+    fan_ins: HashMap<ContextId, FanIn>,
+}
+
+struct FanIn {
+    items: usize,
+    values: Vec<(ContextId, JsonValue)>,
+}
+
+impl LocalRunner {
+    /// Load the task artifacts in `dist_dir/tasks` and the workflows in
+    /// `dist_dir/workflows`, unpacking artifacts under `work_dir`.
+    pub fn load(dist_dir: &Path, work_dir: &Path) -> anyhow::Result<Self> {
+        let mut tasks = HashMap::new();
+        for path in dir_files(&dist_dir.join("tasks"), "zst")? {
+            let bytes = std::fs::read(&path)?;
+            let manifest = artifact::read_manifest(&bytes)
+                .with_context(|| format!("invalid task artifact {}", path.display()))?;
+            let dir = work_dir.join(&manifest.task_id).join(&manifest.version);
+            std::fs::create_dir_all(&dir)?;
+            let artifact_path = artifact::extract_artifact(&bytes, &dir, &manifest.runtime)?;
+            tasks.insert(
+                format!("{}@{}", manifest.task_id, manifest.version),
+                LocalTask {
+                    manifest,
+                    artifact_path,
+                },
+            );
+        }
+
+        let mut workflows = HashMap::new();
+        for path in dir_files(&dist_dir.join("workflows"), "json")? {
+            let raw = std::fs::read_to_string(&path)?;
+            let req: WorkflowUploadRequest = serde_json::from_str(&raw)
+                .with_context(|| format!("invalid workflow file {}", path.display()))?;
+            let workflow: Workflow = serde_json::from_value(req.ir)?;
+            workflows.insert(
+                format!("{}@{}", req.id, req.version),
+                LocalWorkflow {
+                    workflow,
+                    task_versions: req.task_versions,
+                },
+            );
+        }
+
+        Ok(Self {
+            executor: WorkerExecutor::new(tasks.len(), NATIVE_IDLE)?,
+            tasks,
+            workflows,
+        })
+    }
+
+    /// Run a workflow to completion and report it like `GET /runs/{id}/result`.
+    ///
+    /// A failed call stops only the contexts that depend on it; it is listed
+    /// in `failures` and the run ends as `partial_failed`.
+    pub async fn run(
+        &self,
+        workflow_id: &str,
+        version: &str,
+        inputs: &serde_json::Map<String, JsonValue>,
+    ) -> anyhow::Result<RunResultResponse> {
+        let workflow = self
+            .workflows
+            .get(&format!("{workflow_id}@{version}"))
+            .ok_or_else(|| anyhow::anyhow!("workflow {workflow_id}@{version} not found"))?;
+        let inputs = JsonRuntime::bind_inputs(&workflow.workflow, inputs)?;
+
+        let mut run = LocalRun {
+            workflow,
+            kernel: EngineKernel::new(JsonRuntime),
+            store: InMemoryStore::new(),
+            pending: VecDeque::new(),
+            results: Vec::new(),
+            failures: Vec::new(),
+            fan_items: HashMap::new(),
+            fan_ins: HashMap::new(),
+        };
+        let root = run.store.create_root();
+        let root = run
+            .kernel
+            .seed_inputs(&workflow.workflow, &run.store, root, inputs)
+            .await?;
+        run.pending.push_back(Resume {
+            ctx_id: root,
+            op_id: 0,
+            pred_op: None,
+            done: Vec::new(),
+        });
+
+        while let Some(resume) = run.pending.pop_front() {
+            let mut done = resume.done;
+            let plans = run
+                .kernel
+                .drive_until_actions(
+                    &workflow.workflow,
+                    &run.store,
+                    resume.ctx_id,
+                    resume.op_id,
+                    resume.pred_op,
+                    &mut done,
+                )
+                .await?;
+            if plans.len() > 1 {
+                self.run_group(&mut run, resume.ctx_id, plans, done).await?;
+                continue;
+            }
+            match plans.into_iter().next() {
+                Some(KernelPlan::Dispatch {
+                    op_id,
+                    ctx_id,
+                    call,
+                }) => self.run_call(&mut run, op_id, ctx_id, &call, done).await?,
+                Some(KernelPlan::Return { ctx_id, return_var }) => {
+                    run.record_result(ctx_id, return_var).await?;
+                }
+                Some(KernelPlan::Collect { op_id, ctx_id, var }) => {
+                    run.arrive_at_collect(op_id, ctx_id, var).await?;
+                }
+                None => {}
+            }
+        }
+
+        let status = if run.failures.is_empty() {
+            "succeeded"
+        } else {
+            "partial_failed"
+        };
+        Ok(RunResultResponse {
+            status: status.to_string(),
+            results: run.results,
+            failures: run.failures,
+        })
+    }
+
+    async fn run_call(
+        &self,
+        run: &mut LocalRun<'_>,
+        op_id: usize,
+        ctx_id: ContextId,
+        call: &CallSpec,
+        done: Vec<usize>,
+    ) -> anyhow::Result<()> {
+        let output = match self.execute(run, ctx_id, call).await? {
+            Ok(output) => output,
+            Err(error) => {
+                run.failures.push(LeafFailure {
+                    ctx_id,
+                    op_id,
+                    error: Some(error),
+                });
+                return Ok(());
+            }
+        };
+
+        match output {
+            CallOutput::Stream(items) => {
+                let collect_op = run.workflow.workflow.collect_of(op_id);
+                if let Some(collect_op) = collect_op {
+                    if items.is_empty() {
+                        return run.gather(ctx_id, collect_op, Vec::new()).await;
+                    }
+                    run.fan_ins.insert(
+                        ctx_id,
+                        FanIn {
+                            items: items.len(),
+                            values: Vec::new(),
+                        },
+                    );
+                }
+                for item in items {
+                    let child_ctx = run.store.create_child(ctx_id);
+                    if collect_op.is_some() {
+                        run.fan_items.insert(child_ctx, (ctx_id, op_id));
+                    }
+                    run.store_outputs(child_ctx, &call.outputs, &item).await?;
+                    run.continue_after(child_ctx, op_id, &done).await?;
+                }
+            }
+            CallOutput::Single(output) => {
+                run.store_outputs(ctx_id, &call.outputs, &output).await?;
+                run.continue_after(ctx_id, op_id, &done).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Run independent calls and resume once per combination of their
+    /// outputs; a failed member leaves nothing to combine, so the members
+    /// after it are not run.
+    async fn run_group(
+        &self,
+        run: &mut LocalRun<'_>,
+        ctx_id: ContextId,
+        plans: Vec<KernelPlan>,
+        done: Vec<usize>,
+    ) -> anyhow::Result<()> {
+        let mut op_ids = Vec::with_capacity(plans.len());
+        let mut members = Vec::with_capacity(plans.len());
+        for plan in plans {
+            let KernelPlan::Dispatch { op_id, call, .. } = plan else {
+                unreachable!("return plans are never grouped");
+            };
+            op_ids.push(op_id);
+            match self.execute(run, ctx_id, &call).await? {
+                Ok(output) => members.push((call.outputs, output)),
+                Err(error) => {
+                    run.failures.push(LeafFailure {
+                        ctx_id,
+                        op_id,
+                        error: Some(error),
+                    });
+                    return Ok(());
+                }
+            }
+        }
+
+        let done = done.iter().chain(&op_ids).copied().collect::<Vec<_>>();
+        let streamed = members
+            .iter()
+            .any(|(_, output)| matches!(output, CallOutput::Stream(_)));
+        let resume_ctx = |ctx_id| Resume {
+            ctx_id,
+            op_id: op_ids[0],
+            pred_op: None,
+            done: done.clone(),
+        };
+
+        if !streamed {
+            for (outputs, output) in &members {
+                run.store_outputs(ctx_id, outputs, output.items()[0])
+                    .await?;
+            }
+            run.pending.push_back(resume_ctx(ctx_id));
+            return Ok(());
+        }
+
+        let combinations = members
+            .iter()
+            .map(|(_, output)| output.items().into_iter())
+            .multi_cartesian_product()
+            .collect::<Vec<_>>();
+        for combination in combinations {
+            let child_ctx = run.store.create_child(ctx_id);
+            for ((outputs, _), item) in members.iter().zip(combination) {
+                run.store_outputs(child_ctx, outputs, item).await?;
+            }
+            run.pending.push_back(resume_ctx(child_ctx));
+        }
+        Ok(())
+    }
+
+    /// Execute one call; the inner error is the task's own failure.
+    async fn execute(
+        &self,
+        run: &LocalRun<'_>,
+        ctx_id: ContextId,
+        call: &CallSpec,
+    ) -> anyhow::Result<Result<CallOutput, TaskError>> {
+        let task_version = run
+            .workflow
+            .task_versions
+            .get(&call.task_id)
+            .ok_or_else(|| anyhow::anyhow!("missing task version for {}", call.task_id))?;
+        let task = self
+            .tasks
+            .get(&format!("{}@{}", call.task_id, task_version))
+            .ok_or_else(|| anyhow::anyhow!("task {}@{task_version} not found", call.task_id))?;
+
+        let inputs = run.store.get_values(ctx_id, &call.inputs).await?;
+        let input_json = build_input_json(&task.manifest, inputs);
+        let output = if task.manifest.task_kind == TaskKind::Batch {
+            self.executor
+                .execute_batch(&task.manifest, &task.artifact_path, vec![input_json])
+                .await?
+                .remove(0)
+        } else {
+            self.executor
+                .execute(&task.manifest, &task.artifact_path, &input_json)
+                .await?
+        };
+        let output = match output {
+            Ok(output) => output,
+            Err(error) => return Ok(Err(error)),
+        };
+
+        if task.manifest.task_kind == TaskKind::Stream {
+            let JsonValue::Array(items) = output else {
+                return Ok(Err(TaskError::fatal("stream task output must be array")));
+            };
+            Ok(Ok(CallOutput::Stream(items)))
+        } else {
+            Ok(Ok(CallOutput::Single(output)))
+        }
+    }
+}
+
+enum CallOutput {
+    Single(JsonValue),
+    Stream(Vec<JsonValue>),
+}
+
+impl CallOutput {
+    fn items(&self) -> Vec<&JsonValue> {
+        match self {
+            CallOutput::Single(output) => vec![output],
+            CallOutput::Stream(items) => items.iter().collect(),
+        }
+    }
+}
+
+impl LocalRun<'_> {
+    /// Move past the call or collect at `op_id`, or record the result if it
+    /// returns.
+    async fn continue_after(
+        &mut self,
+        ctx_id: ContextId,
+        op_id: usize,
+        done: &[usize],
+    ) -> anyhow::Result<()> {
+        let next = &self.workflow.workflow.operations[op_id].next;
+        match self.kernel.resolve_next(&self.store, ctx_id, next).await? {
+            Some(next_op) => self.pending.push_back(Resume {
+                ctx_id,
+                op_id: next_op,
+                pred_op: Some(op_id),
+                done: done.to_vec(),
+            }),
+            None => {
+                let return_var = match next {
+                    Next::Return { var } => *var,
+                    _ => None,
+                };
+                self.record_result(ctx_id, return_var).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Hand `var` of `ctx_id` to the collect at `op_id`; the last item of
+    /// the fan-out continues after it.
+    async fn arrive_at_collect(
+        &mut self,
+        op_id: usize,
+        ctx_id: ContextId,
+        var: usize,
+    ) -> anyhow::Result<()> {
+        let from = self.workflow.workflow.operations[op_id]
+            .collect
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("op {op_id} has no collect"))?
+            .from;
+        let (item_ctx, fan_ctx) = self.fan_out_item(ctx_id, from).await?;
+        let value = self.store.get_value(ctx_id, var).await?;
+        let fan_in = self
+            .fan_ins
+            .get_mut(&fan_ctx)
+            .ok_or_else(|| anyhow::anyhow!("no collect waits in context {fan_ctx}"))?;
+        fan_in.values.push((item_ctx, value));
+        if fan_in.values.len() < fan_in.items {
+            return Ok(());
+        }
+        let fan_in = self.fan_ins.remove(&fan_ctx).expect("fan-in present");
+        self.gather(fan_ctx, op_id, fan_in.values).await
+    }
+
+    /// The item of the fan-out by `from` that `ctx_id` belongs to, and the
+    /// context that fan-out started in.
+    async fn fan_out_item(
+        &self,
+        ctx_id: ContextId,
+        from: usize,
+    ) -> anyhow::Result<(ContextId, ContextId)> {
+        let mut cursor = Some(ctx_id);
+        while let Some(ctx) = cursor {
+            if let Some(&(fan_ctx, op_id)) = self.fan_items.get(&ctx)
+                && op_id == from
+            {
+                return Ok((ctx, fan_ctx));
+            }
+            cursor = self.store.parent(ctx).await?;
+        }
+        Err(anyhow::anyhow!(
+            "context {ctx_id} reached a collect outside the fan-out of op {from}"
+        ))
+    }
+
+    /// Continue after the collect at `op_id` in a new child of `fan_ctx`,
+    /// with the values in context order.
+    async fn gather(
+        &mut self,
+        fan_ctx: ContextId,
+        op_id: usize,
+        values: Vec<(ContextId, JsonValue)>,
+    ) -> anyhow::Result<()> {
+        let values = values
+            .into_iter()
+            .sorted_by_key(|(item_ctx, _)| *item_ctx)
+            .map(|(_, value)| value)
+            .collect();
+        let ctx_id = self.store.create_child(fan_ctx);
+        let ctx_id = self
+            .kernel
+            .bind_collected(&self.workflow.workflow, &self.store, ctx_id, op_id, values)
+            .await?;
+        self.continue_after(ctx_id, op_id, &[]).await
+    }
+
+    async fn record_result(
+        &mut self,
+        ctx_id: ContextId,
+        return_var: Option<usize>,
+    ) -> anyhow::Result<()> {
+        let value = match return_var {
+            Some(var) => self.store.get_value(ctx_id, var).await?,
+            None => JsonValue::Null,
+        };
+        self.results.push(LeafResult { ctx_id, value });
+        Ok(())
+    }
+
+    async fn store_outputs(
+        &self,
+        ctx_id: ContextId,
+        outputs: &[usize],
+        output: &JsonValue,
+    ) -> anyhow::Result<()> {
+        if outputs.len() == 1 {
+            self.store
+                .set_value(ctx_id, outputs[0], output.clone())
+                .await?;
+            return Ok(());
+        }
+        if outputs.is_empty() {
+            return Ok(());
+        }
+        let values = output
+            .as_array()
+            .ok_or_else(|| anyhow::anyhow!("output must be array for multiple outputs"))?;
+        if values.len() != outputs.len() {
+            return Err(anyhow::anyhow!("output arity mismatch"));
+        }
+        for (out_id, value) in outputs.iter().zip(values) {
+            self.store.set_value(ctx_id, *out_id, value.clone()).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Files in `dir` with `extension`, sorted; empty if `dir` does not exist.
+fn dir_files(dir: &Path, extension: &str) -> anyhow::Result<Vec<PathBuf>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) == Some(extension) {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
@@ -1,22 +1,31 @@
 use std::collections::{HashMap, VecDeque};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use anyhow::Context;
-use async_trait::async_trait;
-use libloading::Library;
-use namu_engine::engine::WorkerEngine;
-use namu_proto::{QueueMessage, TaskCompleteRequest, TaskManifest, TaskRuntime, TaskStartRequest};
+use namu_proto::{
+    QueueMessage, RunStatusResponse, TaskCompleteRequest, TaskError, TaskKind, TaskManifest,
+    TaskRuntime, TaskStartRequest,
+};
+use namu_worker::artifact;
+use namu_worker::batcher::{Batcher, CallResult};
+use namu_worker::executor::{WorkerExecutor, build_input_json};
 use redis::AsyncCommands;
 use redis::aio::ConnectionManager;
 use serde_json::Value as JsonValue;
-use tracing::{error, info};
+use tokio::sync::{OnceCell, OwnedSemaphorePermit, Semaphore};
+use tracing::{error, info, warn};
 use uuid::Uuid;
 
 mod object_store;
-mod wasm_executor;
 
 const MAX_PARENT_HOPS: usize = 10_000;
+/// Tries at reporting a call's result before leaving its message unacked.
+const REPORT_ATTEMPTS: u32 = 5;
+/// How often a running call checks whether its run was cancelled.
+const CANCEL_POLL: Duration = Duration::from_secs(1);
 
 struct CachedValue {
     value: JsonValue,
@@ -86,20 +95,21 @@ impl ValueCache {
     }
 }
 
-struct WorkerExecutor;
-
-#[async_trait]
-impl WorkerEngine for WorkerExecutor {
-    type Value = JsonValue;
-
-    async fn execute(
-        &self,
-        manifest: &TaskManifest,
-        artifact_path: &Path,
-        input: &Self::Value,
-    ) -> anyhow::Result<Result<Self::Value, String>> {
-        call_task(&manifest.runtime, artifact_path, input)
-    }
+/// State shared by every message this worker handles.
+struct Worker {
+    client: reqwest::Client,
+    orchestrator_url: String,
+    cache_dir: PathBuf,
+    object_store: Option<object_store::ObjectStore>,
+    value_cache: Mutex<ValueCache>,
+    /// Extracted artifacts by `task_id@version`. Messages of one task wait
+    /// for a single fetch; other tasks are not held up by it.
+    artifacts: Mutex<HashMap<String, Arc<OnceCell<PathBuf>>>>,
+    executor: WorkerExecutor,
+    /// Calls of batch tasks waiting to run together.
+    batcher: Batcher,
+    /// Bounds the calls running at once; see `NAMU_WORKER_CONCURRENCY`.
+    slots: Arc<Semaphore>,
 }
 
 #[tokio::main]
@@ -120,6 +130,23 @@ async fn main() -> anyhow::Result<()> {
         .ok()
         .and_then(|v| v.parse::<usize>().ok())
         .unwrap_or(268_435_456);
+    let native_pool_size = std::env::var("NAMU_NATIVE_POOL_SIZE")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(32);
+    let native_idle_secs = std::env::var("NAMU_NATIVE_IDLE_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(600);
+    let concurrency = std::env::var("NAMU_WORKER_CONCURRENCY")
+        .ok()
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+        })
+        .max(1);
     let object_store = object_store::ObjectStore::from_env().await?;
 
     let client = reqwest::Client::new();
@@ -142,30 +169,59 @@ async fn main() -> anyhow::Result<()> {
 
     let cache_dir = PathBuf::from(cache_dir);
     tokio::fs::create_dir_all(&cache_dir).await?;
-    let mut value_cache = ValueCache::new(value_cache_bytes);
-
+    let slots = Arc::new(Semaphore::new(concurrency));
+    let worker = Arc::new(Worker {
+        client,
+        orchestrator_url,
+        cache_dir,
+        object_store,
+        value_cache: Mutex::new(ValueCache::new(value_cache_bytes)),
+        artifacts: Mutex::new(HashMap::new()),
+        executor: WorkerExecutor::new(native_pool_size, Duration::from_secs(native_idle_secs))?,
+        batcher: Batcher::new(),
+        slots: slots.clone(),
+    });
+    info!("running up to {concurrency} tasks at once");
+
+    // A message is only read once a slot is free, so at most `concurrency`
+    // messages are in flight and the rest stay in the stream for other workers.
+    // Calls waiting for a batch to fill give their slot back, so the worker
+    // keeps reading until the batch is full or lingered long enough.
     loop {
+        let permit = slots.clone().acquire_owned().await?;
         match read_one(&mut redis, &stream, &group, &worker_id).await {
             Ok(Some((message_id, payload))) => {
-                if let Err(err) = handle_message(
-                    &client,
-                    &orchestrator_url,
-                    &mut redis,
-                    &cache_dir,
-                    &mut value_cache,
-                    object_store.as_ref(),
-                    &payload,
-                )
-                .await
-                {
-                    error!("task failed: {err}");
-                }
-                let _: () = redis::cmd("XACK")
-                    .arg(&stream)
-                    .arg(&group)
-                    .arg(&message_id)
-                    .query_async(&mut redis)
-                    .await?;
+                let worker = worker.clone();
+                let mut redis = redis.clone();
+                let stream = stream.clone();
+                let group = group.clone();
+                tokio::spawn(async move {
+                    let handled = match handle_message(&worker, &mut redis, &payload, permit).await
+                    {
+                        Ok(()) => Ok(()),
+                        Err(MessageError::Setup(err)) => {
+                            error!("could not run {message_id}: {err:#}");
+                            // The master decides whether to retry the call.
+                            let error = TaskError::from_anyhow(&err);
+                            report_result(&worker, &payload, Err(error)).await
+                        }
+                        Err(MessageError::Report(err)) => Err(err),
+                    };
+                    if let Err(err) = handled {
+                        // The master retries the call once its lease expires.
+                        error!("failed to report {message_id}, leaving it unacked: {err:#}");
+                        return;
+                    }
+                    let acked: redis::RedisResult<()> = redis::cmd("XACK")
+                        .arg(&stream)
+                        .arg(&group)
+                        .arg(&message_id)
+                        .query_async(&mut redis)
+                        .await;
+                    if let Err(err) = acked {
+                        error!("failed to ack {message_id}: {err}");
+                    }
+                });
             }
             Ok(None) => continue,
             Err(err) => {
@@ -296,61 +352,213 @@ fn parse_queue_reply(value: redis::Value) -> anyhow::Result<Option<(String, Queu
     Ok(None)
 }
 
+/// Why a message was not handled to the end.
+enum MessageError {
+    /// The call could not be set up or run, e.g. its manifest, artifact or
+    /// inputs could not be fetched; it still has to be reported as failed.
+    Setup(anyhow::Error),
+    /// The call ended but its result could not be reported.
+    Report(anyhow::Error),
+}
+
+/// Run the call of `msg` and report its result.
 async fn handle_message(
-    client: &reqwest::Client,
-    orchestrator_url: &str,
+    worker: &Worker,
     redis: &mut ConnectionManager,
-    cache_dir: &Path,
-    value_cache: &mut ValueCache,
-    object_store: Option<&object_store::ObjectStore>,
     msg: &QueueMessage,
-) -> anyhow::Result<()> {
-    start_task(client, orchestrator_url, msg).await?;
+    permit: OwnedSemaphorePermit,
+) -> Result<(), MessageError> {
+    let Some(result) = run_call(worker, redis, msg, permit)
+        .await
+        .map_err(MessageError::Setup)?
+    else {
+        return Ok(());
+    };
+    if run_cancelled(&worker.client, &worker.orchestrator_url, msg.run_id).await {
+        info!(
+            "dropping result of op {} in ctx {} of run {}: run was cancelled",
+            msg.op_id, msg.ctx_id, msg.run_id
+        );
+        return Ok(());
+    }
+    report_result(worker, msg, result)
+        .await
+        .map_err(MessageError::Report)
+}
+
+/// Run the call of `msg`; `None` when the master told the worker to skip it.
+async fn run_call(
+    worker: &Worker,
+    redis: &mut ConnectionManager,
+    msg: &QueueMessage,
+    permit: OwnedSemaphorePermit,
+) -> anyhow::Result<Option<CallResult>> {
+    let client = &worker.client;
+    let orchestrator_url = worker.orchestrator_url.as_str();
+    if !start_task(client, orchestrator_url, msg).await? {
+        info!(
+            "skipping op {} in ctx {} of run {}: cancelled or already settled",
+            msg.op_id, msg.ctx_id, msg.run_id
+        );
+        return Ok(None);
+    }
 
     let manifest = fetch_manifest(client, orchestrator_url, &msg.task_id, &msg.task_version)
         .await
         .context("fetch manifest")?;
 
-    let artifact_path = ensure_artifact(
-        client,
-        orchestrator_url,
-        cache_dir,
-        &msg.task_id,
-        &msg.task_version,
-        &manifest.runtime,
+    let key = format!("{}@{}", msg.task_id, msg.task_version);
+    let artifact = worker
+        .artifacts
+        .lock()
+        .expect("artifacts poisoned")
+        .entry(key.clone())
+        .or_default()
+        .clone();
+    let artifact_path = artifact
+        .get_or_try_init(|| {
+            ensure_artifact(
+                client,
+                orchestrator_url,
+                &worker.cache_dir,
+                &msg.task_id,
+                &msg.task_version,
+                &manifest.runtime,
+            )
+        })
+        .await?
+        .clone();
+
+    let inputs = resolve_inputs(
+        redis,
+        &worker.value_cache,
+        worker.object_store.as_ref(),
+        msg,
     )
     .await?;
+    let input_json = build_input_json(&manifest, inputs);
+
+    let output = if manifest.task_kind == TaskKind::Batch {
+        drop(permit);
+        worker
+            .batcher
+            .call(&key, &manifest.batch, input_json, |inputs| async {
+                let _slot = worker.slots.acquire().await?;
+                worker
+                    .executor
+                    .execute_batch(&manifest, &artifact_path, inputs)
+                    .await
+            })
+            .await?
+    } else {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let watch = tokio::spawn(watch_cancel(
+            client.clone(),
+            worker.orchestrator_url.clone(),
+            msg.run_id,
+            cancelled.clone(),
+        ));
+        let output = worker
+            .executor
+            .execute_cancellable(&manifest, &artifact_path, &input_json, cancelled)
+            .await;
+        watch.abort();
+        output?
+    };
 
-    let inputs = resolve_inputs(redis, value_cache, object_store, msg).await?;
-    let input_json = build_input_json(&manifest, inputs)?;
+    Ok(Some(output))
+}
 
-    let executor = WorkerExecutor;
-    match executor
-        .execute(&manifest, &artifact_path, &input_json)
-        .await?
-    {
-        Ok(output) => {
-            complete_task(client, orchestrator_url, msg, true, Some(output), None).await?;
+/// Whether `run_id` was cancelled. A failed check counts as not cancelled;
+/// the master ignores reports for cancelled runs anyway.
+async fn run_cancelled(client: &reqwest::Client, orchestrator_url: &str, run_id: Uuid) -> bool {
+    let status = async {
+        client
+            .get(format!("{orchestrator_url}/runs/{run_id}"))
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<RunStatusResponse>()
+            .await
+    };
+    match status.await {
+        Ok(status) => status.status == "cancelled",
+        Err(err) => {
+            warn!("could not check the status of run {run_id}: {err}");
+            false
         }
-        Err(err_json) => {
-            complete_task(client, orchestrator_url, msg, false, None, Some(err_json)).await?;
+    }
+}
+
+/// Set `cancelled` once `run_id` is cancelled, which stops a running wasm
+/// call. Aborted when the call ends.
+async fn watch_cancel(
+    client: reqwest::Client,
+    orchestrator_url: String,
+    run_id: Uuid,
+    cancelled: Arc<AtomicBool>,
+) {
+    loop {
+        tokio::time::sleep(CANCEL_POLL).await;
+        if run_cancelled(&client, &orchestrator_url, run_id).await {
+            cancelled.store(true, Ordering::Relaxed);
+            return;
         }
     }
+}
 
-    Ok(())
+/// Report the result of the call of `msg`, trying again after transient
+/// failures: a call that ran must not run again because one request failed.
+async fn report_result(
+    worker: &Worker,
+    msg: &QueueMessage,
+    result: CallResult,
+) -> anyhow::Result<()> {
+    let (success, output, error) = match result {
+        Ok(output) => (true, Some(output), None),
+        Err(error) => (false, None, Some(error)),
+    };
+    let mut delay = Duration::from_millis(200);
+    let mut attempt = 1;
+    loop {
+        let reported = complete_task(
+            &worker.client,
+            &worker.orchestrator_url,
+            msg,
+            success,
+            output.clone(),
+            error.clone(),
+        )
+        .await;
+        match reported {
+            Err(err) if attempt < REPORT_ATTEMPTS => {
+                warn!(
+                    "reporting op {} in ctx {} of run {} failed, trying again: {err}",
+                    msg.op_id, msg.ctx_id, msg.run_id
+                );
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+            reported => return reported,
+        }
+    }
 }
 
+/// Take the lease on a call; returns `false` when its run was cancelled or
+/// the master no longer wants this attempt.
 async fn start_task(
     client: &reqwest::Client,
     orchestrator_url: &str,
     msg: &QueueMessage,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<bool> {
     let req = TaskStartRequest {
         op_id: msg.op_id,
         ctx_id: msg.ctx_id,
         lease_ms: msg.lease_ms,
+        attempt: msg.attempt,
     };
-    client
+    let res = client
         .post(format!(
             "{orchestrator_url}/runs/{}/nodes/start",
             msg.run_id
@@ -358,8 +566,10 @@ async fn start_task(
         .json(&req)
         .send()
         .await?
-        .error_for_status()?;
-    Ok(())
+        .error_for_status()?
+        .json::<JsonValue>()
+        .await?;
+    Ok(res["status"] == "ok")
 }
 
 async fn complete_task(
@@ -368,7 +578,7 @@ async fn complete_task(
     msg: &QueueMessage,
     success: bool,
     output: Option<JsonValue>,
-    error: Option<String>,
+    error: Option<TaskError>,
 ) -> anyhow::Result<()> {
     let req = TaskCompleteRequest {
         op_id: msg.op_id,
@@ -376,6 +586,7 @@ async fn complete_task(
         success,
         output_json: output,
         error,
+        attempt: msg.attempt,
     };
     client
         .post(format!(
@@ -437,39 +648,12 @@ async fn extract_artifact(
     runtime: &TaskRuntime,
 ) -> anyhow::Result<PathBuf> {
     let data = tokio::fs::read(archive_path).await?;
-    let mut decoder = zstd::stream::read::Decoder::new(std::io::Cursor::new(data))?;
-    let mut archive = tar::Archive::new(&mut decoder);
-    let mut artifact_path = None;
-
-    for entry in archive.entries()? {
-        let mut entry = entry?;
-        let path = entry.path()?.to_path_buf();
-        if path.file_name().is_some() {
-            let name = path.file_name().unwrap().to_string_lossy();
-            let is_match = match runtime {
-                TaskRuntime::Native => {
-                    name.ends_with(".so") || name.ends_with(".dylib") || name.ends_with(".dll")
-                }
-                TaskRuntime::Wasm => name.ends_with(".wasm"),
-            };
-            if is_match {
-                let out_path = dir.join(name.as_ref());
-                entry.unpack(&out_path)?;
-                artifact_path = Some(out_path);
-            }
-        }
-    }
-
-    let kind = match runtime {
-        TaskRuntime::Native => "native library",
-        TaskRuntime::Wasm => "wasm module",
-    };
-    artifact_path.ok_or_else(|| anyhow::anyhow!("{kind} not found in artifact"))
+    artifact::extract_artifact(&data, dir, runtime)
 }
 
 async fn resolve_inputs(
     redis: &mut ConnectionManager,
-    value_cache: &mut ValueCache,
+    value_cache: &Mutex<ValueCache>,
     object_store: Option<&object_store::ObjectStore>,
     msg: &QueueMessage,
 ) -> anyhow::Result<Vec<JsonValue>> {
@@ -478,10 +662,18 @@ async fn resolve_inputs(
     let inline = msg.input_values.as_ref();
     let refs = msg.input_refs.as_ref();
 
+    let cache_insert = |hash: &String, value: &JsonValue, bytes: usize| {
+        value_cache.lock().expect("value cache poisoned").insert(
+            hash.clone(),
+            value.clone(),
+            bytes,
+        );
+    };
+
     for (idx, id) in msg.input_ids.iter().enumerate() {
         if let Some(hashes) = hashes
             && let Some(hash) = hashes.get(idx)
-            && let Some(val) = value_cache.get(hash)
+            && let Some(val) = value_cache.lock().expect("value cache poisoned").get(hash)
         {
             inputs.push(val);
             continue;
@@ -495,7 +687,7 @@ async fn resolve_inputs(
                 && let Some(hash) = hashes.get(idx)
             {
                 let bytes = estimate_json_size(&value);
-                value_cache.insert(hash.clone(), value.clone(), bytes);
+                cache_insert(hash, &value, bytes);
             }
             inputs.push(value);
             continue;
@@ -511,7 +703,7 @@ async fn resolve_inputs(
                     if let Some(hashes) = hashes
                         && let Some(hash) = hashes.get(idx)
                     {
-                        value_cache.insert(hash.clone(), value.clone(), bytes.len());
+                        cache_insert(hash, &value, bytes.len());
                     }
                     inputs.push(value);
                     continue;
@@ -528,7 +720,7 @@ async fn resolve_inputs(
         if let Some(hashes) = hashes
             && let Some(hash) = hashes.get(idx)
         {
-            value_cache.insert(hash.clone(), value.clone(), raw.len());
+            cache_insert(hash, &value, raw.len());
         }
         inputs.push(value);
     }
@@ -588,83 +780,3 @@ async fn get_value_raw(
     }
     Ok(None)
 }
-
-fn build_input_json(manifest: &TaskManifest, inputs: Vec<JsonValue>) -> anyhow::Result<JsonValue> {
-    if manifest.input_arity == 1 {
-        Ok(inputs.into_iter().next().unwrap_or(JsonValue::Null))
-    } else {
-        Ok(JsonValue::Array(inputs))
-    }
-}
-
-fn call_task(
-    runtime: &TaskRuntime,
-    artifact_path: &Path,
-    input_json: &JsonValue,
-) -> anyhow::Result<Result<JsonValue, String>> {
-    match runtime {
-        TaskRuntime::Native => call_task_native(artifact_path, input_json),
-        TaskRuntime::Wasm => wasm_executor::call_task(artifact_path, input_json),
-    }
-}
-
-fn call_task_native(
-    lib_path: &Path,
-    input_json: &JsonValue,
-) -> anyhow::Result<Result<JsonValue, String>> {
-    unsafe {
-        let lib = Library::new(lib_path)?;
-        let create: libloading::Symbol<unsafe extern "C" fn() -> *mut std::ffi::c_void> =
-            lib.get(b"namu_task_create")?;
-        let destroy: libloading::Symbol<unsafe extern "C" fn(*mut std::ffi::c_void)> =
-            lib.get(b"namu_task_destroy")?;
-        let call: libloading::Symbol<
-            unsafe extern "C" fn(
-                *mut std::ffi::c_void,
-                *const u8,
-                usize,
-                *mut u8,
-                *mut usize,
-            ) -> i32,
-        > = lib.get(b"namu_task_call")?;
-
-        let handle = create();
-        let input_bytes = serde_json::to_vec(input_json)?;
-        let mut output_len: usize = 4096;
-        let mut buffer = vec![0u8; output_len];
-        let mut code = call(
-            handle,
-            input_bytes.as_ptr(),
-            input_bytes.len(),
-            buffer.as_mut_ptr(),
-            &mut output_len as *mut usize,
-        );
-
-        if code != 0 && output_len > buffer.len() {
-            buffer.resize(output_len, 0u8);
-            code = call(
-                handle,
-                input_bytes.as_ptr(),
-                input_bytes.len(),
-                buffer.as_mut_ptr(),
-                &mut output_len as *mut usize,
-            );
-        }
-
-        destroy(handle);
-
-        let output_bytes = &buffer[..output_len.min(buffer.len())];
-        let output_str = std::str::from_utf8(output_bytes).unwrap_or("");
-        if code == 0 {
-            let json = serde_json::from_str(output_str)?;
-            Ok(Ok(json))
-        } else {
-            let err = if output_str.is_empty() {
-                "task failed".to_string()
-            } else {
-                output_str.to_string()
-            };
-            Ok(Err(err))
-        }
-    }
-}
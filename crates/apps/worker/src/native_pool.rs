@@ -0,0 +1,213 @@
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use libloading::Library;
+use namu_proto::TaskError;
+use serde_json::Value as JsonValue;
+use tracing::info;
+
+type CreateFn = unsafe extern "C" fn() -> *mut c_void;
+type DestroyFn = unsafe extern "C" fn(*mut c_void);
+type CallFn = unsafe extern "C" fn(*mut c_void, *const u8, usize, *mut u8, *mut usize) -> i32;
+type U64Fn = unsafe extern "C" fn() -> u64;
+
+/// Native task libraries kept loaded across messages, keyed by `task_id@version`.
+///
+/// Each library keeps the handles it created, so state a task builds in
+/// `namu_task_create` survives between calls. Libraries are dropped least
+/// recently used first once more than `max_libraries` are loaded, and when
+/// unused for `idle_ttl`.
+pub struct NativePool {
+    max_libraries: usize,
+    idle_ttl: Duration,
+    entries: HashMap<String, PoolEntry>,
+}
+
+struct PoolEntry {
+    task: Arc<NativeTask>,
+    last_used: Instant,
+}
+
+impl NativePool {
+    pub fn new(max_libraries: usize, idle_ttl: Duration) -> Self {
+        Self {
+            max_libraries: max_libraries.max(1),
+            idle_ttl,
+            entries: HashMap::new(),
+        }
+    }
+
+    pub fn get_or_load(&mut self, key: &str, lib_path: &Path) -> anyhow::Result<Arc<NativeTask>> {
+        let now = Instant::now();
+        self.evict_idle(now);
+
+        if let Some(entry) = self.entries.get_mut(key) {
+            entry.last_used = now;
+            return Ok(entry.task.clone());
+        }
+
+        let task = Arc::new(NativeTask::load(lib_path)?);
+        info!("loaded native task {key}");
+        self.entries.insert(
+            key.to_string(),
+            PoolEntry {
+                task: task.clone(),
+                last_used: now,
+            },
+        );
+        while self.entries.len() > self.max_libraries {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            self.entries.remove(&oldest);
+            info!("evicted native task {oldest}");
+        }
+        Ok(task)
+    }
+
+    fn evict_idle(&mut self, now: Instant) {
+        self.entries.retain(|key, entry| {
+            let keep = now.duration_since(entry.last_used) < self.idle_ttl;
+            if !keep {
+                info!("evicted idle native task {key}");
+            }
+            keep
+        });
+    }
+}
+
+/// Call the functions named `names` that the library at `lib_path` exports
+/// to return a `u64`; `None` for each one it does not export.
+pub fn read_u64_exports(lib_path: &Path, names: &[&str]) -> anyhow::Result<Vec<Option<u64>>> {
+    unsafe {
+        let library = Library::new(lib_path)?;
+        Ok(names
+            .iter()
+            .map(|name| {
+                let export = library.get::<U64Fn>(name.as_bytes()).ok()?;
+                Some(export())
+            })
+            .collect())
+    }
+}
+
+struct TaskHandle(*mut c_void);
+
+// SAFETY: a handle is only used by one call at a time; it moves between
+// threads while parked in `NativeTask::handles`.
+unsafe impl Send for TaskHandle {}
+
+/// A loaded task library plus its idle handles.
+pub struct NativeTask {
+    create: CreateFn,
+    destroy: DestroyFn,
+    call: CallFn,
+    handles: Mutex<Vec<TaskHandle>>,
+    // Dropped last: the function pointers above point into it.
+    _library: Library,
+}
+
+impl NativeTask {
+    fn load(lib_path: &Path) -> anyhow::Result<Self> {
+        unsafe {
+            let library = Library::new(lib_path)?;
+            let create = *library.get::<CreateFn>(b"namu_task_create")?;
+            let destroy = *library.get::<DestroyFn>(b"namu_task_destroy")?;
+            let call = *library.get::<CallFn>(b"namu_task_call")?;
+            Ok(Self {
+                create,
+                destroy,
+                call,
+                handles: Mutex::new(Vec::new()),
+                _library: library,
+            })
+        }
+    }
+
+    /// Run one call on an idle handle, creating one if none is free.
+    ///
+    /// Output over `max_output_bytes` fails the task. The handle may still
+    /// hold that output, so it is destroyed rather than reused.
+    pub fn call(
+        &self,
+        input_json: &JsonValue,
+        max_output_bytes: Option<u64>,
+    ) -> anyhow::Result<Result<JsonValue, TaskError>> {
+        let input_bytes = serde_json::to_vec(input_json)?;
+        let idle = self.handles.lock().expect("native handles poisoned").pop();
+        let handle = match idle {
+            Some(handle) => handle,
+            None => TaskHandle(unsafe { (self.create)() }),
+        };
+
+        let mut output_len: usize = 4096;
+        let mut buffer = vec![0u8; output_len];
+        let mut code = self.invoke(&handle, &input_bytes, &mut buffer, &mut output_len);
+        if let Some(max) = max_output_bytes
+            && output_len as u64 > max
+        {
+            unsafe { (self.destroy)(handle.0) };
+            return Ok(Err(TaskError::fatal(format!(
+                "task output of {output_len} bytes exceeds max_output_bytes ({max})"
+            ))));
+        }
+        if code != 0 && output_len > buffer.len() {
+            buffer.resize(output_len, 0u8);
+            code = self.invoke(&handle, &input_bytes, &mut buffer, &mut output_len);
+        }
+        self.handles
+            .lock()
+            .expect("native handles poisoned")
+            .push(handle);
+
+        let output_bytes = &buffer[..output_len.min(buffer.len())];
+        let output_str = std::str::from_utf8(output_bytes).unwrap_or("");
+        if code == 0 {
+            let json = serde_json::from_str(output_str)?;
+            Ok(Ok(json))
+        } else {
+            let err = if output_str.is_empty() {
+                TaskError::retryable("task failed")
+            } else {
+                TaskError::parse(output_str)
+            };
+            Ok(Err(err))
+        }
+    }
+
+    fn invoke(
+        &self,
+        handle: &TaskHandle,
+        input_bytes: &[u8],
+        buffer: &mut [u8],
+        output_len: &mut usize,
+    ) -> i32 {
+        *output_len = buffer.len();
+        unsafe {
+            (self.call)(
+                handle.0,
+                input_bytes.as_ptr(),
+                input_bytes.len(),
+                buffer.as_mut_ptr(),
+                output_len as *mut usize,
+            )
+        }
+    }
+}
+
+impl Drop for NativeTask {
+    fn drop(&mut self) {
+        let handles = std::mem::take(self.handles.get_mut().expect("native handles poisoned"));
+        for handle in handles {
+            unsafe { (self.destroy)(handle.0) };
+        }
+    }
+}
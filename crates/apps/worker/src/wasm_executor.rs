@@ -1,144 +1,473 @@
+use std::collections::HashMap;
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use anyhow::Context;
+use namu_proto::{TaskError, TaskLimits};
 use serde_json::Value as JsonValue;
-use wasmtime::{Engine, Linker, Memory, Module, Store, TypedFunc};
+use tracing::{error, info};
+use wasmtime::{
+    Config, Engine, Linker, Memory, Module, ResourceLimiter, Store, StoreContextMut, Trap,
+    TypedFunc, UpdateDeadline,
+};
 use wasmtime_wasi::WasiCtxBuilder;
 use wasmtime_wasi::p1::{self, WasiP1Ctx};
 
-pub fn call_task(path: &Path, input_json: &JsonValue) -> anyhow::Result<Result<JsonValue, String>> {
-    let engine = Engine::default();
-    let module = Module::from_file(&engine, path)
-        .with_context(|| format!("failed to load wasm module at {}", path.to_string_lossy()))?;
+/// How often the epoch advances; `timeout_ms` is rounded up to whole ticks.
+const EPOCH_TICK: Duration = Duration::from_millis(10);
+/// Deadline used when a task has no timeout. `set_epoch_deadline` adds it to
+/// the current epoch, so `u64::MAX` would overflow.
+const NO_DEADLINE: u64 = u64::MAX / 2;
+/// Ticks between checks of a call's cancellation flag.
+const CANCEL_CHECK_TICKS: u64 = 10;
+/// Idle instances kept per module.
+const MAX_IDLE_INSTANCES: usize = 8;
+/// Compiled modules kept loaded; the least recently used one goes first.
+const MAX_MODULES: usize = 32;
+const WASM_PAGE_SIZE: usize = 65536;
 
-    let mut linker: Linker<WasiState> = Linker::new(&engine);
-    p1::add_to_linker_sync(&mut linker, |state| &mut state.wasi)?;
+type CallFn = TypedFunc<(i32, i32, i32, i32, i32), i32>;
 
-    let wasi = WasiCtxBuilder::new().build_p1();
-    let mut store = Store::new(&engine, WasiState { wasi });
+/// Wasm tasks share one `Engine`, compiled modules and idle instances.
+///
+/// Compiled modules are written next to the artifact as `*.cwasm`, so a
+/// restarted worker skips compilation. Instances keep their
+/// `namu_task_create` handle between calls, like native handles do; an
+/// instance that traps or breaks a limit is dropped instead of reused. At most
+/// [`MAX_MODULES`] modules stay loaded.
+pub struct WasmRuntime {
+    engine: Engine,
+    linker: Linker<WasiState>,
+    modules: Mutex<HashMap<String, ModuleEntry>>,
+}
 
-    let instance = linker
-        .instantiate(&mut store, &module)
-        .context("instantiate wasm module")?;
+struct ModuleEntry {
+    module: Module,
+    idle: Vec<WasmInstance>,
+    last_used: Instant,
+}
 
-    let memory = instance
-        .get_memory(&mut store, "memory")
-        .ok_or_else(|| anyhow::anyhow!("missing wasm memory export"))?;
-
-    let create = instance
-        .get_typed_func::<(), i32>(&mut store, "namu_task_create")
-        .context("missing namu_task_create")?;
-    let destroy = instance
-        .get_typed_func::<i32, ()>(&mut store, "namu_task_destroy")
-        .context("missing namu_task_destroy")?;
-    let call = instance
-        .get_typed_func::<(i32, i32, i32, i32, i32), i32>(&mut store, "namu_task_call")
-        .context("missing namu_task_call")?;
-
-    let handle = create.call(&mut store, ())?;
-    let input_bytes = serde_json::to_vec(input_json)?;
-    let mut output_capacity = 4096usize;
-
-    let (mut code, out_len, mut output) = call_once(
-        &mut store,
-        &memory,
-        &call,
-        handle,
-        &input_bytes,
-        output_capacity,
-    )?;
-
-    if code != 0 && out_len > output_capacity {
-        output_capacity = out_len;
-        let retry = call_once(
-            &mut store,
-            &memory,
-            &call,
-            handle,
-            &input_bytes,
-            output_capacity,
-        )?;
-        code = retry.0;
-        output = retry.2;
+impl WasmRuntime {
+    pub fn new() -> anyhow::Result<Self> {
+        let mut config = Config::new();
+        config.consume_fuel(true).epoch_interruption(true);
+        let engine = Engine::new(&config)?;
+
+        let mut linker: Linker<WasiState> = Linker::new(&engine);
+        p1::add_to_linker_sync(&mut linker, |state| &mut state.wasi)?;
+
+        let ticker = engine.clone();
+        std::thread::Builder::new()
+            .name("wasm-epoch".to_string())
+            .spawn(move || {
+                loop {
+                    std::thread::sleep(EPOCH_TICK);
+                    ticker.increment_epoch();
+                }
+            })?;
+
+        Ok(Self {
+            engine,
+            linker,
+            modules: Mutex::new(HashMap::new()),
+        })
     }
 
-    destroy.call(&mut store, handle)?;
+    /// Run one call of the module at `path`, pooled under `key`.
+    ///
+    /// Traps and limit breaches fail the task; only problems loading the
+    /// module are returned as errors. Setting `cancelled` stops the call
+    /// within [`CANCEL_CHECK_TICKS`] epoch ticks with a cancelled error.
+    pub fn call(
+        &self,
+        key: &str,
+        path: &Path,
+        limits: &TaskLimits,
+        input_json: &JsonValue,
+        cancelled: &Arc<AtomicBool>,
+    ) -> anyhow::Result<Result<JsonValue, TaskError>> {
+        let (module, idle) = {
+            let mut modules = self.modules.lock().expect("wasm modules poisoned");
+            match modules.get_mut(key) {
+                Some(entry) => {
+                    entry.last_used = Instant::now();
+                    (entry.module.clone(), entry.idle.pop())
+                }
+                None => {
+                    let module = self.load_module(path)?;
+                    modules.insert(
+                        key.to_string(),
+                        ModuleEntry {
+                            module: module.clone(),
+                            idle: Vec::new(),
+                            last_used: Instant::now(),
+                        },
+                    );
+                    while modules.len() > MAX_MODULES {
+                        let Some(oldest) = modules
+                            .iter()
+                            .min_by_key(|(_, entry)| entry.last_used)
+                            .map(|(key, _)| key.clone())
+                        else {
+                            break;
+                        };
+                        modules.remove(&oldest);
+                        info!("evicted wasm module {oldest}");
+                    }
+                    (module, None)
+                }
+            }
+        };
+
+        let mut instance = match idle {
+            Some(instance) => instance,
+            None => match self.instantiate(&module, limits, cancelled) {
+                Ok(instance) => instance,
+                Err(err) => return Ok(Err(describe_failure(&err, limits))),
+            },
+        };
 
-    let output_str = std::str::from_utf8(&output).unwrap_or("");
-    if code == 0 {
-        let json = serde_json::from_str(output_str)?;
-        Ok(Ok(json))
-    } else {
-        let err = if output_str.is_empty() {
-            "task failed".to_string()
+        let input_bytes = serde_json::to_vec(input_json)?;
+        let (code, output) = match instance.call(&input_bytes, limits, cancelled) {
+            Ok(result) => result,
+            Err(err) => return Ok(Err(describe_failure(&err, limits))),
+        };
+
+        if let Some(entry) = self
+            .modules
+            .lock()
+            .expect("wasm modules poisoned")
+            .get_mut(key)
+            && entry.idle.len() < MAX_IDLE_INSTANCES
+            && !instance.stranded
+        {
+            entry.idle.push(instance);
+        }
+
+        let output_str = std::str::from_utf8(&output).unwrap_or("");
+        if code == 0 {
+            let json = serde_json::from_str(output_str)?;
+            Ok(Ok(json))
         } else {
-            output_str.to_string()
+            let err = if output_str.is_empty() {
+                TaskError::retryable("task failed")
+            } else {
+                TaskError::parse(output_str)
+            };
+            Ok(Err(err))
+        }
+    }
+
+    fn load_module(&self, path: &Path) -> anyhow::Result<Module> {
+        let compiled = path.with_extension("cwasm");
+        if compiled.exists() {
+            // SAFETY: the file is only ever written by `Module::serialize`
+            // below; wasmtime rejects files from another version or config.
+            match unsafe { Module::deserialize_file(&self.engine, &compiled) } {
+                Ok(module) => return Ok(module),
+                Err(err) => error!(
+                    "discarding compiled module {}: {err}",
+                    compiled.to_string_lossy()
+                ),
+            }
+        }
+
+        let module = Module::from_file(&self.engine, path)
+            .with_context(|| format!("failed to load wasm module at {}", path.to_string_lossy()))?;
+        info!("compiled wasm module {}", path.to_string_lossy());
+        if let Err(err) = write_compiled(&module, &compiled) {
+            error!(
+                "failed to cache compiled module {}: {err}",
+                compiled.to_string_lossy()
+            );
+        }
+        Ok(module)
+    }
+
+    fn instantiate(
+        &self,
+        module: &Module,
+        limits: &TaskLimits,
+        cancelled: &Arc<AtomicBool>,
+    ) -> anyhow::Result<WasmInstance> {
+        let state = WasiState {
+            wasi: WasiCtxBuilder::new().build_p1(),
+            limits: MemoryLimiter {
+                max_memory_bytes: limits.max_memory_bytes,
+            },
+            cancelled: cancelled.clone(),
+            ticks_left: 0,
         };
-        Ok(Err(err))
+        let mut store = Store::new(&self.engine, state);
+        store.limiter(|state| &mut state.limits);
+        store.epoch_deadline_callback(check_deadline);
+        arm(&mut store, limits, cancelled)?;
+
+        let instance = self
+            .linker
+            .instantiate(&mut store, module)
+            .context("instantiate wasm module")?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| anyhow::anyhow!("missing wasm memory export"))?;
+        let create = instance
+            .get_typed_func::<(), i32>(&mut store, "namu_task_create")
+            .context("missing namu_task_create")?;
+        let call = instance
+            .get_typed_func::<(i32, i32, i32, i32, i32), i32>(&mut store, "namu_task_call")
+            .context("missing namu_task_call")?;
+        let handle = create.call(&mut store, ())?;
+
+        Ok(WasmInstance {
+            store,
+            memory,
+            call,
+            handle,
+            scratch_offset: 0,
+            scratch_len: 0,
+            stranded: false,
+        })
     }
 }
 
+/// Call the functions named `names` that the module at `path` exports to
+/// return a `u64`; `None` for each one it does not export. No other task
+/// code runs, and nothing is cached.
+pub fn read_u64_exports(path: &Path, names: &[&str]) -> anyhow::Result<Vec<Option<u64>>> {
+    let engine = Engine::default();
+    let mut linker: Linker<WasiP1Ctx> = Linker::new(&engine);
+    p1::add_to_linker_sync(&mut linker, |wasi| wasi)?;
+    let module = Module::from_file(&engine, path)
+        .with_context(|| format!("failed to load wasm module at {}", path.to_string_lossy()))?;
+    let mut store = Store::new(&engine, WasiCtxBuilder::new().build_p1());
+    let instance = linker
+        .instantiate(&mut store, &module)
+        .context("instantiate wasm module")?;
+    names
+        .iter()
+        .map(
+            |name| match instance.get_typed_func::<(), i64>(&mut store, name) {
+                Ok(export) => Ok(Some(export.call(&mut store, ())? as u64)),
+                Err(_) => Ok(None),
+            },
+        )
+        .collect()
+}
+
 struct WasiState {
     wasi: WasiP1Ctx,
+    limits: MemoryLimiter,
+    /// Cancellation flag of the running call.
+    cancelled: Arc<AtomicBool>,
+    /// Epoch ticks left before the running call times out.
+    ticks_left: u64,
 }
 
-fn call_once(
-    store: &mut Store<WasiState>,
-    memory: &Memory,
-    call: &TypedFunc<(i32, i32, i32, i32, i32), i32>,
+/// Runs every [`CANCEL_CHECK_TICKS`] ticks: stops a cancelled call, traps
+/// once the timeout is used up and otherwise lets the call go on.
+fn check_deadline(mut store: StoreContextMut<'_, WasiState>) -> anyhow::Result<UpdateDeadline> {
+    let state = store.data_mut();
+    if state.cancelled.load(Ordering::Relaxed) {
+        return Err(TaskError::cancelled("run was cancelled").into());
+    }
+    if state.ticks_left == 0 {
+        return Ok(UpdateDeadline::Interrupt);
+    }
+    let next = state.ticks_left.min(CANCEL_CHECK_TICKS);
+    state.ticks_left -= next;
+    Ok(UpdateDeadline::Continue(next))
+}
+
+/// Enforces `max_memory_bytes`. Growing past it fails the call with a fatal
+/// task error, whether the task or the worker's scratch region asked for it.
+struct MemoryLimiter {
+    max_memory_bytes: Option<u64>,
+}
+
+impl ResourceLimiter for MemoryLimiter {
+    fn memory_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> anyhow::Result<bool> {
+        if let Some(max) = self.max_memory_bytes
+            && desired as u64 > max
+        {
+            return Err(TaskError::fatal(format!(
+                "task needs {desired} bytes of memory, over max_memory_bytes ({max})"
+            ))
+            .into());
+        }
+        Ok(true)
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: usize,
+        _desired: usize,
+        _maximum: Option<usize>,
+    ) -> anyhow::Result<bool> {
+        Ok(true)
+    }
+}
+
+struct WasmInstance {
+    store: Store<WasiState>,
+    memory: Memory,
+    call: CallFn,
     handle: i32,
-    input: &[u8],
-    output_capacity: usize,
-) -> anyhow::Result<(i32, usize, Vec<u8>)> {
-    let input_len = input.len();
-    let input_offset = 0usize;
-    let output_offset = align_up(input_offset + input_len, 8);
-    let output_len_offset = align_up(output_offset + output_capacity, 8);
-    let required = output_len_offset + std::mem::size_of::<u32>();
+    /// Pages grown by the worker to pass input and output through. The
+    /// task's allocator never hands these out, so they can be reused.
+    scratch_offset: usize,
+    scratch_len: usize,
+    /// An outgrown scratch region was left behind in linear memory; the
+    /// instance is dropped after its call instead of going back to the pool.
+    stranded: bool,
+}
 
-    ensure_memory(memory, store, required)?;
+impl WasmInstance {
+    fn call(
+        &mut self,
+        input: &[u8],
+        limits: &TaskLimits,
+        cancelled: &Arc<AtomicBool>,
+    ) -> anyhow::Result<(i32, Vec<u8>)> {
+        arm(&mut self.store, limits, cancelled)?;
 
-    {
-        let data = memory.data_mut(&mut *store);
-        data[input_offset..input_offset + input_len].copy_from_slice(input);
-        write_u32(data, output_len_offset, output_capacity as u32);
+        let mut output_capacity = 4096usize;
+        let (mut code, out_len, mut output) = self.call_once(input, output_capacity)?;
+        check_output_size(out_len, limits)?;
+        if code != 0 && out_len > output_capacity {
+            output_capacity = out_len;
+            (code, _, output) = self.call_once(input, output_capacity)?;
+        }
+        Ok((code, output))
     }
 
-    let code = call.call(
-        &mut *store,
-        (
-            handle,
-            input_offset as i32,
-            input_len as i32,
-            output_offset as i32,
-            output_len_offset as i32,
-        ),
-    )?;
+    fn call_once(
+        &mut self,
+        input: &[u8],
+        output_capacity: usize,
+    ) -> anyhow::Result<(i32, usize, Vec<u8>)> {
+        let input_len = input.len();
+        let input_offset = 0usize;
+        let output_offset = align_up(input_offset + input_len, 8);
+        let output_len_offset = align_up(output_offset + output_capacity, 8);
+        let required = output_len_offset + std::mem::size_of::<u32>();
+        self.ensure_scratch(required)?;
 
-    let (out_len, output) = {
-        let data = memory.data(&mut *store);
-        let out_len = read_u32(data, output_len_offset) as usize;
-        let end = output_offset + out_len.min(output_capacity);
-        (out_len, data[output_offset..end].to_vec())
-    };
+        let base = self.scratch_offset;
+        {
+            let data = self.memory.data_mut(&mut self.store);
+            data[base + input_offset..base + input_offset + input_len].copy_from_slice(input);
+            write_u32(data, base + output_len_offset, output_capacity as u32);
+        }
 
-    Ok((code, out_len, output))
+        let code = self.call.call(
+            &mut self.store,
+            (
+                self.handle,
+                (base + input_offset) as i32,
+                input_len as i32,
+                (base + output_offset) as i32,
+                (base + output_len_offset) as i32,
+            ),
+        )?;
+
+        let data = self.memory.data(&self.store);
+        let out_len = read_u32(data, base + output_len_offset) as usize;
+        let start = base + output_offset;
+        let output = data[start..start + out_len.min(output_capacity)].to_vec();
+        Ok((code, out_len, output))
+    }
+
+    /// Make sure the scratch region holds `required` bytes. A region at the
+    /// end of memory grows in place; otherwise a fresh one is grown at the
+    /// end and the instance is marked stranded.
+    fn ensure_scratch(&mut self, required: usize) -> anyhow::Result<()> {
+        if self.scratch_len >= required {
+            return Ok(());
+        }
+        let at_end = self.scratch_len > 0
+            && self.scratch_offset + self.scratch_len == self.memory.data_size(&self.store);
+        let kept = if at_end { self.scratch_len } else { 0 };
+        let pages = (required - kept).div_ceil(WASM_PAGE_SIZE);
+        let previous = self
+            .memory
+            .grow(&mut self.store, pages as u64)
+            .context("failed to grow wasm memory")?;
+        if !at_end {
+            self.stranded |= self.scratch_len > 0;
+            self.scratch_offset = previous as usize * WASM_PAGE_SIZE;
+        }
+        self.scratch_len = kept + pages * WASM_PAGE_SIZE;
+        Ok(())
+    }
 }
 
-fn ensure_memory(
-    memory: &Memory,
+/// Reset fuel, the epoch deadline and the cancellation flag before running
+/// guest code.
+fn arm(
     store: &mut Store<WasiState>,
-    required: usize,
+    limits: &TaskLimits,
+    cancelled: &Arc<AtomicBool>,
 ) -> anyhow::Result<()> {
-    let current = memory.data_size(&mut *store);
-    if current >= required {
-        return Ok(());
-    }
-    let delta = required - current;
-    let pages = delta.div_ceil(WASM_PAGE_SIZE);
-    memory
-        .grow(&mut *store, pages as u64)
-        .context("failed to grow wasm memory")?;
+    store.set_fuel(limits.fuel.unwrap_or(u64::MAX))?;
+    let ticks = limits
+        .timeout_ms
+        .map(|ms| ms.div_ceil(EPOCH_TICK.as_millis() as u64).max(1))
+        .unwrap_or(NO_DEADLINE);
+    let first = ticks.min(CANCEL_CHECK_TICKS);
+    let state = store.data_mut();
+    state.cancelled = cancelled.clone();
+    state.ticks_left = ticks - first;
+    store.set_epoch_deadline(first);
+    Ok(())
+}
+
+fn check_output_size(out_len: usize, limits: &TaskLimits) -> anyhow::Result<()> {
+    if let Some(max) = limits.max_output_bytes
+        && out_len as u64 > max
+    {
+        return Err(TaskError::fatal(format!(
+            "task output of {out_len} bytes exceeds max_output_bytes ({max})"
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+/// Traps and limit breaches fail every attempt alike, so only other
+/// failures are retryable.
+fn describe_failure(err: &anyhow::Error, limits: &TaskLimits) -> TaskError {
+    if let Some(error) = err
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<TaskError>())
+    {
+        return error.clone();
+    }
+    match err.downcast_ref::<Trap>() {
+        Some(Trap::OutOfFuel) => TaskError::fatal(format!(
+            "task exceeded its fuel limit ({})",
+            limits.fuel.unwrap_or(u64::MAX)
+        )),
+        Some(Trap::Interrupt) => TaskError::timeout(format!(
+            "task exceeded its timeout ({} ms)",
+            limits.timeout_ms.unwrap_or_default()
+        )),
+        Some(_) => TaskError::fatal(format!("wasm task failed: {err:#}")),
+        None => TaskError::retryable(format!("wasm task failed: {err:#}")),
+    }
+}
+
+fn write_compiled(module: &Module, path: &Path) -> anyhow::Result<()> {
+    let bytes = module.serialize()?;
+    let tmp = path.with_extension("cwasm.tmp");
+    std::fs::write(&tmp, bytes)?;
+    std::fs::rename(&tmp, path)?;
     Ok(())
 }
 
@@ -157,4 +486,119 @@ fn read_u32(buf: &[u8], offset: usize) -> u32 {
     u32::from_le_bytes(out)
 }
 
-const WASM_PAGE_SIZE: usize = 65536;
+#[cfg(test)]
+mod tests {
+    use namu_proto::TaskErrorKind;
+    use serde_json::json;
+
+    use super::*;
+
+    /// A task that answers `0` to any input, with one page of memory.
+    const ZERO_TASK: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "namu_task_create") (result i32) i32.const 0)
+          (func (export "namu_task_call") (param i32 i32 i32 i32 i32) (result i32)
+            local.get 3
+            i32.const 48
+            i32.store8
+            local.get 4
+            i32.const 1
+            i32.store
+            i32.const 0))
+    "#;
+
+    /// A task whose calls never return.
+    const SPIN_TASK: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (func (export "namu_task_create") (result i32) i32.const 0)
+          (func (export "namu_task_call") (param i32 i32 i32 i32 i32) (result i32)
+            (loop (br 0))
+            i32.const 0))
+    "#;
+
+    fn task_file(name: &str, wat: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("namu-{name}-{}.wat", std::process::id()));
+        std::fs::write(&path, wat).unwrap();
+        path
+    }
+
+    fn remove_task(path: &Path) {
+        let _ = std::fs::remove_file(path);
+        let _ = std::fs::remove_file(path.with_extension("cwasm"));
+    }
+
+    #[test]
+    fn input_over_max_memory_fails_fatally() {
+        let runtime = WasmRuntime::new().unwrap();
+        let path = task_file("wasm-memory", ZERO_TASK);
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let limits = TaskLimits {
+            max_memory_bytes: Some(2 * WASM_PAGE_SIZE as u64),
+            ..TaskLimits::default()
+        };
+
+        let small = runtime
+            .call("zero@1", &path, &limits, &json!("x"), &cancelled)
+            .unwrap();
+        assert_eq!(small, Ok(json!(0)));
+
+        let large = json!("x".repeat(3 * WASM_PAGE_SIZE));
+        let error = runtime
+            .call("zero@1", &path, &limits, &large, &cancelled)
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(error.kind, TaskErrorKind::Fatal, "{error}");
+        assert!(error.message.contains("max_memory_bytes"), "{error}");
+        remove_task(&path);
+    }
+
+    #[test]
+    fn cancelling_stops_a_running_call() {
+        let runtime = WasmRuntime::new().unwrap();
+        let path = task_file("wasm-cancel", SPIN_TASK);
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let flag = cancelled.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            flag.store(true, Ordering::Relaxed);
+        });
+
+        let error = runtime
+            .call(
+                "spin@1",
+                &path,
+                &TaskLimits::default(),
+                &json!(null),
+                &cancelled,
+            )
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(error.kind, TaskErrorKind::Cancelled, "{error}");
+        remove_task(&path);
+    }
+
+    #[test]
+    fn timeout_still_applies_between_cancel_checks() {
+        let runtime = WasmRuntime::new().unwrap();
+        let path = task_file("wasm-timeout", SPIN_TASK);
+        let limits = TaskLimits {
+            timeout_ms: Some(250),
+            ..TaskLimits::default()
+        };
+
+        let error = runtime
+            .call(
+                "spin@1",
+                &path,
+                &limits,
+                &json!(null),
+                &Arc::new(AtomicBool::new(false)),
+            )
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(error.kind, TaskErrorKind::Timeout, "{error}");
+        remove_task(&path);
+    }
+}
@@ -1,6 +1,9 @@
 use std::any::TypeId;
+use std::collections::HashSet;
 use std::fmt::{Display, Formatter};
 use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 
 use anyhow::Result;
 use async_trait::async_trait;
@@ -21,6 +24,48 @@ impl Display for TaskEnd {
 
 impl std::error::Error for TaskEnd {}
 
+/// Set once the run a task serves is cancelled, or for single calls of it.
+///
+/// Clones share the state, so the engine keeps one and hands clones to the
+/// task contexts of the run.
+#[derive(Debug, Clone, Default)]
+pub struct CancelSignal {
+    run: Arc<AtomicBool>,
+    calls: Arc<Mutex<HashSet<ContextId>>>,
+}
+
+impl CancelSignal {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.run.store(true, Ordering::Release);
+    }
+
+    /// Cancel the call dispatched in `id` only.
+    pub fn cancel_call(&self, id: ContextId) {
+        self.calls
+            .lock()
+            .expect("cancelled calls lock poisoned")
+            .insert(id);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.run.load(Ordering::Acquire)
+    }
+
+    /// Whether the run or the call dispatched in `id` was cancelled.
+    pub fn is_call_cancelled(&self, id: ContextId) -> bool {
+        self.is_cancelled()
+            || self
+                .calls
+                .lock()
+                .expect("cancelled calls lock poisoned")
+                .contains(&id)
+    }
+}
+
 #[async_trait]
 pub trait TaskContext: Send {
     fn recv<T: Send + DeserializeOwned + 'static>(&self) -> Result<(ContextId, T), ReceiveError>;
@@ -48,12 +93,18 @@ pub trait TaskContext: Send {
     fn send_end(&self, item_id: ContextId) -> Result<(), SendError>;
 
     async fn send_end_async(&self, item_id: ContextId) -> Result<(), SendError>;
+
+    /// Whether the call of `item_id` was cancelled, on its own or with its
+    /// run; it should be ended without calling the task, or without sending
+    /// more outputs.
+    fn is_cancelled(&self, item_id: ContextId) -> bool;
 }
 
 #[derive(Clone)]
 pub struct StaticTaskContext<In, Out> {
     input_ch: Receiver<(ContextId, In)>,
     output_ch: Sender<(ContextId, Result<Out>)>,
+    cancel: CancelSignal,
 }
 
 impl<In, Out> StaticTaskContext<In, Out> {
@@ -64,8 +115,14 @@ impl<In, Out> StaticTaskContext<In, Out> {
         Self {
             input_ch,
             output_ch,
+            cancel: CancelSignal::new(),
         }
     }
+
+    pub fn with_cancel(mut self, cancel: CancelSignal) -> Self {
+        self.cancel = cancel;
+        self
+    }
 }
 
 #[async_trait]
@@ -169,12 +226,17 @@ where
             .send((item_id, Err(anyhow::Error::from(TaskEnd))))
             .await
     }
+
+    fn is_cancelled(&self, item_id: ContextId) -> bool {
+        self.cancel.is_call_cancelled(item_id)
+    }
 }
 
 #[derive(Clone)]
 pub struct DynamicTaskContext {
     input_ch: Receiver<(ContextId, Value)>,
     output_ch: Sender<(ContextId, Result<Value>)>,
+    cancel: CancelSignal,
 }
 
 impl DynamicTaskContext {
@@ -185,8 +247,14 @@ impl DynamicTaskContext {
         Self {
             input_ch,
             output_ch,
+            cancel: CancelSignal::new(),
         }
     }
+
+    pub fn with_cancel(mut self, cancel: CancelSignal) -> Self {
+        self.cancel = cancel;
+        self
+    }
 }
 
 #[async_trait]
@@ -240,6 +308,10 @@ impl TaskContext for DynamicTaskContext {
             .send((item_id, Err(anyhow::Error::from(TaskEnd))))
             .await
     }
+
+    fn is_cancelled(&self, item_id: ContextId) -> bool {
+        self.cancel.is_call_cancelled(item_id)
+    }
 }
 
 // https://github.com/funnsam/stable-intrinsics/blob/586a139ccb758488f109daf5165ecef574723b3e/src/lib.rs#L181
@@ -0,0 +1,193 @@
+//! Structured task failures.
+//!
+//! Tasks fail with an `anyhow::Error`; returning a [`TaskError`] in it, e.g.
+//! `Err(TaskError::invalid_input("empty batch").into())`, tells engines,
+//! retry policies and observers what kind of failure it was. Any other error
+//! counts as [`TaskErrorKind::Retryable`].
+//!
+//! Across the C ABI, the master API and run events a task error travels as
+//! JSON: `{"kind": "retryable", "message": "...", "sources": [...],
+//! "details": ...}`, inside `{"error": ...}` in an ABI error envelope.
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+/// What kind of failure a [`TaskError`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskErrorKind {
+    /// May succeed if called again.
+    Retryable,
+    /// Fails again however often it is called.
+    Fatal,
+    /// The call's inputs are unusable.
+    InvalidInput,
+    /// The call ran out of time.
+    Timeout,
+    /// The call was cancelled before it finished.
+    Cancelled,
+}
+
+impl TaskErrorKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TaskErrorKind::Retryable => "retryable",
+            TaskErrorKind::Fatal => "fatal",
+            TaskErrorKind::InvalidInput => "invalid_input",
+            TaskErrorKind::Timeout => "timeout",
+            TaskErrorKind::Cancelled => "cancelled",
+        }
+    }
+
+    /// Whether calling again may help.
+    pub fn is_retryable(self) -> bool {
+        matches!(self, TaskErrorKind::Retryable | TaskErrorKind::Timeout)
+    }
+}
+
+impl fmt::Display for TaskErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for TaskErrorKind {
+    type Err = anyhow::Error;
+
+    fn from_str(kind: &str) -> anyhow::Result<Self> {
+        match kind {
+            "retryable" => Ok(TaskErrorKind::Retryable),
+            "fatal" => Ok(TaskErrorKind::Fatal),
+            "invalid_input" => Ok(TaskErrorKind::InvalidInput),
+            "timeout" => Ok(TaskErrorKind::Timeout),
+            "cancelled" => Ok(TaskErrorKind::Cancelled),
+            _ => Err(anyhow::anyhow!("unknown task error kind `{kind}`")),
+        }
+    }
+}
+
+/// A task failure: its kind, message, the messages of the errors that caused
+/// it, outermost first, and optional details as JSON.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskError {
+    pub kind: TaskErrorKind,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<JsonValue>,
+}
+
+impl TaskError {
+    pub fn new(kind: TaskErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            sources: Vec::new(),
+            details: None,
+        }
+    }
+
+    pub fn retryable(message: impl Into<String>) -> Self {
+        Self::new(TaskErrorKind::Retryable, message)
+    }
+
+    pub fn fatal(message: impl Into<String>) -> Self {
+        Self::new(TaskErrorKind::Fatal, message)
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::new(TaskErrorKind::InvalidInput, message)
+    }
+
+    pub fn timeout(message: impl Into<String>) -> Self {
+        Self::new(TaskErrorKind::Timeout, message)
+    }
+
+    pub fn cancelled(message: impl Into<String>) -> Self {
+        Self::new(TaskErrorKind::Cancelled, message)
+    }
+
+    pub fn with_details(mut self, details: JsonValue) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    /// Add the message of an error that caused this one, after those
+    /// already added.
+    pub fn with_source(mut self, source: impl fmt::Display) -> Self {
+        self.sources.push(source.to_string());
+        self
+    }
+
+    /// The task error `err` carries, with the errors it was wrapped in as
+    /// context; a retryable error with `err`'s chain as sources otherwise.
+    pub fn from_anyhow(err: &anyhow::Error) -> Self {
+        let mut chain = err.chain();
+        let mut outer = Vec::new();
+        for cause in chain.by_ref() {
+            if let Some(task_error) = cause.downcast_ref::<TaskError>() {
+                let mut task_error = task_error.clone();
+                if !outer.is_empty() {
+                    outer.push(task_error.message);
+                    task_error.message = outer.join(": ");
+                }
+                task_error.sources.extend(chain.map(ToString::to_string));
+                return task_error;
+            }
+            outer.push(cause.to_string());
+        }
+        let mut messages = outer.into_iter();
+        Self {
+            kind: TaskErrorKind::Retryable,
+            message: messages.next().unwrap_or_default(),
+            sources: messages.collect(),
+            details: None,
+        }
+    }
+
+    /// The ABI error envelope: `{"error": <this error>}`.
+    pub fn to_envelope(&self) -> JsonValue {
+        serde_json::json!({ "error": self })
+    }
+
+    /// Read an error envelope or a bare task error. Anything else, such as
+    /// envelopes of older builds with a kind that is none of
+    /// [`TaskErrorKind`], is a retryable error with the message it has.
+    pub fn from_envelope(value: &JsonValue) -> Self {
+        let inner = value.get("error").unwrap_or(value);
+        if let Ok(task_error) = serde_json::from_value::<TaskError>(inner.clone()) {
+            return task_error;
+        }
+        match inner.get("message").unwrap_or(inner) {
+            JsonValue::String(message) => Self::retryable(message.clone()),
+            other => Self::retryable(other.to_string()),
+        }
+    }
+
+    /// Read an error reported as text: JSON as in [`TaskError::from_envelope`],
+    /// or a plain message, which is retryable.
+    pub fn parse(error: &str) -> Self {
+        match serde_json::from_str::<JsonValue>(error) {
+            Ok(value) => Self::from_envelope(&value),
+            Err(_) => Self::retryable(error),
+        }
+    }
+}
+
+impl fmt::Display for TaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)?;
+        if f.alternate() {
+            for source in &self.sources {
+                write!(f, ": {source}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for TaskError {}
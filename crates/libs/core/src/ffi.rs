@@ -0,0 +1,131 @@
+//! C ABI glue behind `#[task(export)]`.
+//!
+//! `namu-worker` loads task artifacts (native libraries or `wasm32-wasip1`
+//! modules) through three symbols:
+//!
+//! - `namu_task_create() -> handle`
+//! - `namu_task_call(handle, input_ptr, input_len, output_ptr, output_len) -> code`
+//! - `namu_task_destroy(handle)`
+//!
+//! Inputs and outputs are JSON. `output_len` holds the buffer capacity on the
+//! way in and the payload length on the way out. When the payload does not
+//! fit, the call returns [`BUFFER_TOO_SMALL`] and keeps the payload on the
+//! handle, so the retry with a larger buffer does not run the task again.
+//!
+//! Batch tasks take a JSON array of inputs and return an array with one
+//! entry per input: `{"ok": output}` or an error envelope. They also export
+//! `namu_task_batch_size() -> u64` and, when `max_wait_ms` is set,
+//! `namu_task_max_wait_ms() -> u64`, which `namu build` writes into the
+//! manifest's batch policy.
+//!
+//! An error envelope is `{"error": <TaskError>}`; see [`TaskError`].
+
+use std::ffi::c_void;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use crate::TaskError;
+
+/// The output buffer holds the JSON-encoded task output.
+pub const OK: i32 = 0;
+/// `output_len` was set to the required size; call again with a larger buffer.
+pub const BUFFER_TOO_SMALL: i32 = 1;
+/// The output buffer holds a JSON error envelope.
+pub const TASK_ERROR: i32 = 2;
+
+struct Handle {
+    pending: Option<(i32, Vec<u8>)>,
+}
+
+pub fn create() -> *mut c_void {
+    Box::into_raw(Box::new(Handle { pending: None })).cast()
+}
+
+/// # Safety
+/// `handle` must be null or come from [`create`], and not be used afterwards.
+pub unsafe fn destroy(handle: *mut c_void) {
+    if !handle.is_null() {
+        drop(unsafe { Box::from_raw(handle.cast::<Handle>()) });
+    }
+}
+
+/// Run `run` on the input bytes and write its result to the output buffer.
+///
+/// # Safety
+/// `handle` must come from [`create`]; `input_ptr` must point to `input_len`
+/// readable bytes and `output_ptr` to `*output_len` writable bytes.
+pub unsafe fn call(
+    handle: *mut c_void,
+    input_ptr: *const u8,
+    input_len: usize,
+    output_ptr: *mut u8,
+    output_len: *mut usize,
+    run: impl FnOnce(&[u8]) -> anyhow::Result<Vec<u8>>,
+) -> i32 {
+    let handle = unsafe { &mut *handle.cast::<Handle>() };
+    let (code, payload) = match handle.pending.take() {
+        Some(pending) => pending,
+        None => {
+            let input = if input_len == 0 {
+                &[][..]
+            } else {
+                unsafe { std::slice::from_raw_parts(input_ptr, input_len) }
+            };
+            match catch_unwind(AssertUnwindSafe(|| run(input))) {
+                Ok(Ok(output)) => (OK, output),
+                Ok(Err(err)) => (TASK_ERROR, error_envelope(&TaskError::from_anyhow(&err))),
+                Err(_) => (
+                    TASK_ERROR,
+                    error_envelope(&TaskError::fatal("task panicked")),
+                ),
+            }
+        }
+    };
+
+    let capacity = unsafe { *output_len };
+    unsafe { *output_len = payload.len() };
+    if capacity < payload.len() {
+        handle.pending = Some((code, payload));
+        return BUFFER_TOO_SMALL;
+    }
+    unsafe { std::ptr::copy_nonoverlapping(payload.as_ptr(), output_ptr, payload.len()) };
+    code
+}
+
+/// Decode the task arguments; several arguments arrive as a JSON array.
+pub fn decode<T: DeserializeOwned>(input: &[u8]) -> anyhow::Result<T> {
+    serde_json::from_slice(input)
+        .map_err(|err| TaskError::invalid_input(format!("invalid task input: {err}")).into())
+}
+
+pub fn encode<T: Serialize>(output: &T) -> anyhow::Result<Vec<u8>> {
+    Ok(serde_json::to_vec(output)?)
+}
+
+/// Encode the outputs of one batch call, which must match its inputs in number.
+pub fn encode_batch<T: Serialize>(
+    outputs: Vec<anyhow::Result<T>>,
+    inputs: usize,
+) -> anyhow::Result<Vec<u8>> {
+    if outputs.len() != inputs {
+        return Err(TaskError::fatal(format!(
+            "batch task returned {} outputs for {inputs} inputs",
+            outputs.len()
+        ))
+        .into());
+    }
+    let items = outputs
+        .into_iter()
+        .map(|output| match output {
+            Ok(value) => Ok(serde_json::json!({ "ok": serde_json::to_value(value)? })),
+            Err(err) => Ok(TaskError::from_anyhow(&err).to_envelope()),
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(serde_json::to_vec(&items)?)
+}
+
+fn error_envelope(error: &TaskError) -> Vec<u8> {
+    error.to_envelope().to_string().into_bytes()
+}
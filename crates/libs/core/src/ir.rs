@@ -5,21 +5,65 @@ use crate::{OpId, ValueId};
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Workflow {
     pub name: String,
+    /// Workflow parameters, seeded into the root context before the first op.
+    #[serde(default)]
+    pub inputs: Vec<Input>,
     pub operations: Vec<Operation>,
 }
 
 impl Workflow {
     pub fn new(name: String, operations: Vec<Operation>) -> Self {
-        Self { name, operations }
+        Self {
+            name,
+            inputs: Vec::new(),
+            operations,
+        }
+    }
+
+    pub fn with_inputs(mut self, inputs: Vec<Input>) -> Self {
+        self.inputs = inputs;
+        self
+    }
+
+    /// The op collecting the fan-out of the call at `from`, if any.
+    pub fn collect_of(&self, from: OpId) -> Option<OpId> {
+        self.operations.iter().position(|operation| {
+            operation
+                .collect
+                .as_ref()
+                .is_some_and(|collect| collect.from == from)
+        })
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Input {
+    /// Parameter name as written in the `#[workflow]` signature.
+    pub name: String,
+    /// Type tag of the parameter (e.g. `i32`, see [`crate::literal`]), or its
+    /// declared Rust type when it has none.
+    pub ty: String,
+    pub output: ValueId,
+}
+
 // --- New grouped IR representation -----------------------------------------
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Literal {
     pub output: ValueId,
-    pub value: String,
+    /// Type tag, see [`crate::literal`].
+    pub ty: String,
+    pub value: serde_json::Value,
+}
+
+impl Literal {
+    pub fn unit(output: ValueId) -> Self {
+        Self {
+            output,
+            ty: "()".to_string(),
+            value: serde_json::Value::Null,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -36,6 +80,21 @@ pub struct Call {
     pub outputs: Vec<ValueId>,
 }
 
+/// Fan-in of the contexts a stream call fanned out into.
+///
+/// Every context created from an output of `from` stops here and hands in
+/// its `input`. Once all of them have, the values are bound as one `Vec`, in
+/// context order, to `output` in a new child of the context `from` ran in,
+/// and that context continues at the op's `next`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Collect {
+    pub from: OpId,
+    pub input: ValueId,
+    /// Type tag of `input`, see [`crate::literal`].
+    pub ty: String,
+    pub output: ValueId,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Operation {
     /// Zero or more literal constants produced *before* any phi or call.
@@ -48,6 +107,11 @@ pub struct Operation {
     /// basic-block that ends with only literals/phis.
     pub call: Option<Call>,
 
+    /// Optional fan-in, evaluated after literals and phis. An op never has
+    /// both a call and a collect.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collect: Option<Collect>,
+
     /// Control-flow successor metadata.
     pub next: Next,
 }
@@ -58,9 +122,15 @@ impl Operation {
             literals,
             phis,
             call,
+            collect: None,
             next,
         }
     }
+
+    pub fn with_collect(mut self, collect: Collect) -> Self {
+        self.collect = Some(collect);
+        self
+    }
 }
 
 // ---------------------------------------------------------------------------
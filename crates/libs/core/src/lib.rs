@@ -1,10 +1,15 @@
 mod context;
+mod error;
+pub mod ffi;
 pub mod ir;
+pub mod literal;
 pub mod registry;
 mod task;
+pub mod validate;
 mod value;
 
-pub use context::{DynamicTaskContext, StaticTaskContext, TaskContext, TaskEnd};
+pub use context::{CancelSignal, DynamicTaskContext, StaticTaskContext, TaskContext, TaskEnd};
+pub use error::{TaskError, TaskErrorKind};
 pub use task::{
     AsyncBatchedTask, AsyncSingleTask, AsyncStreamTask, AsyncTask, BatchedTask, SingleTask,
     StreamTask, Task,
@@ -0,0 +1,173 @@
+//! Typed literal encoding shared by the workflow builder and the engine runtimes.
+//!
+//! A literal is stored in the IR as a type tag plus its JSON form. Built-in
+//! scalars (and `Vec`/`Option` of them) use their Rust spelling as the tag,
+//! user types use the name they were registered under with `#[type]`.
+//!
+//! The same tags name the element type of a `collect`, see [`collect`].
+
+use std::any::{TypeId, type_name};
+use std::sync::OnceLock;
+
+use hashbrown::HashMap;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value as JsonValue;
+
+use crate::Value;
+use crate::registry::{CollectFn, get_types};
+
+type DecodeFn = fn(&JsonValue) -> anyhow::Result<Value>;
+
+struct Builtins {
+    tags: HashMap<TypeId, &'static str>,
+    decoders: HashMap<&'static str, DecodeFn>,
+    collectors: HashMap<&'static str, CollectFn>,
+}
+
+fn decode_as<T: DeserializeOwned + Serialize + Clone + Send + Sync + 'static>(
+    json: &JsonValue,
+) -> anyhow::Result<Value> {
+    Ok(Value::new(T::deserialize(json)?))
+}
+
+macro_rules! register_builtins {
+    ($builtins:ident, $($tag:literal => $ty:ty),* $(,)?) => {
+        $(
+            $builtins.tags.insert(TypeId::of::<$ty>(), $tag);
+            $builtins.decoders.insert($tag, decode_as::<$ty>);
+            $builtins.collectors.insert($tag, collect_as::<$ty>);
+            $builtins.tags.insert(TypeId::of::<Vec<$ty>>(), concat!("Vec<", $tag, ">"));
+            $builtins.decoders.insert(concat!("Vec<", $tag, ">"), decode_as::<Vec<$ty>>);
+            $builtins.collectors.insert(concat!("Vec<", $tag, ">"), collect_as::<Vec<$ty>>);
+            $builtins.tags.insert(TypeId::of::<Option<$ty>>(), concat!("Option<", $tag, ">"));
+            $builtins.decoders.insert(concat!("Option<", $tag, ">"), decode_as::<Option<$ty>>);
+            $builtins.collectors.insert(concat!("Option<", $tag, ">"), collect_as::<Option<$ty>>);
+        )*
+    };
+}
+
+fn builtins() -> &'static Builtins {
+    static BUILTINS: OnceLock<Builtins> = OnceLock::new();
+    BUILTINS.get_or_init(|| {
+        let mut builtins = Builtins {
+            tags: HashMap::new(),
+            decoders: HashMap::new(),
+            collectors: HashMap::new(),
+        };
+        register_builtins!(builtins,
+            "()" => (),
+            "bool" => bool,
+            "char" => char,
+            "i8" => i8,
+            "i16" => i16,
+            "i32" => i32,
+            "i64" => i64,
+            "isize" => isize,
+            "u8" => u8,
+            "u16" => u16,
+            "u32" => u32,
+            "u64" => u64,
+            "usize" => usize,
+            "f32" => f32,
+            "f64" => f64,
+            "String" => String,
+        );
+        // String literals in workflow code are `&'static str`; they decode as `String`.
+        builtins.tags.insert(TypeId::of::<&'static str>(), "String");
+        builtins
+    })
+}
+
+/// Type tag for `T`, or `None` if it is neither built-in nor registered with `#[type]`.
+pub fn type_tag<T: 'static>() -> Option<&'static str> {
+    tag_for(TypeId::of::<T>())
+}
+
+/// Type tag of the value `value` holds, see [`type_tag`].
+pub fn tag_of(value: &Value) -> Option<&'static str> {
+    tag_for(value.type_id())
+}
+
+fn tag_for(type_id: TypeId) -> Option<&'static str> {
+    if let Some(tag) = builtins().tags.get(&type_id) {
+        return Some(tag);
+    }
+    get_types()
+        .values()
+        .find(|entry| (entry.type_id)() == type_id)
+        .map(|entry| entry.name)
+}
+
+/// Encode a type-erased `value` as a `(type tag, JSON)` pair that
+/// [`decode`] turns back into the same value.
+pub fn encode_value(value: &Value) -> anyhow::Result<(String, JsonValue)> {
+    let tag = tag_of(value)
+        .ok_or_else(|| anyhow::anyhow!("value of an unknown type, register it with #[type]"))?;
+    let mut json = Vec::new();
+    value.serialize(&mut serde_json::Serializer::new(&mut json))?;
+    Ok((tag.to_string(), serde_json::from_slice(&json)?))
+}
+
+/// Encode `value` as a `(type tag, JSON)` pair; fails for types that have no
+/// tag, which no runtime could decode.
+pub fn encode<T: Serialize + 'static>(value: &T) -> anyhow::Result<(String, JsonValue)> {
+    let tag = type_tag::<T>().ok_or_else(|| {
+        anyhow::anyhow!(
+            "literal of unknown type {}, register it with #[type]",
+            type_name::<T>()
+        )
+    })?;
+    Ok((tag.to_string(), serde_json::to_value(value)?))
+}
+
+/// Rebuild the exact value described by a type tag and its JSON form.
+pub fn decode(ty: &str, json: &JsonValue) -> anyhow::Result<Value> {
+    if let Some(decode) = builtins().decoders.get(ty) {
+        return decode(json).map_err(|err| anyhow::anyhow!("invalid literal of type {ty}: {err}"));
+    }
+    let entry = get_types()
+        .get(ty)
+        .copied()
+        .ok_or_else(|| anyhow::anyhow!("unknown literal type {ty}, register it with #[type]"))?;
+    let mut erased = <dyn erased_serde::Deserializer>::erase(json);
+    (entry.deserialize)(&mut erased)
+        .map_err(|err| anyhow::anyhow!("invalid literal of type {ty}: {err}"))
+}
+
+/// Check that `json` decodes as type tag `ty`. Tags this process cannot
+/// decode, such as `#[type]`s of task crates it does not link, pass unchecked.
+pub fn check(ty: &str, json: &JsonValue) -> anyhow::Result<()> {
+    if builtins().decoders.contains_key(ty) || get_types().contains_key(ty) {
+        decode(ty, json)?;
+    }
+    Ok(())
+}
+
+/// Gather values of type tag `ty` into one `Vec` of that type.
+pub fn collect(ty: &str, values: Vec<Value>) -> anyhow::Result<Value> {
+    if let Some(collect) = builtins().collectors.get(ty) {
+        return collect(values);
+    }
+    let entry = get_types()
+        .get(ty)
+        .copied()
+        .ok_or_else(|| anyhow::anyhow!("unknown collect type {ty}, register it with #[type]"))?;
+    (entry.collect)(values)
+}
+
+/// [`CollectFn`] for `T`; `#[type]` registers it for user types.
+pub fn collect_as<T: Serialize + Clone + Send + Sync + 'static>(
+    values: Vec<Value>,
+) -> anyhow::Result<Value> {
+    let items = values
+        .iter()
+        .map(|value| {
+            value
+                .downcast_ref::<T>()
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("collected value is not a {}", type_name::<T>()))
+        })
+        .collect::<anyhow::Result<Vec<T>>>()?;
+    Ok(Value::new(items))
+}
@@ -1,23 +1,40 @@
+use std::any::TypeId;
 use std::sync::OnceLock;
 
 use hashbrown::HashMap;
 
-use crate::{DynamicTaskContext, Task, Value};
+use crate::{AsyncTask, DynamicTaskContext, Task, Value};
 
 pub type PackFn = fn(Vec<Value>) -> Value;
 pub type UnpackFn = fn(Value) -> Vec<Value>;
+/// Gathers values of one type into a `Vec` of it, see [`crate::literal::collect`].
+pub type CollectFn = fn(Vec<Value>) -> anyhow::Result<Value>;
 pub type TaskImpl = Box<dyn Task<DynamicTaskContext> + Send + Sync>;
+pub type AsyncTaskImpl = Box<dyn AsyncTask<DynamicTaskContext> + Send + Sync>;
 pub type DeserializeFn =
     fn(&mut dyn erased_serde::Deserializer) -> Result<Value, erased_serde::Error>;
 
+/// Creates an instance of a registered task.
+#[derive(Clone, Copy)]
+pub enum TaskCreate {
+    /// A task an engine runs on a thread of its own.
+    Sync(fn() -> TaskImpl),
+    /// A task an engine drives on its async runtime, from `#[task]` on an
+    /// `async fn`.
+    Async(fn() -> AsyncTaskImpl),
+}
+
 #[derive(Clone, Copy)]
 pub struct TaskEntry {
     pub name: &'static str,
     pub author: &'static str,
-    pub create: fn() -> TaskImpl,
+    pub create: TaskCreate,
     pub version: &'static str,
     pub pack: Option<PackFn>,
     pub unpack: Option<UnpackFn>,
+    /// How many replicas of the task an in-process engine runs, each taking
+    /// calls from the same input; set with `#[task(concurrency = n)]`.
+    pub concurrency: usize,
 }
 
 inventory::collect!(TaskEntry);
@@ -37,8 +54,9 @@ pub fn get_tasks() -> HashMap<String, TaskEntry> {
 #[derive(Clone, Copy)]
 pub struct TypeEntry {
     pub name: &'static str,
-    pub type_id: &'static str,
+    pub type_id: fn() -> TypeId,
     pub deserialize: DeserializeFn,
+    pub collect: CollectFn,
 }
 
 inventory::collect!(TypeEntry);
@@ -58,7 +76,7 @@ pub fn get_types() -> HashMap<String, TypeEntry> {
 #[derive(Clone, Copy)]
 pub struct WorkflowEntry {
     pub id: &'static str,
-    pub build: fn() -> crate::ir::Workflow,
+    pub build: fn() -> anyhow::Result<crate::ir::Workflow>,
 }
 
 inventory::collect!(WorkflowEntry);
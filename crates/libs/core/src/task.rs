@@ -1,3 +1,5 @@
+use std::time::{Duration, Instant};
+
 use anyhow::Result;
 use async_trait::async_trait;
 use futures::{Stream, StreamExt, pin_mut};
@@ -6,6 +8,9 @@ use serde::de::DeserializeOwned;
 
 use crate::context::TaskContext;
 
+/// How often a batch waiting on `max_wait` checks for more input.
+const BATCH_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
 pub trait Task<C>
 where
     C: TaskContext,
@@ -28,8 +33,10 @@ where
 
     fn run(&mut self, context: C) -> Result<()> {
         while let Ok((id, x)) = context.recv() {
-            let y = self.call(x);
-            let _ = context.send(id, y);
+            if !context.is_cancelled(id) {
+                let y = self.call(x);
+                let _ = context.send(id, y);
+            }
             let _ = context.send_end(id);
         }
         Ok(())
@@ -45,36 +52,74 @@ where
 
     fn batch_size(&self) -> usize;
 
+    /// How long a partial batch waits for more input before it is called
+    /// anyway. `None` waits until the batch is full or the input closes.
+    fn max_wait(&self) -> Option<Duration> {
+        None
+    }
+
     fn call(&mut self, input: Vec<Self::Input>) -> Vec<Result<Self::Output>>;
 
     fn run(&mut self, context: C) -> Result<()> {
         let batch_size = self.batch_size();
+        let max_wait = self.max_wait();
         let mut ids = Vec::with_capacity(batch_size);
         let mut buf = Vec::with_capacity(batch_size);
+        let mut closed = false;
 
-        while let Ok((id, x)) = context.recv() {
+        while !closed {
+            let Ok((id, x)) = context.recv() else {
+                break;
+            };
             ids.push(id);
             buf.push(x);
 
-            if buf.len() >= batch_size {
-                #[allow(clippy::drain_collect)]
-                let ys = self.call(buf.drain(..).collect());
-                debug_assert_eq!(ys.len(), batch_size);
-
-                for (i, y) in ys.into_iter().enumerate() {
-                    let _ = context.send(ids[i], y);
-                    let _ = context.send_end(ids[i]);
+            let deadline = max_wait.map(|wait| Instant::now() + wait);
+            while buf.len() < batch_size {
+                let next = match deadline {
+                    None => context.recv(),
+                    Some(deadline) => match context.try_recv() {
+                        Ok(Some(next)) => Ok(next),
+                        Ok(None) => {
+                            let now = Instant::now();
+                            if now >= deadline {
+                                break;
+                            }
+                            std::thread::sleep(BATCH_POLL_INTERVAL.min(deadline - now));
+                            continue;
+                        }
+                        Err(err) => Err(err),
+                    },
+                };
+                match next {
+                    Ok((id, x)) => {
+                        ids.push(id);
+                        buf.push(x);
+                    }
+                    Err(_) => {
+                        closed = true;
+                        break;
+                    }
                 }
-                ids.clear();
             }
-        }
 
-        if !buf.is_empty() {
-            #[allow(clippy::drain_collect)]
-            let ys = self.call(buf.drain(..).collect());
-            for (i, y) in ys.into_iter().enumerate() {
-                let _ = context.send(ids[i], y);
-                let _ = context.send_end(ids[i]);
+            // Cancelled calls are left out of the batch and only ended.
+            let (live, input): (Vec<_>, Vec<_>) = ids
+                .iter()
+                .copied()
+                .zip(buf.drain(..))
+                .filter(|(id, _)| !context.is_cancelled(*id))
+                .unzip();
+            if !input.is_empty() {
+                let ys = self.call(input);
+                debug_assert_eq!(ys.len(), live.len());
+
+                for (id, y) in live.into_iter().zip(ys) {
+                    let _ = context.send(id, y);
+                }
+            }
+            for id in ids.drain(..) {
+                let _ = context.send_end(id);
             }
         }
         Ok(())
@@ -92,9 +137,13 @@ where
 
     fn run(&mut self, context: C) -> Result<()> {
         while let Ok((id, x)) = context.recv() {
-            let ys = self.call(x);
-            for y in ys {
-                let _ = context.send(id, y);
+            if !context.is_cancelled(id) {
+                for y in self.call(x) {
+                    let _ = context.send(id, y);
+                    if context.is_cancelled(id) {
+                        break;
+                    }
+                }
             }
             let _ = context.send_end(id);
         }
@@ -126,8 +175,10 @@ where
 
     async fn run(&mut self, context: C) -> Result<()> {
         while let Ok((id, x)) = context.recv_async().await {
-            let y = self.call(x).await;
-            let _ = context.send_async(id, y).await;
+            if !context.is_cancelled(id) {
+                let y = self.call(x).await;
+                let _ = context.send_async(id, y).await;
+            }
             let _ = context.send_end_async(id).await;
         }
         Ok(())
@@ -144,36 +195,67 @@ where
 
     fn batch_size(&self) -> usize;
 
+    /// See [`BatchedTask::max_wait`].
+    fn max_wait(&self) -> Option<Duration> {
+        None
+    }
+
     async fn call(&mut self, input: Vec<Self::Input>) -> Vec<Result<Self::Output>>;
 
     async fn run(&mut self, context: C) -> Result<()> {
         let batch_size = self.batch_size();
+        let max_wait = self.max_wait();
         let mut ids = Vec::with_capacity(batch_size);
         let mut buf = Vec::with_capacity(batch_size);
+        let mut closed = false;
 
-        while let Ok((id, x)) = context.recv_async().await {
+        while !closed {
+            let Ok((id, x)) = context.recv_async().await else {
+                break;
+            };
             ids.push(id);
             buf.push(x);
 
-            if buf.len() >= batch_size {
-                #[allow(clippy::drain_collect)]
-                let ys = self.call(buf.drain(..).collect()).await;
-                debug_assert_eq!(ys.len(), batch_size);
-
-                for (i, y) in ys.into_iter().enumerate() {
-                    let _ = context.send_async(ids[i], y).await;
-                    let _ = context.send_end_async(ids[i]).await;
+            let deadline = max_wait.map(|wait| tokio::time::Instant::now() + wait);
+            while buf.len() < batch_size {
+                let next = match deadline {
+                    None => context.recv_async().await,
+                    Some(deadline) => {
+                        match tokio::time::timeout_at(deadline, context.recv_async()).await {
+                            Ok(next) => next,
+                            Err(_) => break,
+                        }
+                    }
+                };
+                match next {
+                    Ok((id, x)) => {
+                        ids.push(id);
+                        buf.push(x);
+                    }
+                    Err(_) => {
+                        closed = true;
+                        break;
+                    }
                 }
-                ids.clear();
             }
-        }
 
-        if !buf.is_empty() {
-            #[allow(clippy::drain_collect)]
-            let ys = self.call(buf.drain(..).collect()).await;
-            for (i, y) in ys.into_iter().enumerate() {
-                let _ = context.send_async(ids[i], y).await;
-                let _ = context.send_end_async(ids[i]).await;
+            // Cancelled calls are left out of the batch and only ended.
+            let (live, input): (Vec<_>, Vec<_>) = ids
+                .iter()
+                .copied()
+                .zip(buf.drain(..))
+                .filter(|(id, _)| !context.is_cancelled(*id))
+                .unzip();
+            if !input.is_empty() {
+                let ys = self.call(input).await;
+                debug_assert_eq!(ys.len(), live.len());
+
+                for (id, y) in live.into_iter().zip(ys) {
+                    let _ = context.send_async(id, y).await;
+                }
+            }
+            for id in ids.drain(..) {
+                let _ = context.send_end_async(id).await;
             }
         }
         Ok(())
@@ -192,10 +274,15 @@ where
 
     async fn run(&mut self, context: C) -> Result<()> {
         while let Ok((id, x)) = context.recv_async().await {
-            let ys = self.call(x);
-            pin_mut!(ys);
-            while let Some(y) = ys.next().await {
-                let _ = context.send_async(id, y).await;
+            if !context.is_cancelled(id) {
+                let ys = self.call(x);
+                pin_mut!(ys);
+                while let Some(y) = ys.next().await {
+                    let _ = context.send_async(id, y).await;
+                    if context.is_cancelled(id) {
+                        break;
+                    }
+                }
             }
             let _ = context.send_end_async(id).await;
         }
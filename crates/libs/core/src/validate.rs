@@ -0,0 +1,384 @@
+//! Static checks for workflow IR.
+//!
+//! The engines trust the IR they are given; a broken op id or phi only shows
+//! up once a run reaches it. [`Workflow::validate`] catches these up front.
+
+use std::collections::{BTreeSet, VecDeque};
+use std::fmt;
+
+use hashbrown::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+
+use crate::ir::{Collect, Next, Operation, Workflow};
+use crate::{OpId, ValueId};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ValidationError {
+    /// A jump, branch or phi refers to an op that does not exist.
+    InvalidTarget { op: OpId, target: OpId },
+    /// A value is read on a path where it has not been defined yet.
+    UndefinedValue { op: OpId, value: ValueId },
+    /// A phi has no entry for one of the op's predecessors.
+    MissingPhiSource {
+        op: OpId,
+        phi: ValueId,
+        predecessor: OpId,
+    },
+    /// The entry op has phis, which are never applied on the way in.
+    EntryPhi { op: OpId },
+    /// A branch condition is produced by something other than a `bool`.
+    NonBoolCondition {
+        op: OpId,
+        value: ValueId,
+        ty: String,
+    },
+    /// No `Return` can be reached from the entry op.
+    NoReturn,
+    /// A collect's `from` is not a call, the collecting op has a call of its
+    /// own, or another op already collects the same call.
+    InvalidCollect { op: OpId, from: OpId },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidTarget { op, target } => {
+                write!(f, "op {op} refers to missing op {target}")
+            }
+            Self::UndefinedValue { op, value } => {
+                write!(f, "op {op} reads value {value} before it is defined")
+            }
+            Self::MissingPhiSource {
+                op,
+                phi,
+                predecessor,
+            } => write!(
+                f,
+                "phi {phi} in op {op} has no source for predecessor {predecessor}"
+            ),
+            Self::EntryPhi { op } => write!(f, "entry op {op} has phis"),
+            Self::NonBoolCondition { op, value, ty } => {
+                write!(f, "op {op} branches on value {value} of type {ty}")
+            }
+            Self::NoReturn => write!(f, "no reachable return"),
+            Self::InvalidCollect { op, from } => {
+                write!(f, "op {op} cannot collect the outputs of op {from}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl Workflow {
+    /// Check that op ids are in range, every value is defined on every path
+    /// before it is read, phis cover every predecessor, branch conditions are
+    /// `bool`s, collects gather from calls and a `Return` is reachable.
+    ///
+    /// Call outputs carry no type in the IR; those conditions were already
+    /// type-checked when the workflow was compiled.
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        self.check_targets(&mut errors);
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let reachable = self.reachable();
+        if !reachable
+            .iter()
+            .any(|&op| matches!(self.operations[op].next, Next::Return { .. }))
+        {
+            errors.push(ValidationError::NoReturn);
+        }
+        if let Some(entry) = self.operations.first()
+            && !entry.phis.is_empty()
+        {
+            errors.push(ValidationError::EntryPhi { op: 0 });
+        }
+        self.check_phi_sources(&reachable, &mut errors);
+        self.check_collects(&mut errors);
+        self.check_definitions(&reachable, &mut errors);
+        self.check_conditions(&reachable, &mut errors);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn check_targets(&self, errors: &mut Vec<ValidationError>) {
+        let len = self.operations.len();
+        for (op, operation) in self.operations.iter().enumerate() {
+            let phi_sources = operation
+                .phis
+                .iter()
+                .flat_map(|phi| phi.from.iter().map(|(from, _)| *from));
+            let collect_source = operation.collect.iter().map(|collect| collect.from);
+            for target in successors(operation)
+                .chain(phi_sources)
+                .chain(collect_source)
+            {
+                if target >= len {
+                    errors.push(ValidationError::InvalidTarget { op, target });
+                }
+            }
+        }
+    }
+
+    /// Ops reachable from the entry, in ascending order.
+    fn reachable(&self) -> BTreeSet<OpId> {
+        let mut reachable = BTreeSet::new();
+        if self.operations.is_empty() {
+            return reachable;
+        }
+        let mut queue = VecDeque::from([0]);
+        while let Some(op) = queue.pop_front() {
+            if reachable.insert(op) {
+                queue.extend(successors(&self.operations[op]));
+            }
+        }
+        reachable
+    }
+
+    fn predecessors(&self, reachable: &BTreeSet<OpId>) -> HashMap<OpId, BTreeSet<OpId>> {
+        let mut preds: HashMap<OpId, BTreeSet<OpId>> = HashMap::new();
+        for &op in reachable {
+            for next in successors(&self.operations[op]) {
+                preds.entry(next).or_default().insert(op);
+            }
+        }
+        preds
+    }
+
+    fn check_phi_sources(&self, reachable: &BTreeSet<OpId>, errors: &mut Vec<ValidationError>) {
+        let preds = self.predecessors(reachable);
+        for &op in reachable {
+            let Some(op_preds) = preds.get(&op) else {
+                continue;
+            };
+            for phi in &self.operations[op].phis {
+                for &predecessor in op_preds {
+                    if !phi.from.iter().any(|(from, _)| *from == predecessor) {
+                        errors.push(ValidationError::MissingPhiSource {
+                            op,
+                            phi: phi.output,
+                            predecessor,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_collects(&self, errors: &mut Vec<ValidationError>) {
+        for (op, operation) in self.operations.iter().enumerate() {
+            let Some(collect) = &operation.collect else {
+                continue;
+            };
+            if operation.call.is_some()
+                || self.operations[collect.from].call.is_none()
+                || self.collect_of(collect.from) != Some(op)
+            {
+                errors.push(ValidationError::InvalidCollect {
+                    op,
+                    from: collect.from,
+                });
+            }
+        }
+    }
+
+    /// Forward "defined on every path" analysis over the reachable ops.
+    ///
+    /// After a collect only what was defined where its fan-out started, plus
+    /// the collected `Vec`, is visible.
+    fn check_definitions(&self, reachable: &BTreeSet<OpId>, errors: &mut Vec<ValidationError>) {
+        let preds = self.predecessors(reachable);
+        let entry: HashSet<ValueId> = self.inputs.iter().map(|input| input.output).collect();
+
+        // `None` stands for "every value" until an op has been visited.
+        let mut defined_out: HashMap<OpId, Option<HashSet<ValueId>>> =
+            reachable.iter().map(|&op| (op, None)).collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &op in reachable {
+                let out = match &self.operations[op].collect {
+                    Some(collect) => self.collected_in(collect, &entry, &preds, &defined_out),
+                    None => self
+                        .defined_in(op, &entry, &preds, &defined_out)
+                        .map(|mut defined| {
+                            defined.extend(definitions(&self.operations[op]));
+                            defined
+                        }),
+                };
+                if defined_out[&op] != out {
+                    defined_out.insert(op, out);
+                    changed = true;
+                }
+            }
+        }
+
+        for &op in reachable {
+            let operation = &self.operations[op];
+            let Some(mut defined) = self.defined_in(op, &entry, &preds, &defined_out) else {
+                continue;
+            };
+            let mut check = |value: ValueId, defined: &HashSet<ValueId>| {
+                if !defined.contains(&value) {
+                    errors.push(ValidationError::UndefinedValue { op, value });
+                }
+            };
+
+            for phi in &operation.phis {
+                for (from, value) in &phi.from {
+                    if let Some(Some(from_out)) = defined_out.get(from) {
+                        check(*value, from_out);
+                    }
+                }
+            }
+            defined.extend(operation.literals.iter().map(|literal| literal.output));
+            defined.extend(operation.phis.iter().map(|phi| phi.output));
+            if let Some(call) = &operation.call {
+                for &input in &call.inputs {
+                    check(input, &defined);
+                }
+                defined.extend(call.outputs.iter().copied());
+            }
+            if let Some(collect) = &operation.collect {
+                check(collect.input, &defined);
+                defined = defined_out[&op].clone().unwrap_or_default();
+            }
+            match operation.next {
+                Next::Branch { var, .. } | Next::Return { var: Some(var) } => check(var, &defined),
+                Next::Jump { .. } | Next::Return { var: None } => {}
+            }
+        }
+    }
+
+    fn defined_in(
+        &self,
+        op: OpId,
+        entry: &HashSet<ValueId>,
+        preds: &HashMap<OpId, BTreeSet<OpId>>,
+        defined_out: &HashMap<OpId, Option<HashSet<ValueId>>>,
+    ) -> Option<HashSet<ValueId>> {
+        let sources = preds
+            .get(&op)
+            .into_iter()
+            .flatten()
+            .filter_map(|pred| defined_out[pred].as_ref());
+        let mut defined = (op == 0).then(|| entry.clone());
+        for source in sources {
+            match &mut defined {
+                Some(defined) => defined.retain(|value| source.contains(value)),
+                None => defined = Some(source.clone()),
+            }
+        }
+        defined
+    }
+
+    /// Values visible once `collect` continues in the context its fan-out
+    /// started from.
+    fn collected_in(
+        &self,
+        collect: &Collect,
+        entry: &HashSet<ValueId>,
+        preds: &HashMap<OpId, BTreeSet<OpId>>,
+        defined_out: &HashMap<OpId, Option<HashSet<ValueId>>>,
+    ) -> Option<HashSet<ValueId>> {
+        let from = &self.operations[collect.from];
+        let mut defined = self.defined_in(collect.from, entry, preds, defined_out)?;
+        defined.extend(from.literals.iter().map(|literal| literal.output));
+        defined.extend(from.phis.iter().map(|phi| phi.output));
+        defined.insert(collect.output);
+        Some(defined)
+    }
+
+    fn check_conditions(&self, reachable: &BTreeSet<OpId>, errors: &mut Vec<ValidationError>) {
+        let mut types: HashMap<ValueId, &str> = self
+            .inputs
+            .iter()
+            .map(|input| (input.output, input.ty.as_str()))
+            .collect();
+        for operation in &self.operations {
+            types.extend(
+                operation
+                    .literals
+                    .iter()
+                    .map(|literal| (literal.output, literal.ty.as_str())),
+            );
+        }
+        let phis: HashMap<ValueId, Vec<ValueId>> = self
+            .operations
+            .iter()
+            .flat_map(|operation| &operation.phis)
+            .map(|phi| {
+                (
+                    phi.output,
+                    phi.from.iter().map(|(_, value)| *value).collect(),
+                )
+            })
+            .collect();
+
+        for &op in reachable {
+            let Next::Branch { var, .. } = self.operations[op].next else {
+                continue;
+            };
+            if let Some(ty) = non_bool_source(var, &types, &phis, &mut HashSet::new()) {
+                errors.push(ValidationError::NonBoolCondition {
+                    op,
+                    value: var,
+                    ty: ty.to_string(),
+                });
+            }
+        }
+    }
+}
+
+fn successors(operation: &Operation) -> impl Iterator<Item = OpId> {
+    let (first, second) = match operation.next {
+        Next::Jump { next } => (Some(next), None),
+        Next::Branch {
+            true_next,
+            false_next,
+            ..
+        } => (Some(true_next), Some(false_next)),
+        Next::Return { .. } => (None, None),
+    };
+    first.into_iter().chain(second)
+}
+
+fn definitions(operation: &Operation) -> impl Iterator<Item = ValueId> + '_ {
+    operation
+        .literals
+        .iter()
+        .map(|literal| literal.output)
+        .chain(operation.phis.iter().map(|phi| phi.output))
+        .chain(
+            operation
+                .call
+                .iter()
+                .flat_map(|call| call.outputs.iter().copied()),
+        )
+}
+
+/// Known non-`bool` type feeding `value`, following phis back to their sources.
+fn non_bool_source<'a>(
+    value: ValueId,
+    types: &HashMap<ValueId, &'a str>,
+    phis: &HashMap<ValueId, Vec<ValueId>>,
+    seen: &mut HashSet<ValueId>,
+) -> Option<&'a str> {
+    if !seen.insert(value) {
+        return None;
+    }
+    if let Some(ty) = types.get(&value) {
+        return (*ty != "bool").then_some(*ty);
+    }
+    phis.get(&value)?
+        .iter()
+        .find_map(|source| non_bool_source(*source, types, phis, seen))
+}
@@ -129,6 +129,11 @@ impl Value {
         }
     }
 
+    /// `TypeId` of the value held.
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
     pub fn take<T: 'static>(self) -> Option<T> {
         if self.type_id == TypeId::of::<T>() {
             // SAFETY: The `type_id` is guaranteed to match the type stored in
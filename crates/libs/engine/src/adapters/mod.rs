@@ -1 +1,2 @@
+pub mod replay;
 pub mod simple;
@@ -0,0 +1,412 @@
+//! Drive a workflow over a [`Recording`] instead of running its tasks.
+//!
+//! Every call the kernel reaches is answered with the outputs recorded for
+//! its op, context path and inputs, so a run's `if`/`while` decisions can be
+//! stepped through offline, or checked against a changed workflow.
+
+use std::collections::{HashMap, VecDeque};
+
+use itertools::Itertools;
+use namu_core::ir::{Next, Workflow};
+use namu_core::{ContextId, OpId, ValueId};
+use serde_json::Value as JsonValue;
+
+use crate::kernel::{CallSpec, EngineKernel, JsonRuntime, KernelPlan};
+use crate::runtime::graph::ContextGraph;
+use crate::runtime::replay::{
+    CallRecord, CtxPath, PathStep, RecordedOutput, Recording, input_hash,
+};
+use crate::runtime::store::{InMemoryStore, ValueStore};
+
+/// A call the replay reached.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplayedCall {
+    pub op_id: OpId,
+    pub path: CtxPath,
+    pub task_id: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplayOutcome {
+    /// What each context that returned returned, in replay order.
+    pub results: Vec<JsonValue>,
+    /// The calls reached, in replay order.
+    pub calls: Vec<ReplayedCall>,
+    /// Recorded calls the replay never reached: its control flow parted
+    /// from the recorded run's.
+    pub unused: Vec<CallRecord>,
+}
+
+/// Replay `recording` to its end.
+///
+/// Fails at the first call reached that has no recorded output left, naming
+/// it; [`ReplayOutcome::calls`] of a replay that does not fail shows the path
+/// taken.
+pub async fn replay(recording: &Recording) -> anyhow::Result<ReplayOutcome> {
+    let mut replayer = Replayer {
+        workflow: &recording.workflow,
+        kernel: EngineKernel::new(JsonRuntime),
+        store: InMemoryStore::new(),
+        recorded: recording.by_key(),
+        paths: HashMap::new(),
+        fan_items: HashMap::new(),
+        fan_ins: HashMap::new(),
+        queue: VecDeque::new(),
+        results: Vec::new(),
+        calls: Vec::new(),
+    };
+
+    let root = replayer.store.create_root();
+    replayer.paths.insert(root, CtxPath::root());
+    let root = replayer
+        .kernel
+        .seed_inputs(
+            replayer.workflow,
+            &replayer.store,
+            root,
+            recording.inputs.clone(),
+        )
+        .await?;
+    replayer.queue.push_back(Work::Drive {
+        ctx_id: root,
+        op_id: 0,
+        pred_op: None,
+        done: Vec::new(),
+    });
+    while let Some(work) = replayer.queue.pop_front() {
+        replayer.step(work).await?;
+    }
+
+    let unused = replayer
+        .recorded
+        .into_values()
+        .flatten()
+        .sorted_by_key(|(idx, _)| *idx)
+        .map(|(_, record)| record)
+        .collect();
+    Ok(ReplayOutcome {
+        results: replayer.results,
+        calls: replayer.calls,
+        unused,
+    })
+}
+
+enum Work {
+    /// Drive `ctx_id` from `op_id`.
+    Drive {
+        ctx_id: ContextId,
+        op_id: OpId,
+        pred_op: Option<OpId>,
+        done: Vec<OpId>,
+    },
+    /// Continue `ctx_id` after the call or collect at `op_id`.
+    Resume {
+        ctx_id: ContextId,
+        op_id: OpId,
+        done: Vec<OpId>,
+    },
+}
+
+/// Items of a collected stream that fanned out from the context this is
+/// keyed by.
+struct FanIn {
+    items: usize,
+    values: Vec<(ContextId, JsonValue)>,
+}
+
+struct Replayer<'a> {
+    workflow: &'a Workflow,
+    kernel: EngineKernel<JsonRuntime>,
+    store: InMemoryStore<JsonValue>,
+    recorded: HashMap<(OpId, CtxPath, String), VecDeque<(usize, CallRecord)>>,
+    paths: HashMap<ContextId, CtxPath>,
+    /// The fan-out context and op of each item of a collected stream.
+    fan_items: HashMap<ContextId, (ContextId, OpId)>,
+    fan_ins: HashMap<ContextId, FanIn>,
+    queue: VecDeque<Work>,
+    results: Vec<JsonValue>,
+    calls: Vec<ReplayedCall>,
+}
+
+impl Replayer<'_> {
+    async fn step(&mut self, work: Work) -> anyhow::Result<()> {
+        match work {
+            Work::Drive {
+                ctx_id,
+                op_id,
+                pred_op,
+                mut done,
+            } => {
+                let mut plans = self
+                    .kernel
+                    .drive_until_actions(
+                        self.workflow,
+                        &self.store,
+                        ctx_id,
+                        op_id,
+                        pred_op,
+                        &mut done,
+                    )
+                    .await?;
+                if plans.len() > 1 {
+                    return self.join(ctx_id, plans, done).await;
+                }
+                match plans.pop().expect("kernel yields at least one plan") {
+                    KernelPlan::Dispatch {
+                        op_id,
+                        ctx_id,
+                        call,
+                    } => self.call(op_id, ctx_id, &call, done).await,
+                    KernelPlan::Return { ctx_id, return_var } => {
+                        self.send_result(ctx_id, return_var).await
+                    }
+                    KernelPlan::Collect { op_id, ctx_id, var } => {
+                        self.arrive_at_collect(op_id, ctx_id, var).await
+                    }
+                }
+            }
+            Work::Resume {
+                ctx_id,
+                op_id,
+                done,
+            } => {
+                let next = &self.workflow.operations[op_id].next;
+                match self.kernel.resolve_next(&self.store, ctx_id, next).await? {
+                    Some(next_op) => {
+                        self.queue.push_back(Work::Drive {
+                            ctx_id,
+                            op_id: next_op,
+                            pred_op: Some(op_id),
+                            done,
+                        });
+                        Ok(())
+                    }
+                    None => {
+                        let return_var = match next {
+                            Next::Return { var } => *var,
+                            _ => None,
+                        };
+                        self.send_result(ctx_id, return_var).await
+                    }
+                }
+            }
+        }
+    }
+
+    /// The outputs recorded for the call of `op_id` in `ctx_id`.
+    async fn take_outputs(
+        &mut self,
+        op_id: OpId,
+        ctx_id: ContextId,
+        call: &CallSpec,
+    ) -> anyhow::Result<Vec<RecordedOutput>> {
+        let inputs = self.store.get_values(ctx_id, &call.inputs).await?;
+        let path = self.paths[&ctx_id].clone();
+        let key = (op_id, path.clone(), input_hash(&inputs));
+        let (_, record) = self
+            .recorded
+            .get_mut(&key)
+            .and_then(VecDeque::pop_front)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "no recorded output for op {op_id} ({}) at {path} with inputs {}",
+                    call.task_id,
+                    key.2
+                )
+            })?;
+        self.calls.push(ReplayedCall {
+            op_id,
+            path,
+            task_id: call.task_id.clone(),
+        });
+        Ok(record.outputs)
+    }
+
+    async fn call(
+        &mut self,
+        op_id: OpId,
+        ctx_id: ContextId,
+        call: &CallSpec,
+        done: Vec<OpId>,
+    ) -> anyhow::Result<()> {
+        let outputs = self.take_outputs(op_id, ctx_id, call).await?;
+        let collected = self.workflow.collect_of(op_id);
+        let mut items = 0;
+        for (index, output) in outputs.into_iter().enumerate() {
+            let RecordedOutput::Values(values) = output else {
+                continue;
+            };
+            let child = self.create_child(ctx_id, PathStep::Output { op_id, index });
+            self.set_outputs(child, &call.outputs, values).await?;
+            if collected.is_some() {
+                self.fan_items.insert(child, (ctx_id, op_id));
+                items += 1;
+            }
+            self.queue.push_back(Work::Resume {
+                ctx_id: child,
+                op_id,
+                done: done.clone(),
+            });
+        }
+        if let Some(collect_op) = collected {
+            self.fan_ins.insert(
+                ctx_id,
+                FanIn {
+                    items,
+                    values: Vec::new(),
+                },
+            );
+            self.gather_if_complete(ctx_id, collect_op).await?;
+        }
+        Ok(())
+    }
+
+    /// Continue once per combination of the outputs of calls dispatched
+    /// together, as the engines do.
+    async fn join(
+        &mut self,
+        ctx_id: ContextId,
+        plans: Vec<KernelPlan>,
+        mut done: Vec<OpId>,
+    ) -> anyhow::Result<()> {
+        let mut members = Vec::with_capacity(plans.len());
+        for plan in plans {
+            let KernelPlan::Dispatch { op_id, call, .. } = plan else {
+                unreachable!("return plans are never grouped");
+            };
+            let values = self
+                .take_outputs(op_id, ctx_id, &call)
+                .await?
+                .into_iter()
+                .filter_map(|output| match output {
+                    RecordedOutput::Values(values) => Some(values),
+                    RecordedOutput::Error(_) => None,
+                })
+                .collect::<Vec<_>>();
+            members.push((op_id, call.outputs, values));
+        }
+        let first_op = members[0].0;
+        done.extend(members.iter().map(|(op_id, _, _)| *op_id));
+
+        let combinations = members
+            .iter()
+            .map(|(_, _, values)| values.iter())
+            .multi_cartesian_product()
+            .collect::<Vec<_>>();
+        for (index, combination) in combinations.into_iter().enumerate() {
+            let child = self.create_child(
+                ctx_id,
+                PathStep::Join {
+                    op_id: first_op,
+                    index,
+                },
+            );
+            for ((_, outputs, _), values) in members.iter().zip(combination) {
+                self.set_outputs(child, outputs, values.clone()).await?;
+            }
+            self.queue.push_back(Work::Drive {
+                ctx_id: child,
+                op_id: first_op,
+                pred_op: None,
+                done: done.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    async fn arrive_at_collect(
+        &mut self,
+        op_id: OpId,
+        ctx_id: ContextId,
+        var: ValueId,
+    ) -> anyhow::Result<()> {
+        let from = self.workflow.operations[op_id]
+            .collect
+            .as_ref()
+            .expect("collect plan for op without collect")
+            .from;
+        let mut cursor = Some(ctx_id);
+        let (item_ctx, fan_ctx) = loop {
+            let ctx = cursor.ok_or_else(|| {
+                anyhow::anyhow!(
+                    "context {ctx_id} reached a collect outside the fan-out of op {from}"
+                )
+            })?;
+            if let Some(&(fan_ctx, fan_op)) = self.fan_items.get(&ctx)
+                && fan_op == from
+            {
+                break (ctx, fan_ctx);
+            }
+            cursor = self.store.parent(ctx).await?;
+        };
+        let value = self.store.get_value(ctx_id, var).await?;
+        self.fan_ins
+            .get_mut(&fan_ctx)
+            .expect("fan-in of a collected stream")
+            .values
+            .push((item_ctx, value));
+        self.gather_if_complete(fan_ctx, op_id).await
+    }
+
+    async fn gather_if_complete(&mut self, fan_ctx: ContextId, op_id: OpId) -> anyhow::Result<()> {
+        let complete = self
+            .fan_ins
+            .get(&fan_ctx)
+            .is_some_and(|fan_in| fan_in.values.len() == fan_in.items);
+        if !complete {
+            return Ok(());
+        }
+        let fan_in = self.fan_ins.remove(&fan_ctx).expect("complete fan-in");
+        let values = fan_in
+            .values
+            .into_iter()
+            .sorted_by_key(|(item_ctx, _)| *item_ctx)
+            .map(|(_, value)| value)
+            .collect();
+
+        let ctx_id = self.store.create_child(fan_ctx);
+        self.paths.insert(ctx_id, self.paths[&fan_ctx].clone());
+        let ctx_id = self
+            .kernel
+            .bind_collected(self.workflow, &self.store, ctx_id, op_id, values)
+            .await?;
+        self.queue.push_back(Work::Resume {
+            ctx_id,
+            op_id,
+            done: Vec::new(),
+        });
+        Ok(())
+    }
+
+    fn create_child(&mut self, parent: ContextId, step: PathStep) -> ContextId {
+        let ctx_id = self.store.create_child(parent);
+        let path = self.paths[&parent].push(step);
+        self.paths.insert(ctx_id, path);
+        ctx_id
+    }
+
+    async fn set_outputs(
+        &self,
+        ctx_id: ContextId,
+        outputs: &[ValueId],
+        values: Vec<JsonValue>,
+    ) -> anyhow::Result<()> {
+        for (out_id, value) in outputs.iter().copied().zip(values) {
+            self.store.set_value(ctx_id, out_id, value).await?;
+        }
+        Ok(())
+    }
+
+    async fn send_result(
+        &mut self,
+        ctx_id: ContextId,
+        return_var: Option<ValueId>,
+    ) -> anyhow::Result<()> {
+        let result = match return_var {
+            Some(var) => self.store.get_value(ctx_id, var).await?,
+            None => JsonValue::Null,
+        };
+        self.results.push(result);
+        Ok(())
+    }
+}
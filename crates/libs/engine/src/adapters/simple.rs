@@ -1,5 +1,6 @@
-use std::sync::Arc;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
 use std::thread;
 
 use async_trait::async_trait;
@@ -7,22 +8,44 @@ use inventory;
 use itertools::Itertools;
 use kanal::{Receiver, Sender as OneShotSender, Sender, bounded, unbounded};
 use namu_core::ir::Workflow;
-use namu_core::registry::{PackFn, TaskEntry, TaskImpl, UnpackFn};
-use namu_core::{ContextId, DynamicTaskContext, Value};
+use namu_core::registry::{AsyncTaskImpl, PackFn, TaskCreate, TaskEntry, TaskImpl, UnpackFn};
+use namu_core::{CancelSignal, ContextId, DynamicTaskContext, TaskEnd, TaskError, Value, literal};
 use scc::ebr::Guard;
 use scc::{HashIndex, HashMap};
+use serde_json::Value as JsonValue;
 
 use crate::kernel::{CallSpec, CoreValueRuntime, EngineKernel, KernelPlan};
-use crate::runtime::store::ValueStore;
+use crate::runtime::replay::{
+    CallRecord, CtxPath, PathStep, RecordDir, RecordedOutput, input_hash,
+};
+use crate::runtime::store::{CallOutput, ChildKey, RunStore};
 use crate::store::InMemoryStore;
 use crate::traits::engine::{Engine, TaskRegistry};
+use crate::traits::observer::{RunObserver, RunOutcome};
 
-struct RunContext<'a> {
+struct RunContext<'a, S> {
+    run_id: usize,
+    /// Cancels the run, or single calls of it.
+    cancel: &'a CancelSignal,
+    observers: &'a [Arc<dyn RunObserver>],
+    recorder: Option<&'a RecordDir>,
     kernel: &'a EngineKernel<CoreValueRuntime>,
-    store: &'a InMemoryStore<Value>,
+    store: &'a S,
     workflow: &'a Workflow,
     ctx_origin: &'a HashIndex<ContextId, usize>,
+    call_groups: &'a HashIndex<ContextId, (ContextId, usize)>,
+    join_groups: &'a HashMap<ContextId, JoinGroup>,
+    fan_items: &'a HashIndex<ContextId, (ContextId, usize)>,
+    fan_ins: &'a HashMap<ContextId, FanIn>,
+    ctx_done: &'a HashIndex<ContextId, Vec<usize>>,
     finished_ctxs: &'a HashIndex<ContextId, ()>,
+    /// Outputs sent so far by the call dispatched in a context.
+    output_counts: &'a HashMap<ContextId, usize>,
+    /// Paths of the contexts of a recorded run.
+    ctx_paths: &'a HashIndex<ContextId, CtxPath>,
+    /// What the call dispatched in a context sent so far, if recorded.
+    records: &'a HashMap<ContextId, CallRecord>,
+    event_tx: &'a Sender<TaskEvent>,
     result_tx: &'a Sender<Value>,
     active_ctxs: &'a AtomicUsize,
     finish_tx: &'a OneShotSender<()>,
@@ -31,14 +54,27 @@ struct RunContext<'a> {
     unpack_map: &'a HashIndex<String, UnpackFn>,
 }
 
-impl<'a> Clone for RunContext<'a> {
+impl<'a, S> Clone for RunContext<'a, S> {
     fn clone(&self) -> Self {
         RunContext {
+            run_id: self.run_id,
+            cancel: self.cancel,
+            observers: self.observers,
+            recorder: self.recorder,
             kernel: self.kernel,
             store: self.store,
             workflow: self.workflow,
             ctx_origin: self.ctx_origin,
+            call_groups: self.call_groups,
+            join_groups: self.join_groups,
+            fan_items: self.fan_items,
+            fan_ins: self.fan_ins,
+            ctx_done: self.ctx_done,
             finished_ctxs: self.finished_ctxs,
+            output_counts: self.output_counts,
+            ctx_paths: self.ctx_paths,
+            records: self.records,
+            event_tx: self.event_tx,
             result_tx: self.result_tx,
             active_ctxs: self.active_ctxs,
             finish_tx: self.finish_tx,
@@ -49,65 +85,157 @@ impl<'a> Clone for RunContext<'a> {
     }
 }
 
-pub struct SimpleEngineInner {
+impl<S> RunContext<'_, S> {
+    fn observe(&self, notify: impl Fn(&dyn RunObserver)) {
+        for observer in self.observers {
+            notify(observer.as_ref());
+        }
+    }
+}
+
+pub struct SimpleEngineInner<S> {
     kernel: EngineKernel<CoreValueRuntime>,
-    store: InMemoryStore<Value>,
+    store: S,
     workflows: HashIndex<usize, Workflow>,
     workflow_counter: AtomicUsize,
     runs: HashIndex<usize, usize>,
+    run_inputs: HashMap<usize, Vec<Value>>,
     run_counter: AtomicUsize,
-    tasks: HashIndex<String, TaskImpl>,
+    tasks: HashIndex<String, RegisteredTask>,
     pack_map: HashIndex<String, PackFn>,
     unpack_map: HashIndex<String, UnpackFn>,
+    /// Replicas run of each task; one if unset.
+    concurrency: HashIndex<String, usize>,
     run_results: HashMap<usize, Receiver<Value>>,
     run_result_senders: HashMap<usize, Sender<Value>>,
+    run_cancels: HashMap<usize, RunCancel>,
+    observers: RwLock<Vec<Arc<dyn RunObserver>>>,
+    recorder: RwLock<Option<Arc<RecordDir>>>,
+}
+
+/// A registered task; its replicas run on threads of their own, or as
+/// tasks on the tokio runtime for an async one.
+#[derive(Clone)]
+enum RegisteredTask {
+    Sync(TaskImpl),
+    Async(AsyncTaskImpl),
+}
+
+/// Cancellation handles of a run that has not finished.
+#[derive(Clone)]
+struct RunCancel {
+    signal: CancelSignal,
+    tx: Sender<()>,
+    rx: Receiver<()>,
 }
 
-pub struct SimpleEngine {
-    inner: Arc<SimpleEngineInner>,
+/// Runs workflows in process, keeping their state in a [`RunStore`]; by
+/// default an [`InMemoryStore`].
+pub struct SimpleEngine<S = InMemoryStore<Value>> {
+    inner: Arc<SimpleEngineInner<S>>,
 }
 
 impl SimpleEngine {
     pub fn new() -> SimpleEngine {
+        SimpleEngine::with_store(InMemoryStore::new())
+    }
+
+    /// Construct the engine and register tasks collected via `inventory`.
+    pub fn with_registered() -> SimpleEngine {
+        let engine = Self::new();
+        engine.register_all_tasks();
+        engine
+    }
+}
+
+impl<S: RunStore> SimpleEngine<S> {
+    /// Construct the engine on `store`. A store that outlives the process,
+    /// like [`crate::store::SqliteStore`], lets a run resume after a restart.
+    pub fn with_store(store: S) -> SimpleEngine<S> {
         SimpleEngine {
             inner: Arc::new(SimpleEngineInner {
                 kernel: EngineKernel::new(CoreValueRuntime),
-                store: InMemoryStore::new(),
+                store,
                 workflows: HashIndex::new(),
                 workflow_counter: AtomicUsize::new(0),
                 runs: HashIndex::new(),
+                run_inputs: HashMap::new(),
                 run_counter: AtomicUsize::new(0),
                 tasks: HashIndex::new(),
                 pack_map: HashIndex::new(),
                 unpack_map: HashIndex::new(),
+                concurrency: HashIndex::new(),
                 run_results: HashMap::new(),
                 run_result_senders: HashMap::new(),
+                run_cancels: HashMap::new(),
+                observers: RwLock::new(Vec::new()),
+                recorder: RwLock::new(None),
             }),
         }
     }
 
-    /// Construct the engine and register tasks collected via `inventory`.
-    pub fn with_registered() -> SimpleEngine {
-        let engine = Self::new();
-        engine.register_all_tasks();
-        engine
-    }
-
-    fn register_all_tasks(&self) {
+    /// Register every task collected via `inventory`.
+    pub fn register_all_tasks(&self) {
         for entry in inventory::iter::<TaskEntry> {
-            let task: TaskImpl = (entry.create)();
-            self.add_task_sync(entry.name, task, entry.pack, entry.unpack);
+            let task = match entry.create {
+                TaskCreate::Sync(create) => RegisteredTask::Sync(create()),
+                TaskCreate::Async(create) => RegisteredTask::Async(create()),
+            };
+            self.insert_task(entry.name, task, entry.pack, entry.unpack);
+            self.set_concurrency(entry.name, entry.concurrency);
         }
     }
 
-    pub fn get_result(&self, run_id: usize) -> Receiver<Value> {
-        self.inner.run_results.get(&run_id).unwrap().clone()
+    /// Run `replicas` instances of `task_name` in runs started from now on,
+    /// each a [`namu_core::Task::clone_boxed`] of the registered task taking calls from
+    /// one shared input. A call runs on one replica from start to end, so a
+    /// stream's outputs keep their order.
+    pub fn set_concurrency(&self, task_name: &str, replicas: usize) {
+        let replicas = replicas.max(1);
+        self.inner.concurrency.remove(task_name);
+        let _ = self
+            .inner
+            .concurrency
+            .insert(task_name.to_string(), replicas);
+    }
+
+    /// Notify `observer` of the progress of runs started from now on.
+    pub fn add_observer(&self, observer: Arc<dyn RunObserver>) {
+        self.inner
+            .observers
+            .write()
+            .expect("observers lock poisoned")
+            .push(observer);
     }
 
-    fn add_task_sync(
+    /// Record the task outputs of runs started from now on to
+    /// `<dir>/<run_id>.jsonl`, see [`crate::runtime::replay`]. Every value
+    /// they pass must be a built-in type or one registered with `#[type]`.
+    pub fn record_to(&self, dir: impl Into<PathBuf>) {
+        *self.inner.recorder.write().expect("recorder lock poisoned") =
+            Some(Arc::new(RecordDir::new(dir)));
+    }
+
+    /// Take the receiver of `run_id`'s results, before or after the run
+    /// finished; it closes once the run is done. `None` if the run is unknown
+    /// or its receiver was taken already.
+    pub fn get_result(&self, run_id: usize) -> Option<Receiver<Value>> {
+        self.inner.run_results.remove(&run_id).map(|(_, rx)| rx)
+    }
+
+    /// Drop the bookkeeping of a run once it finished or was cancelled. Its
+    /// results stay until [`SimpleEngine::get_result`] takes them.
+    fn finish_run(&self, run_id: usize) {
+        let _ = self.inner.run_inputs.remove(&run_id);
+        let _ = self.inner.run_result_senders.remove(&run_id);
+        let _ = self.inner.run_cancels.remove(&run_id);
+        self.inner.runs.remove(&run_id);
+    }
+
+    fn insert_task(
         &self,
         task_name: &str,
-        task: TaskImpl,
+        task: RegisteredTask,
         pack: Option<PackFn>,
         unpack: Option<UnpackFn>,
     ) {
@@ -132,7 +260,7 @@ impl Default for SimpleEngine {
 }
 
 #[async_trait]
-impl Engine for SimpleEngine {
+impl<S: RunStore + 'static> Engine for SimpleEngine<S> {
     type WorkflowId = usize;
     type RunId = usize;
 
@@ -146,17 +274,28 @@ impl Engine for SimpleEngine {
         id
     }
 
-    async fn create_run(&self, workflow_id: Self::WorkflowId) -> Self::RunId {
+    async fn create_run(&self, workflow_id: Self::WorkflowId, inputs: Vec<Value>) -> Self::RunId {
         let id = self.inner.run_counter.fetch_add(1, Ordering::Release);
         if self.inner.runs.get(&id).is_some() {
             eprintln!("[engine] Replaced existing run mapping with id {id}");
             self.inner.runs.remove(&id);
         }
         self.inner.runs.insert(id, workflow_id).unwrap();
+        let _ = self.inner.run_inputs.upsert(id, inputs);
 
         let (result_tx, result_rx) = unbounded();
         self.inner.run_results.insert(id, result_rx).unwrap();
         self.inner.run_result_senders.insert(id, result_tx).unwrap();
+
+        let (tx, rx) = bounded(1);
+        let _ = self.inner.run_cancels.upsert(
+            id,
+            RunCancel {
+                signal: CancelSignal::new(),
+                tx,
+                rx,
+            },
+        );
         id
     }
 
@@ -169,10 +308,38 @@ impl Engine for SimpleEngine {
             .cloned()
             .unwrap();
         let result_tx = self.inner.run_result_senders.get(&run_id).unwrap().clone();
+        let cancel = self.inner.run_cancels.get(&run_id).unwrap().clone();
+        let observers = self
+            .inner
+            .observers
+            .read()
+            .expect("observers lock poisoned")
+            .clone();
+        let recorder = self
+            .inner
+            .recorder
+            .read()
+            .expect("recorder lock poisoned")
+            .clone();
+        if cancel.signal.is_cancelled() {
+            self.finish_run(run_id);
+            for observer in &observers {
+                observer.on_run_finished(run_id, RunOutcome::Cancelled);
+            }
+            return Ok(());
+        }
 
         let task_senders = HashIndex::new();
         let ctx_origin: HashIndex<ContextId, usize> = HashIndex::new();
+        let call_groups: HashIndex<ContextId, (ContextId, usize)> = HashIndex::new();
+        let join_groups: HashMap<ContextId, JoinGroup> = HashMap::new();
+        let fan_items: HashIndex<ContextId, (ContextId, usize)> = HashIndex::new();
+        let fan_ins: HashMap<ContextId, FanIn> = HashMap::new();
+        let ctx_done: HashIndex<ContextId, Vec<usize>> = HashIndex::new();
         let finished_ctxs: HashIndex<ContextId, ()> = HashIndex::new();
+        let output_counts: HashMap<ContextId, usize> = HashMap::new();
+        let ctx_paths: HashIndex<ContextId, CtxPath> = HashIndex::new();
+        let records: HashMap<ContextId, CallRecord> = HashMap::new();
 
         let (finish_tx, finish_rx) = bounded::<()>(1);
         let active_ctxs = AtomicUsize::new(0);
@@ -180,11 +347,24 @@ impl Engine for SimpleEngine {
         let (event_tx, event_rx) = unbounded::<TaskEvent>();
 
         let run_ctx = RunContext {
+            run_id,
+            cancel: &cancel.signal,
+            observers: &observers,
+            recorder: recorder.as_deref(),
             kernel: &self.inner.kernel,
             store: &self.inner.store,
             workflow: &workflow,
             ctx_origin: &ctx_origin,
+            call_groups: &call_groups,
+            join_groups: &join_groups,
+            fan_items: &fan_items,
+            fan_ins: &fan_ins,
+            ctx_done: &ctx_done,
             finished_ctxs: &finished_ctxs,
+            output_counts: &output_counts,
+            ctx_paths: &ctx_paths,
+            records: &records,
+            event_tx: &event_tx,
             result_tx: &result_tx,
             active_ctxs: &active_ctxs,
             finish_tx: &finish_tx,
@@ -214,41 +394,98 @@ impl Engine for SimpleEngine {
                     .peek(&task_name, &Guard::new())
                     .cloned()
                     .unwrap();
-
-                thread::spawn(move || {
-                    let mut task = task;
-                    if let Err(e) = task.prepare() {
-                        panic!("prepare error: {e}");
-                    }
-                    if let Err(e) = task.run(DynamicTaskContext::new(in_rx, out_tx)) {
-                        panic!("run error: {e}");
-                    }
-                });
+                let replicas = self
+                    .inner
+                    .concurrency
+                    .peek(&task_name, &Guard::new())
+                    .copied()
+                    .unwrap_or(1);
+                let context =
+                    DynamicTaskContext::new(in_rx, out_tx).with_cancel(cancel.signal.clone());
 
                 let event_tx = event_tx.clone();
-                let task_name_clone = task_name.clone();
-                thread::spawn(move || {
-                    while let Ok((ctx_id, out_box)) = out_rx.recv() {
-                        let _ = event_tx.send(TaskEvent {
-                            task_name: task_name_clone.clone(),
-                            ctx_id,
-                            output: out_box,
+                let forward = move |ctx_id, out_box| {
+                    let _ = event_tx.send(TaskEvent {
+                        task_name: task_name.clone(),
+                        ctx_id,
+                        output: TaskOutput::Sent(out_box),
+                    });
+                };
+
+                match task {
+                    RegisteredTask::Sync(task) => {
+                        for _ in 0..replicas {
+                            let mut task = task.clone_boxed();
+                            let context = context.clone();
+                            thread::spawn(move || {
+                                if let Err(e) = task.prepare() {
+                                    panic!("prepare error: {e}");
+                                }
+                                if let Err(e) = task.run(context) {
+                                    panic!("run error: {e}");
+                                }
+                            });
+                        }
+                        thread::spawn(move || {
+                            while let Ok((ctx_id, out_box)) = out_rx.recv() {
+                                forward(ctx_id, out_box);
+                            }
                         });
                     }
-                });
+                    RegisteredTask::Async(task) => {
+                        for _ in 0..replicas {
+                            let mut task = task.clone_box();
+                            let context = context.clone();
+                            tokio::spawn(async move {
+                                if let Err(e) = task.prepare().await {
+                                    panic!("prepare error: {e}");
+                                }
+                                if let Err(e) = task.run(context).await {
+                                    panic!("run error: {e}");
+                                }
+                            });
+                        }
+                        tokio::spawn(async move {
+                            while let Ok((ctx_id, out_box)) = out_rx.as_async().recv().await {
+                                forward(ctx_id, out_box);
+                            }
+                        });
+                    }
+                }
             });
 
         // Kick off the root context.
-        let root_ctx = self.inner.store.create_root();
+        let inputs = self
+            .inner
+            .run_inputs
+            .remove(&run_id)
+            .map(|(_, inputs)| inputs)
+            .unwrap_or_default();
+        if let Some(recorder) = &recorder {
+            recorder.start(run_id, &workflow, to_json(&inputs)?)?;
+        }
+        let root = self
+            .inner
+            .store
+            .open_run(run_id, &workflow, &inputs)
+            .await?;
+        run_ctx.observe(|observer| observer.on_context_created(run_id, root, None));
+        let root_ctx = self
+            .inner
+            .kernel
+            .seed_inputs(&workflow, &self.inner.store, root, inputs)
+            .await?;
         run_ctx.active_ctxs.fetch_add(1, Ordering::Release);
-        drive_from(&run_ctx, root_ctx, 0, None).await?;
+        drive_from(&run_ctx, root_ctx, 0, None, Vec::new()).await?;
 
         let finish_rx_async = finish_rx.as_async();
         let event_rx_async = event_rx.as_async();
+        let cancel_rx_async = cancel.rx.as_async();
 
         loop {
             tokio::select! {
                 _ = finish_rx_async.recv() => break,
+                _ = cancel_rx_async.recv() => break,
                 event = event_rx_async.recv() => {
                     match event {
                         Ok(event) => {
@@ -260,16 +497,33 @@ impl Engine for SimpleEngine {
             }
         }
 
-        let _ = self.inner.run_results.remove(&run_id);
-        let _ = self.inner.run_result_senders.remove(&run_id);
-        self.inner.runs.remove(&run_id);
+        // Finished contexts are already gone; a cancelled run leaves the rest.
+        let closed = self.inner.store.close_run(root).await;
+        self.finish_run(run_id);
+        let outcome = if cancel.signal.is_cancelled() {
+            RunOutcome::Cancelled
+        } else {
+            RunOutcome::Finished
+        };
+        run_ctx.observe(|observer| observer.on_run_finished(run_id, outcome));
+        closed
+    }
 
+    async fn cancel(&self, run_id: Self::RunId) -> anyhow::Result<()> {
+        let cancel = self
+            .inner
+            .run_cancels
+            .get(&run_id)
+            .map(|entry| entry.get().clone())
+            .ok_or_else(|| anyhow::anyhow!("run {run_id} is not active"))?;
+        cancel.signal.cancel();
+        let _ = cancel.tx.try_send(());
         Ok(())
     }
 }
 
 #[async_trait]
-impl TaskRegistry for SimpleEngine {
+impl<S: RunStore> TaskRegistry for SimpleEngine<S> {
     async fn add_task(
         &self,
         task_name: &str,
@@ -277,11 +531,21 @@ impl TaskRegistry for SimpleEngine {
         pack: Option<PackFn>,
         unpack: Option<UnpackFn>,
     ) {
-        self.add_task_sync(task_name, task, pack, unpack);
+        self.insert_task(task_name, RegisteredTask::Sync(task), pack, unpack);
+    }
+
+    async fn add_async_task(
+        &self,
+        task_name: &str,
+        task: AsyncTaskImpl,
+        pack: Option<PackFn>,
+        unpack: Option<UnpackFn>,
+    ) {
+        self.insert_task(task_name, RegisteredTask::Async(task), pack, unpack);
     }
 }
 
-impl Clone for SimpleEngine {
+impl<S> Clone for SimpleEngine<S> {
     fn clone(&self) -> Self {
         SimpleEngine {
             inner: self.inner.clone(),
@@ -292,46 +556,281 @@ impl Clone for SimpleEngine {
 struct TaskEvent {
     task_name: String,
     ctx_id: ContextId,
-    output: anyhow::Result<Value>,
+    output: TaskOutput,
+}
+
+enum TaskOutput {
+    /// As the task sent it: an output, an error or the end marker.
+    Sent(anyhow::Result<Value>),
+    /// An output the store recorded before the run was restarted.
+    Recorded(CallOutput),
+}
+
+/// Calls dispatched together from `parent`; each runs in its own call context
+/// and their outputs are joined once every one of them has ended.
+struct JoinGroup {
+    parent: ContextId,
+    ops: Vec<usize>,
+    /// Call context of each member.
+    ctxs: Vec<ContextId>,
+    /// A member failed, so the others are cancelled and nothing is joined.
+    failed: bool,
+    /// Calls of the segment that have run once the join completes.
+    done: Vec<usize>,
+    outputs: Vec<Vec<Vec<Value>>>,
+    pending: usize,
+}
+
+/// Items of a collected stream call that ran in the context this is keyed by.
+#[derive(Default)]
+struct FanIn {
+    items: usize,
+    /// The stream has sent its end marker, so `items` is final.
+    ended: bool,
+    /// Values handed in so far, by item context.
+    values: Vec<(ContextId, Value)>,
 }
 
-async fn drive_from(
-    run_ctx: &RunContext<'_>,
+async fn drive_from<S: RunStore>(
+    run_ctx: &RunContext<'_, S>,
     ctx_id: ContextId,
     start_op: usize,
     pred_op: Option<usize>,
+    mut done: Vec<usize>,
 ) -> anyhow::Result<()> {
-    match run_ctx
+    let mut plans = run_ctx
         .kernel
-        .drive_until_action(run_ctx.workflow, run_ctx.store, ctx_id, start_op, pred_op)
-        .await?
-    {
+        .drive_until_actions(
+            run_ctx.workflow,
+            run_ctx.store,
+            ctx_id,
+            start_op,
+            pred_op,
+            &mut done,
+        )
+        .await?;
+
+    if plans.len() > 1 {
+        return dispatch_group(run_ctx, ctx_id, plans, done).await;
+    }
+
+    match plans.pop().expect("kernel yields at least one plan") {
         KernelPlan::Dispatch {
             op_id,
             ctx_id,
             call,
         } => {
+            run_ctx.ctx_done.remove(&ctx_id);
+            if !done.is_empty() {
+                let _ = run_ctx.ctx_done.insert(ctx_id, done);
+            }
             dispatch_call(run_ctx, op_id, ctx_id, &call).await?;
         }
         KernelPlan::Return { ctx_id, return_var } => {
-            if let Some(var) = return_var {
-                let val = run_ctx.store.get_value(ctx_id, var).await?;
-                let _ = run_ctx.result_tx.send(val);
-            } else {
-                let _ = run_ctx.result_tx.send(Value::new(()));
-            }
-            finish_ctx(run_ctx, ctx_id);
+            send_result(run_ctx, ctx_id, return_var).await?;
+            finish_ctx(run_ctx, ctx_id).await?;
+        }
+        KernelPlan::Collect { op_id, ctx_id, var } => {
+            arrive_at_collect(run_ctx, op_id, ctx_id, var).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Continue `ctx_id` after `op_id`, or send its result if `op_id` returns.
+async fn resume_after<S: RunStore>(
+    run_ctx: &RunContext<'_, S>,
+    ctx_id: ContextId,
+    op_id: usize,
+    done: Vec<usize>,
+) -> anyhow::Result<()> {
+    let next = &run_ctx.workflow.operations[op_id].next;
+    match run_ctx
+        .kernel
+        .resolve_next(run_ctx.store, ctx_id, next)
+        .await?
+    {
+        Some(next_op) => drive_from(run_ctx, ctx_id, next_op, Some(op_id), done).await,
+        None => {
+            let return_var = match next {
+                namu_core::ir::Next::Return { var } => *var,
+                _ => None,
+            };
+            send_result(run_ctx, ctx_id, return_var).await?;
+            finish_ctx(run_ctx, ctx_id).await?;
+            Ok(())
+        }
+    }
+}
+
+/// Hand `var` of `ctx_id` to the collect at `op_id`.
+async fn arrive_at_collect<S: RunStore>(
+    run_ctx: &RunContext<'_, S>,
+    op_id: usize,
+    ctx_id: ContextId,
+    var: usize,
+) -> anyhow::Result<()> {
+    let from = run_ctx.workflow.operations[op_id]
+        .collect
+        .as_ref()
+        .expect("collect plan for op without collect")
+        .from;
+    let (item_ctx, fan_ctx) = fan_out_item(run_ctx, ctx_id, from).await?;
+    let value = run_ctx.store.get_value(ctx_id, var).await?;
+    run_ctx
+        .fan_ins
+        .entry(fan_ctx)
+        .or_default()
+        .get_mut()
+        .values
+        .push((item_ctx, value));
+    // Boxed: continuing after the collect drives again.
+    Box::pin(gather_if_complete(run_ctx, fan_ctx, op_id)).await?;
+    finish_ctx(run_ctx, ctx_id).await?;
+    Ok(())
+}
+
+/// The item of the fan-out by `from` that `ctx_id` belongs to, and the
+/// context that fan-out started in.
+async fn fan_out_item<S: RunStore>(
+    run_ctx: &RunContext<'_, S>,
+    ctx_id: ContextId,
+    from: usize,
+) -> anyhow::Result<(ContextId, ContextId)> {
+    let mut cursor = Some(ctx_id);
+    while let Some(ctx) = cursor {
+        if let Some((fan_ctx, op_id)) = run_ctx.fan_items.peek(&ctx, &Guard::new()).copied()
+            && op_id == from
+        {
+            return Ok((ctx, fan_ctx));
         }
+        cursor = run_ctx.store.parent(ctx).await?;
     }
+    Err(anyhow::anyhow!(
+        "context {ctx_id} reached a collect outside the fan-out of op {from}"
+    ))
+}
+
+/// Mark the stream that ran in `ctx_id` as ended if a collect waits for it.
+async fn end_fan_out<S: RunStore>(
+    run_ctx: &RunContext<'_, S>,
+    ctx_id: ContextId,
+) -> anyhow::Result<()> {
+    let Some(origin_op_id) = run_ctx.ctx_origin.peek(&ctx_id, &Guard::new()).copied() else {
+        return Ok(());
+    };
+    let Some(collect_op) = run_ctx.workflow.collect_of(origin_op_id) else {
+        return Ok(());
+    };
+    run_ctx.fan_ins.entry(ctx_id).or_default().get_mut().ended = true;
+    gather_if_complete(run_ctx, ctx_id, collect_op).await
+}
+
+/// Once every item fanned out in `fan_ctx` reached the collect at `op_id`,
+/// continue in a new child of `fan_ctx` with the values in context order.
+async fn gather_if_complete<S: RunStore>(
+    run_ctx: &RunContext<'_, S>,
+    fan_ctx: ContextId,
+    op_id: usize,
+) -> anyhow::Result<()> {
+    let complete = run_ctx
+        .fan_ins
+        .read(&fan_ctx, |_, fan_in| {
+            fan_in.ended && fan_in.values.len() == fan_in.items
+        })
+        .unwrap_or(false);
+    if !complete {
+        return Ok(());
+    }
+    let Some((_, fan_in)) = run_ctx.fan_ins.remove(&fan_ctx) else {
+        return Ok(());
+    };
+    let values = fan_in
+        .values
+        .into_iter()
+        .sorted_by_key(|(item_ctx, _)| *item_ctx)
+        .map(|(_, value)| value)
+        .collect();
+
+    let ctx_id = create_child(run_ctx, fan_ctx, ChildKey::Collect, None).await?;
+    let ctx_id = run_ctx
+        .kernel
+        .bind_collected(run_ctx.workflow, run_ctx.store, ctx_id, op_id, values)
+        .await?;
+    resume_after(run_ctx, ctx_id, op_id, Vec::new()).await
+}
+
+async fn dispatch_group<S: RunStore>(
+    run_ctx: &RunContext<'_, S>,
+    parent: ContextId,
+    plans: Vec<KernelPlan>,
+    mut done: Vec<usize>,
+) -> anyhow::Result<()> {
+    let mut ops = Vec::with_capacity(plans.len());
+    for (idx, plan) in plans.into_iter().enumerate() {
+        let KernelPlan::Dispatch { op_id, call, .. } = plan else {
+            unreachable!("return plans are never grouped");
+        };
+        let call_ctx = create_child(run_ctx, parent, ChildKey::Call(idx), None).await?;
+        let _ = run_ctx.call_groups.insert(call_ctx, (parent, idx));
+        ops.push((op_id, call_ctx, call));
+    }
+
+    let op_ids = ops.iter().map(|(op_id, _, _)| *op_id).collect::<Vec<_>>();
+    done.extend(&op_ids);
+    let _ = run_ctx.join_groups.insert(
+        parent,
+        JoinGroup {
+            parent,
+            done,
+            ops: op_ids,
+            ctxs: ops.iter().map(|(_, call_ctx, _)| *call_ctx).collect(),
+            failed: false,
+            outputs: vec![Vec::new(); ops.len()],
+            pending: ops.len(),
+        },
+    );
+    for (op_id, call_ctx, call) in &ops {
+        dispatch_call(run_ctx, *op_id, *call_ctx, call).await?;
+    }
+
+    // The parent lives on through the call contexts and their joins.
+    finish_ctx(run_ctx, parent).await?;
     Ok(())
 }
 
-async fn dispatch_call(
-    run_ctx: &RunContext<'_>,
+async fn dispatch_call<S: RunStore>(
+    run_ctx: &RunContext<'_, S>,
     op_id: usize,
     ctx_id: ContextId,
     call: &CallSpec,
 ) -> anyhow::Result<()> {
+    let _ = run_ctx.ctx_origin.insert(ctx_id, op_id);
+    run_ctx.observe(|observer| observer.on_dispatch(run_ctx.run_id, op_id, ctx_id, &call.task_id));
+    if run_ctx.recorder.is_some() {
+        let inputs = run_ctx.store.get_values(ctx_id, &call.inputs).await?;
+        let record = CallRecord {
+            op_id,
+            path: path_of(run_ctx, ctx_id),
+            input_hash: input_hash(&to_json(&inputs)?),
+            task_id: call.task_id.clone(),
+            outputs: Vec::new(),
+        };
+        let _ = run_ctx.records.upsert(ctx_id, record);
+    }
+    if let Some(outputs) = run_ctx.store.completed_call(op_id, ctx_id).await? {
+        // Completed before the run was restarted: replay what it sent.
+        let ends = std::iter::once(TaskOutput::Sent(Err(anyhow::Error::from(TaskEnd))));
+        for output in outputs.into_iter().map(TaskOutput::Recorded).chain(ends) {
+            let _ = run_ctx.event_tx.send(TaskEvent {
+                task_name: call.task_id.clone(),
+                ctx_id,
+                output,
+            });
+        }
+        return Ok(());
+    }
+
     let inputs = run_ctx.store.get_values(ctx_id, &call.inputs).await?;
 
     let pack_fn = run_ctx.pack_map.peek(&call.task_id, &Guard::new()).cloned();
@@ -354,86 +853,320 @@ async fn dispatch_call(
         .expect("sender not found")
         .clone();
 
-    let _ = run_ctx.ctx_origin.insert(ctx_id, op_id);
     sender
         .send((ctx_id, packed))
         .expect("failed to send to task");
     Ok(())
 }
 
-async fn handle_event(run_ctx: &RunContext<'_>, event: TaskEvent) -> anyhow::Result<()> {
-    match event.output {
-        Ok(res) => {
-            let origin_op_id = *run_ctx
-                .ctx_origin
-                .peek(&event.ctx_id, &Guard::new())
-                .expect("origin not found");
+async fn handle_event<S: RunStore>(
+    run_ctx: &RunContext<'_, S>,
+    event: TaskEvent,
+) -> anyhow::Result<()> {
+    let TaskEvent {
+        task_name,
+        ctx_id,
+        output,
+    } = event;
+    let origin_op_id = *run_ctx
+        .ctx_origin
+        .peek(&ctx_id, &Guard::new())
+        .expect("origin not found");
+
+    let output = match output {
+        TaskOutput::Recorded(output) => {
+            run_ctx.observe(|observer| {
+                observer.on_output(run_ctx.run_id, origin_op_id, ctx_id, &task_name)
+            });
+            record_output(run_ctx, ctx_id, || {
+                Ok(RecordedOutput::Values(to_json(&output.1)?))
+            })?;
+            Ok(output)
+        }
+        TaskOutput::Sent(Ok(res)) => {
+            run_ctx.observe(|observer| {
+                observer.on_output(run_ctx.run_id, origin_op_id, ctx_id, &task_name)
+            });
+            let output = (
+                next_output(run_ctx, ctx_id),
+                unpack_outputs(run_ctx, &task_name, res),
+            );
+            run_ctx
+                .store
+                .record_output(origin_op_id, ctx_id, &output)
+                .await?;
+            record_output(run_ctx, ctx_id, || {
+                Ok(RecordedOutput::Values(to_json(&output.1)?))
+            })?;
+            Ok(output)
+        }
+        TaskOutput::Sent(Err(err)) => {
+            if err.is::<TaskEnd>() {
+                run_ctx.store.complete_call(origin_op_id, ctx_id).await?;
+                if let (Some(recorder), Some((_, record))) =
+                    (run_ctx.recorder, run_ctx.records.remove(&ctx_id))
+                {
+                    recorder.record(run_ctx.run_id, record)?;
+                }
+            } else {
+                // Failed outputs keep their index, so replayed ones line up.
+                next_output(run_ctx, ctx_id);
+                let error = TaskError::from_anyhow(&err);
+                record_output(run_ctx, ctx_id, || {
+                    Ok(RecordedOutput::Error(error.message.clone()))
+                })?;
+                run_ctx.observe(|observer| {
+                    observer.on_error(run_ctx.run_id, origin_op_id, ctx_id, &task_name, &error)
+                });
+            }
+            Err(err)
+        }
+    };
+
+    let group_member = run_ctx.call_groups.peek(&ctx_id, &Guard::new()).copied();
+    if let Some((parent, idx)) = group_member {
+        return handle_group_event(run_ctx, parent, idx, ctx_id, output).await;
+    }
+
+    match output {
+        Ok((index, out_vals)) => {
             let operation = &run_ctx.workflow.operations[origin_op_id];
             let call = operation.call.as_ref().expect("origin should be call");
 
-            let unpack_fn = run_ctx
-                .unpack_map
-                .peek(&call.task_id, &Guard::new())
-                .cloned();
-
-            let out_vals = if let Some(unpack_fn) = unpack_fn {
-                (unpack_fn)(res)
-            } else {
-                vec![res]
+            let step = PathStep::Output {
+                op_id: origin_op_id,
+                index,
             };
+            let child_ctx =
+                create_child(run_ctx, ctx_id, ChildKey::Output(index), Some(step)).await?;
+            set_outputs(run_ctx, child_ctx, &call.outputs, out_vals).await?;
+            if run_ctx.workflow.collect_of(origin_op_id).is_some() {
+                let _ = run_ctx.fan_items.insert(child_ctx, (ctx_id, origin_op_id));
+                run_ctx.fan_ins.entry(ctx_id).or_default().get_mut().items += 1;
+            }
 
-            let child_ctx = run_ctx.store.create_child(event.ctx_id);
-            run_ctx.active_ctxs.fetch_add(1, Ordering::Release);
+            let done = run_ctx
+                .ctx_done
+                .peek(&ctx_id, &Guard::new())
+                .cloned()
+                .unwrap_or_default();
+            resume_after(run_ctx, child_ctx, origin_op_id, done).await?;
+        }
+        Err(err) => {
+            if !err.is::<TaskEnd>() {
+                // Observers saw the error; the end marker follows and
+                // finishes the context.
+                return Ok(());
+            }
+            end_fan_out(run_ctx, ctx_id).await?;
+            finish_ctx(run_ctx, ctx_id).await?;
+        }
+    }
+    Ok(())
+}
 
-            if out_vals.len() == 1 {
-                run_ctx
-                    .store
-                    .set_value(child_ctx, call.outputs[0], out_vals[0].clone())
-                    .await?;
-            } else {
-                for (out_id, val) in call.outputs.iter().copied().zip(out_vals.into_iter()) {
-                    run_ctx.store.set_value(child_ctx, out_id, val).await?;
+async fn handle_group_event<S: RunStore>(
+    run_ctx: &RunContext<'_, S>,
+    parent: ContextId,
+    idx: usize,
+    ctx_id: ContextId,
+    output: anyhow::Result<CallOutput>,
+) -> anyhow::Result<()> {
+    let err = match output {
+        Ok((_, out_vals)) => {
+            run_ctx.join_groups.update(&parent, |_, group| {
+                if !group.failed {
+                    group.outputs[idx].push(out_vals);
                 }
-            }
+            });
+            return Ok(());
+        }
+        Err(err) => err,
+    };
 
-            if let Some(next_op) = run_ctx
-                .kernel
-                .resolve_next(run_ctx.store, child_ctx, &operation.next)
-                .await?
-            {
-                drive_from(run_ctx, child_ctx, next_op, Some(origin_op_id)).await?;
-            } else {
-                // Return terminator.
-                let return_var = match &operation.next {
-                    namu_core::ir::Next::Return { var } => *var,
-                    _ => None,
-                };
-                if let Some(var) = return_var {
-                    let val = run_ctx.store.get_value(child_ctx, var).await?;
-                    let _ = run_ctx.result_tx.send(val);
-                } else {
-                    let _ = run_ctx.result_tx.send(Value::new(()));
+    if !err.is::<TaskEnd>() {
+        // A failed member fails the join: the others are cancelled, and the
+        // group is dropped once every end marker came in.
+        run_ctx.join_groups.update(&parent, |_, group| {
+            if !group.failed {
+                group.failed = true;
+                for other in group.ctxs.iter().filter(|other| **other != ctx_id) {
+                    run_ctx.cancel.cancel_call(*other);
                 }
-                finish_ctx(run_ctx, child_ctx);
             }
+        });
+        return Ok(());
+    }
+
+    let ready = run_ctx
+        .join_groups
+        .update(&parent, |_, group| {
+            group.pending -= 1;
+            group.pending == 0
+        })
+        .unwrap_or(false);
+    if ready
+        && let Some((_, group)) = run_ctx.join_groups.remove(&parent)
+        && !group.failed
+    {
+        join_group(run_ctx, group).await?;
+    }
+    finish_ctx(run_ctx, ctx_id).await?;
+    Ok(())
+}
+
+/// Index of the next output of the call dispatched in `ctx_id`.
+fn next_output<S: RunStore>(run_ctx: &RunContext<'_, S>, ctx_id: ContextId) -> usize {
+    let mut entry = run_ctx.output_counts.entry(ctx_id).or_default();
+    let count = entry.get_mut();
+    *count += 1;
+    *count - 1
+}
+
+/// Add an output to the record of the call dispatched in `ctx_id`, if the
+/// run is recorded.
+fn record_output<S: RunStore>(
+    run_ctx: &RunContext<'_, S>,
+    ctx_id: ContextId,
+    output: impl FnOnce() -> anyhow::Result<RecordedOutput>,
+) -> anyhow::Result<()> {
+    if run_ctx.recorder.is_none() {
+        return Ok(());
+    }
+    let output = output()?;
+    run_ctx
+        .records
+        .update(&ctx_id, |_, record| record.outputs.push(output));
+    Ok(())
+}
+
+fn path_of<S: RunStore>(run_ctx: &RunContext<'_, S>, ctx_id: ContextId) -> CtxPath {
+    run_ctx
+        .ctx_paths
+        .peek(&ctx_id, &Guard::new())
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Values as recorded: the JSON of [`literal::encode_value`].
+fn to_json(values: &[Value]) -> anyhow::Result<Vec<JsonValue>> {
+    values
+        .iter()
+        .map(|value| literal::encode_value(value).map(|(_, json)| json))
+        .collect()
+}
+
+/// Continue once per combination of the group's outputs, as if the calls had
+/// run one after another.
+async fn join_group<S: RunStore>(
+    run_ctx: &RunContext<'_, S>,
+    group: JoinGroup,
+) -> anyhow::Result<()> {
+    let combinations = group
+        .outputs
+        .iter()
+        .map(|outputs| outputs.iter())
+        .multi_cartesian_product()
+        .collect::<Vec<_>>();
+
+    for (n, combination) in combinations.into_iter().enumerate() {
+        let step = PathStep::Join {
+            op_id: group.ops[0],
+            index: n,
+        };
+        let join_ctx = create_child(run_ctx, group.parent, ChildKey::Join(n), Some(step)).await?;
+        for (op_id, out_vals) in group.ops.iter().zip(combination) {
+            let call = run_ctx.workflow.operations[*op_id]
+                .call
+                .as_ref()
+                .expect("group member should be call");
+            set_outputs(run_ctx, join_ctx, &call.outputs, out_vals.clone()).await?;
         }
-        Err(err) => {
-            if !err.is::<namu_core::TaskEnd>() {
-                eprintln!("[dispatcher::{}] error: {err}", event.task_name);
-            }
-            finish_ctx(run_ctx, event.ctx_id);
+        drive_from(run_ctx, join_ctx, group.ops[0], None, group.done.clone()).await?;
+    }
+    Ok(())
+}
+
+fn unpack_outputs<S: RunStore>(
+    run_ctx: &RunContext<'_, S>,
+    task_id: &str,
+    res: Value,
+) -> Vec<Value> {
+    let unpack_fn = run_ctx.unpack_map.peek(task_id, &Guard::new()).cloned();
+    if let Some(unpack_fn) = unpack_fn {
+        (unpack_fn)(res)
+    } else {
+        vec![res]
+    }
+}
+
+async fn set_outputs<S: RunStore>(
+    run_ctx: &RunContext<'_, S>,
+    ctx_id: ContextId,
+    outputs: &[usize],
+    out_vals: Vec<Value>,
+) -> anyhow::Result<()> {
+    if out_vals.len() == 1 {
+        run_ctx
+            .store
+            .set_value(ctx_id, outputs[0], out_vals[0].clone())
+            .await?;
+    } else {
+        for (out_id, val) in outputs.iter().copied().zip(out_vals) {
+            run_ctx.store.set_value(ctx_id, out_id, val).await?;
         }
     }
     Ok(())
 }
 
-fn finish_ctx(run_ctx: &RunContext<'_>, ctx_id: ContextId) {
+async fn send_result<S: RunStore>(
+    run_ctx: &RunContext<'_, S>,
+    ctx_id: ContextId,
+    return_var: Option<usize>,
+) -> anyhow::Result<()> {
+    if let Some(var) = return_var {
+        let val = run_ctx.store.get_value(ctx_id, var).await?;
+        let _ = run_ctx.result_tx.send(val);
+    } else {
+        let _ = run_ctx.result_tx.send(Value::new(()));
+    }
+    Ok(())
+}
+
+/// Create the child of `parent` for `key`; it counts as active until finished.
+/// Its path is the parent's, extended by `step` if given.
+async fn create_child<S: RunStore>(
+    run_ctx: &RunContext<'_, S>,
+    parent: ContextId,
+    key: ChildKey,
+    step: Option<PathStep>,
+) -> anyhow::Result<ContextId> {
+    let ctx_id = run_ctx.store.child(parent, key).await?;
+    if run_ctx.recorder.is_some() {
+        let path = match step {
+            Some(step) => path_of(run_ctx, parent).push(step),
+            None => path_of(run_ctx, parent),
+        };
+        let _ = run_ctx.ctx_paths.insert(ctx_id, path);
+    }
+    run_ctx.active_ctxs.fetch_add(1, Ordering::Release);
+    run_ctx.observe(|observer| observer.on_context_created(run_ctx.run_id, ctx_id, Some(parent)));
+    Ok(ctx_id)
+}
+
+async fn finish_ctx<S: RunStore>(
+    run_ctx: &RunContext<'_, S>,
+    ctx_id: ContextId,
+) -> anyhow::Result<()> {
     if run_ctx.finished_ctxs.get(&ctx_id).is_some() {
-        return;
+        return Ok(());
     }
     let _ = run_ctx.finished_ctxs.insert(ctx_id, ());
-    run_ctx.store.finish_context(ctx_id);
+    let _ = run_ctx.output_counts.remove(&ctx_id);
+    run_ctx.store.finish(ctx_id).await?;
+    run_ctx.observe(|observer| observer.on_context_finished(run_ctx.run_id, ctx_id));
     if run_ctx.active_ctxs.fetch_sub(1, Ordering::AcqRel) == 1 {
         let _ = run_ctx.finish_tx.send(());
     }
+    Ok(())
 }
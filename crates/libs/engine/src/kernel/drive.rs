@@ -1,5 +1,7 @@
+use std::collections::HashSet;
+
 use namu_core::ir::{Call, Next, Operation, Workflow};
-use namu_core::{ContextId, ValueId};
+use namu_core::{ContextId, OpId, ValueId};
 
 use super::plan::{CallSpec, KernelPlan};
 use crate::runtime::codec::ValueRuntime;
@@ -15,46 +17,178 @@ impl<R: ValueRuntime> EngineKernel<R> {
         Self { runtime }
     }
 
-    pub async fn drive_until_action<S: ValueStore<Value = R::Value>>(
+    /// Bind workflow parameters into `ctx_id`, in declaration order.
+    pub async fn seed_inputs<S: ValueStore<Value = R::Value>>(
+        &self,
+        workflow: &Workflow,
+        store: &S,
+        mut ctx_id: ContextId,
+        inputs: Vec<R::Value>,
+    ) -> anyhow::Result<ContextId> {
+        if inputs.len() != workflow.inputs.len() {
+            return Err(anyhow::anyhow!(
+                "workflow {} expects {} inputs, got {}",
+                workflow.name,
+                workflow.inputs.len(),
+                inputs.len()
+            ));
+        }
+        for (input, value) in workflow.inputs.iter().zip(inputs) {
+            self.runtime
+                .check_input(&input.ty, &value)
+                .map_err(|err| anyhow::anyhow!("invalid workflow input {}: {err}", input.name))?;
+            ctx_id = store.set_value(ctx_id, input.output, value).await?;
+        }
+        Ok(ctx_id)
+    }
+
+    /// Bind what the collect at `op_id` gathered, already in context order,
+    /// into `ctx_id`.
+    pub async fn bind_collected<S: ValueStore<Value = R::Value>>(
+        &self,
+        workflow: &Workflow,
+        store: &S,
+        ctx_id: ContextId,
+        op_id: OpId,
+        values: Vec<R::Value>,
+    ) -> anyhow::Result<ContextId> {
+        let collect = workflow
+            .operations
+            .get(op_id)
+            .and_then(|operation| operation.collect.as_ref())
+            .ok_or_else(|| anyhow::anyhow!("op {op_id} has no collect"))?;
+        let value = self.runtime.collect(&collect.ty, values)?;
+        store.set_value(ctx_id, collect.output, value).await
+    }
+
+    /// Drive `ctx_id` from `op_id` until it either returns or reaches calls.
+    ///
+    /// Yields a single `Return` or `Collect`, or one `Dispatch` per call that
+    /// is ready to run: the first call reached plus every later call on the
+    /// same straight-line segment that does not consume a pending output.
+    /// Calls that feed a collect are always dispatched alone. Once the
+    /// dispatched calls have completed, resume at the first dispatched op with
+    /// their ids in `done` (and `pred_op = None`, phis were already applied).
+    ///
+    /// `done` is cleared when the drive leaves the segment it refers to; keep
+    /// what is left for the next resume in the same context.
+    pub async fn drive_until_actions<S: ValueStore<Value = R::Value>>(
         &self,
         workflow: &Workflow,
         store: &S,
         mut ctx_id: ContextId,
-        mut op_id: usize,
-        mut pred_op: Option<usize>,
-    ) -> anyhow::Result<KernelPlan> {
+        mut op_id: OpId,
+        mut pred_op: Option<OpId>,
+        done: &mut Vec<OpId>,
+    ) -> anyhow::Result<Vec<KernelPlan>> {
         loop {
             let operation = workflow
                 .operations
                 .get(op_id)
                 .ok_or_else(|| anyhow::anyhow!("invalid op id {op_id}"))?;
 
+            // Entering a merge point leaves the segment `done` refers to.
+            if pred_op.is_some() && !operation.phis.is_empty() {
+                done.clear();
+            }
+
             ctx_id = self.apply_literals(store, ctx_id, operation).await?;
             ctx_id = self.apply_phis(store, ctx_id, operation, pred_op).await?;
 
-            if let Some(call) = &operation.call {
-                return Ok(KernelPlan::Dispatch {
+            if let Some(collect) = &operation.collect {
+                return Ok(vec![KernelPlan::Collect {
                     op_id,
                     ctx_id,
-                    call: call_spec(call),
-                });
+                    var: collect.input,
+                }]);
+            }
+
+            if let Some(call) = &operation.call
+                && !done.contains(&op_id)
+            {
+                return self
+                    .ready_calls(workflow, store, ctx_id, op_id, call, done)
+                    .await;
             }
 
             match self.resolve_next(store, ctx_id, &operation.next).await? {
                 Some(next) => {
+                    if matches!(operation.next, Next::Branch { .. }) {
+                        done.clear();
+                    }
                     pred_op = Some(op_id);
                     op_id = next;
                 }
                 None => {
-                    return Ok(KernelPlan::Return {
+                    return Ok(vec![KernelPlan::Return {
                         ctx_id,
                         return_var: return_var(&operation.next),
-                    });
+                    }]);
                 }
             }
         }
     }
 
+    /// Collect the calls on the segment starting at `first_op` that can run
+    /// alongside it.
+    async fn ready_calls<S: ValueStore<Value = R::Value>>(
+        &self,
+        workflow: &Workflow,
+        store: &S,
+        mut ctx_id: ContextId,
+        first_op: OpId,
+        first_call: &Call,
+        done: &[OpId],
+    ) -> anyhow::Result<Vec<KernelPlan>> {
+        let mut pending: HashSet<ValueId> = first_call.outputs.iter().copied().collect();
+        let mut visited = HashSet::from([first_op]);
+        let mut plans = vec![KernelPlan::Dispatch {
+            op_id: first_op,
+            ctx_id,
+            call: call_spec(first_call),
+        }];
+
+        // The items of a collected call must fan out from the context it
+        // was dispatched in, not from a join.
+        if workflow.collect_of(first_op).is_some() {
+            return Ok(plans);
+        }
+
+        let mut cursor = first_op;
+        while let Next::Jump { next } = workflow.operations[cursor].next {
+            let Some(operation) = workflow.operations.get(next) else {
+                break;
+            };
+            if !operation.phis.is_empty()
+                || operation.collect.is_some()
+                || workflow.collect_of(next).is_some()
+                || !visited.insert(next)
+            {
+                break;
+            }
+            cursor = next;
+
+            ctx_id = self.apply_literals(store, ctx_id, operation).await?;
+            let Some(call) = &operation.call else {
+                continue;
+            };
+            if done.contains(&cursor) {
+                continue;
+            }
+            let blocked = call.inputs.iter().any(|input| pending.contains(input));
+            pending.extend(call.outputs.iter().copied());
+            if !blocked {
+                plans.push(KernelPlan::Dispatch {
+                    op_id: cursor,
+                    ctx_id,
+                    call: call_spec(call),
+                });
+            }
+        }
+
+        Ok(plans)
+    }
+
     pub async fn resolve_next<S: ValueStore<Value = R::Value>>(
         &self,
         store: &S,
@@ -83,7 +217,7 @@ impl<R: ValueRuntime> EngineKernel<R> {
         op: &Operation,
     ) -> anyhow::Result<ContextId> {
         for lit in &op.literals {
-            let value = self.runtime.parse_literal(&lit.value)?;
+            let value = self.runtime.parse_literal(lit)?;
             ctx_id = store.set_value(ctx_id, lit.output, value).await?;
         }
         Ok(ctx_id)
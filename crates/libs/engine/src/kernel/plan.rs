@@ -18,6 +18,12 @@ pub enum KernelPlan {
         ctx_id: ContextId,
         return_var: Option<ValueId>,
     },
+    /// `ctx_id` reached the collect at `op_id` and hands in `var`.
+    Collect {
+        op_id: usize,
+        ctx_id: ContextId,
+        var: ValueId,
+    },
 }
 
 pub type KernelAction = KernelPlan;
@@ -1,10 +1,15 @@
 use namu_core::Value;
+use namu_core::ir::{Literal, Workflow};
 
 pub trait ValueRuntime: Send + Sync + Clone + 'static {
     type Value: Clone + Send + Sync + 'static;
 
-    fn parse_literal(&self, raw: &str) -> anyhow::Result<Self::Value>;
+    fn parse_literal(&self, literal: &Literal) -> anyhow::Result<Self::Value>;
+    /// Check a workflow input against the parameter's type tag.
+    fn check_input(&self, ty: &str, value: &Self::Value) -> anyhow::Result<()>;
     fn as_bool(&self, value: &Self::Value) -> anyhow::Result<bool>;
+    /// Gather the values a collect received, typed by the collect's tag.
+    fn collect(&self, ty: &str, values: Vec<Self::Value>) -> anyhow::Result<Self::Value>;
 }
 
 #[derive(Clone, Default)]
@@ -13,20 +18,15 @@ pub struct CoreValueRuntime;
 impl ValueRuntime for CoreValueRuntime {
     type Value = Value;
 
-    fn parse_literal(&self, raw: &str) -> anyhow::Result<Self::Value> {
-        let val = match raw {
-            "true" => Value::new(true),
-            "false" => Value::new(false),
-            "()" => Value::new(()),
-            _ => {
-                if let Ok(n) = raw.parse::<i32>() {
-                    Value::new(n)
-                } else {
-                    Value::new(raw.trim_matches('"').to_string())
-                }
-            }
-        };
-        Ok(val)
+    fn parse_literal(&self, literal: &Literal) -> anyhow::Result<Self::Value> {
+        namu_core::literal::decode(&literal.ty, &literal.value)
+    }
+
+    fn check_input(&self, ty: &str, value: &Self::Value) -> anyhow::Result<()> {
+        match namu_core::literal::tag_of(value) {
+            Some(tag) if tag != ty => Err(anyhow::anyhow!("expected {ty}, got {tag}")),
+            _ => Ok(()),
+        }
     }
 
     fn as_bool(&self, value: &Self::Value) -> anyhow::Result<bool> {
@@ -35,28 +35,54 @@ impl ValueRuntime for CoreValueRuntime {
             .copied()
             .ok_or_else(|| anyhow::anyhow!("branch value not bool"))
     }
+
+    fn collect(&self, ty: &str, values: Vec<Self::Value>) -> anyhow::Result<Self::Value> {
+        namu_core::literal::collect(ty, values)
+    }
 }
 
 #[derive(Clone, Default)]
 pub struct JsonRuntime;
 
+impl JsonRuntime {
+    /// Order named run inputs by the workflow's declared parameters, checking
+    /// each against its declared type.
+    pub fn bind_inputs(
+        workflow: &Workflow,
+        inputs: &serde_json::Map<String, serde_json::Value>,
+    ) -> anyhow::Result<Vec<serde_json::Value>> {
+        if let Some(name) = inputs
+            .keys()
+            .find(|name| !workflow.inputs.iter().any(|input| &input.name == *name))
+        {
+            return Err(anyhow::anyhow!("unknown workflow input {name}"));
+        }
+        workflow
+            .inputs
+            .iter()
+            .map(|input| {
+                let value = inputs
+                    .get(&input.name)
+                    .ok_or_else(|| anyhow::anyhow!("missing workflow input {}", input.name))?;
+                JsonRuntime.check_input(&input.ty, value).map_err(|err| {
+                    anyhow::anyhow!("invalid workflow input {}: {err}", input.name)
+                })?;
+                Ok(value.clone())
+            })
+            .collect()
+    }
+}
+
 impl ValueRuntime for JsonRuntime {
     type Value = serde_json::Value;
 
-    fn parse_literal(&self, raw: &str) -> anyhow::Result<Self::Value> {
-        let val = match raw {
-            "true" => serde_json::Value::Bool(true),
-            "false" => serde_json::Value::Bool(false),
-            "()" => serde_json::Value::Null,
-            _ => {
-                if let Ok(n) = raw.parse::<i32>() {
-                    serde_json::Value::Number(n.into())
-                } else {
-                    serde_json::Value::String(raw.trim_matches('"').to_string())
-                }
-            }
-        };
-        Ok(val)
+    fn parse_literal(&self, literal: &Literal) -> anyhow::Result<Self::Value> {
+        // Task payloads are JSON already; the tag only matters to typed runtimes.
+        Ok(literal.value.clone())
+    }
+
+    fn check_input(&self, ty: &str, value: &Self::Value) -> anyhow::Result<()> {
+        namu_core::literal::check(ty, value)
     }
 
     fn as_bool(&self, value: &Self::Value) -> anyhow::Result<bool> {
@@ -64,4 +90,8 @@ impl ValueRuntime for JsonRuntime {
             .as_bool()
             .ok_or_else(|| anyhow::anyhow!("branch value not bool"))
     }
+
+    fn collect(&self, _ty: &str, values: Vec<Self::Value>) -> anyhow::Result<Self::Value> {
+        Ok(serde_json::Value::Array(values))
+    }
 }
@@ -1,3 +1,5 @@
 pub mod codec;
 pub mod graph;
+pub mod replay;
+pub mod sqlite;
 pub mod store;
@@ -0,0 +1,247 @@
+//! Recordings of the task outputs of a run, so its control flow can be
+//! replayed without running any task, see [`crate::adapters::replay`].
+//!
+//! A recording is a JSON-lines file: a [`RecordEntry::Run`] header with the
+//! workflow and its inputs, then one [`RecordEntry::Call`] per call that
+//! ended. Calls are keyed by their op, the [`CtxPath`] of the context they
+//! ran in and a hash of their inputs. None of these depend on how an engine
+//! numbers its contexts, so the SimpleEngine and the master record a run the
+//! same way.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use namu_core::ir::Workflow;
+use namu_core::{OpId, ValueId};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sha2::Digest;
+
+/// Where a context sits in its run, from the root.
+///
+/// The first output of a call, and the first combination of a join, carry
+/// on the path of the context they came from; a chain or loop of calls keeps
+/// its path. Every further one adds a step, so contexts alive at the same
+/// time have distinct paths. Calls repeated on one path, like the iterations
+/// of a loop, are told apart by their inputs and else by their order.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct CtxPath(String);
+
+/// How a context continues from the one it was created from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathStep {
+    /// Output `index` of the call of `op_id`, failed outputs included.
+    Output { op_id: OpId, index: usize },
+    /// Combination `index` of the joined outputs of the calls dispatched
+    /// together, the first of them being `op_id`.
+    Join { op_id: OpId, index: usize },
+}
+
+impl CtxPath {
+    pub fn root() -> Self {
+        Self::default()
+    }
+
+    /// The path of a context created from this one by `step`.
+    pub fn push(&self, step: PathStep) -> Self {
+        let step = match step {
+            PathStep::Output { index: 0, .. } | PathStep::Join { index: 0, .. } => {
+                return self.clone();
+            }
+            PathStep::Output { op_id, index } => format!("{op_id}.{index}"),
+            PathStep::Join { op_id, index } => format!("j{op_id}.{index}"),
+        };
+        if self.0.is_empty() {
+            CtxPath(step)
+        } else {
+            CtxPath(format!("{}/{step}", self.0))
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for CtxPath {
+    fn from(path: String) -> Self {
+        CtxPath(path)
+    }
+}
+
+impl fmt::Display for CtxPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "/{}", self.0)
+    }
+}
+
+/// Hash of the inputs of a call, as JSON.
+pub fn input_hash(inputs: &[JsonValue]) -> String {
+    let json = serde_json::to_string(inputs).expect("JSON values serialize");
+    format!("sha256:{:x}", sha2::Sha256::digest(json.as_bytes()))
+}
+
+/// One output of a recorded call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordedOutput {
+    /// A value per output of the call.
+    Values(Vec<JsonValue>),
+    Error(String),
+}
+
+impl RecordedOutput {
+    /// Split an output as a task sends it, one value or an array of one
+    /// per output, into the values of `outputs`.
+    pub fn from_json(outputs: &[ValueId], output: &JsonValue) -> anyhow::Result<Self> {
+        let values = match outputs.len() {
+            0 => Vec::new(),
+            1 => vec![output.clone()],
+            n => {
+                let values = output
+                    .as_array()
+                    .ok_or_else(|| anyhow::anyhow!("output must be array for multiple outputs"))?;
+                if values.len() != n {
+                    return Err(anyhow::anyhow!("output arity mismatch"));
+                }
+                values.clone()
+            }
+        };
+        Ok(RecordedOutput::Values(values))
+    }
+}
+
+/// Everything a call sent, in order, until it ended.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CallRecord {
+    pub op_id: OpId,
+    pub path: CtxPath,
+    /// See [`input_hash`].
+    pub input_hash: String,
+    pub task_id: String,
+    pub outputs: Vec<RecordedOutput>,
+}
+
+/// A line of a recording.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "entry", rename_all = "snake_case")]
+pub enum RecordEntry {
+    Run {
+        workflow: Workflow,
+        inputs: Vec<JsonValue>,
+    },
+    Call(CallRecord),
+}
+
+/// Writes the recording of each run to `<dir>/<run_id>.jsonl`.
+pub struct RecordDir {
+    dir: PathBuf,
+    /// Keeps the lines of concurrent calls whole.
+    lock: Mutex<()>,
+}
+
+impl RecordDir {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// The recording of `run_id`.
+    pub fn path(&self, run_id: impl fmt::Display) -> PathBuf {
+        self.dir.join(format!("{run_id}.jsonl"))
+    }
+
+    /// Start the recording of `run_id` over, with its header.
+    pub fn start(
+        &self,
+        run_id: impl fmt::Display,
+        workflow: &Workflow,
+        inputs: Vec<JsonValue>,
+    ) -> anyhow::Result<()> {
+        let _guard = self.lock.lock().expect("record lock poisoned");
+        std::fs::create_dir_all(&self.dir)?;
+        let mut file = File::create(self.path(run_id))?;
+        let entry = RecordEntry::Run {
+            workflow: workflow.clone(),
+            inputs,
+        };
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+        Ok(())
+    }
+
+    /// Append a call that ended to the recording of `run_id`.
+    pub fn record(&self, run_id: impl fmt::Display, record: CallRecord) -> anyhow::Result<()> {
+        let line = serde_json::to_string(&RecordEntry::Call(record))?;
+        let _guard = self.lock.lock().expect("record lock poisoned");
+        let mut file = OpenOptions::new().append(true).open(self.path(run_id))?;
+        writeln!(file, "{line}")?;
+        Ok(())
+    }
+}
+
+/// A recording read back, see [`crate::adapters::replay::replay`].
+#[derive(Debug, Clone)]
+pub struct Recording {
+    /// The workflow replayed; swap it for a changed one to see where their
+    /// control flow parts.
+    pub workflow: Workflow,
+    pub inputs: Vec<JsonValue>,
+    pub calls: Vec<CallRecord>,
+}
+
+impl Recording {
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let mut lines = BufReader::new(File::open(path)?).lines();
+        let header = lines
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("recording {} is empty", path.display()))??;
+        let RecordEntry::Run { workflow, inputs } = serde_json::from_str(&header)? else {
+            return Err(anyhow::anyhow!(
+                "recording {} does not start with its run",
+                path.display()
+            ));
+        };
+
+        let mut calls = Vec::new();
+        for line in lines {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line)? {
+                RecordEntry::Call(record) => calls.push(record),
+                RecordEntry::Run { .. } => {
+                    return Err(anyhow::anyhow!(
+                        "recording {} holds more than one run",
+                        path.display()
+                    ));
+                }
+            }
+        }
+        Ok(Self {
+            workflow,
+            inputs,
+            calls,
+        })
+    }
+
+    /// The recorded calls by key, each key's in the order they ended.
+    pub(crate) fn by_key(&self) -> HashMap<(OpId, CtxPath, String), VecDeque<(usize, CallRecord)>> {
+        let mut calls: HashMap<_, VecDeque<_>> = HashMap::new();
+        for (idx, record) in self.calls.iter().enumerate() {
+            calls
+                .entry((record.op_id, record.path.clone(), record.input_hash.clone()))
+                .or_default()
+                .push_back((idx, record.clone()));
+        }
+        calls
+    }
+}
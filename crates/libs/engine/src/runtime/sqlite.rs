@@ -0,0 +1,345 @@
+//! A [`RunStore`] on an embedded SQLite file, so runs survive a restart.
+//!
+//! Values are kept as the `(type tag, JSON)` pairs of
+//! [`namu_core::literal::encode_value`], so every value a run holds must be a
+//! built-in type or one registered with `#[type]`.
+
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+use async_trait::async_trait;
+use namu_core::ir::Workflow;
+use namu_core::{ContextId, OpId, Value, ValueId, literal};
+use rusqlite::{Connection, OptionalExtension, Transaction, params};
+use serde_json::Value as JsonValue;
+
+use crate::runtime::graph::ContextGraph;
+use crate::runtime::store::{CallOutput, ChildKey, RunStore, ValueStore};
+
+const SCHEMA: &str = "
+PRAGMA journal_mode = WAL;
+PRAGMA synchronous = NORMAL;
+CREATE TABLE IF NOT EXISTS runs (
+    run_id INTEGER PRIMARY KEY,
+    key TEXT NOT NULL,
+    root INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS contexts (
+    ctx_id INTEGER PRIMARY KEY,
+    run_id INTEGER NOT NULL,
+    parent INTEGER,
+    child_key TEXT,
+    UNIQUE (parent, child_key)
+);
+CREATE INDEX IF NOT EXISTS contexts_run ON contexts (run_id);
+CREATE TABLE IF NOT EXISTS context_values (
+    ctx_id INTEGER NOT NULL,
+    val_id INTEGER NOT NULL,
+    ty TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (ctx_id, val_id)
+);
+CREATE TABLE IF NOT EXISTS call_outputs (
+    op_id INTEGER NOT NULL,
+    ctx_id INTEGER NOT NULL,
+    idx INTEGER NOT NULL,
+    outputs TEXT NOT NULL,
+    PRIMARY KEY (op_id, ctx_id, idx)
+);
+CREATE TABLE IF NOT EXISTS completed_calls (
+    op_id INTEGER NOT NULL,
+    ctx_id INTEGER NOT NULL,
+    PRIMARY KEY (op_id, ctx_id)
+);
+";
+
+/// Walk from a context up to the root, nearest first.
+const GET_VALUE: &str = "
+WITH RECURSIVE chain(ctx_id, depth) AS (
+    SELECT ?1, 0
+    UNION ALL
+    SELECT contexts.parent, chain.depth + 1
+    FROM contexts JOIN chain ON contexts.ctx_id = chain.ctx_id
+    WHERE contexts.parent IS NOT NULL
+)
+SELECT context_values.ty, context_values.value
+FROM chain JOIN context_values ON context_values.ctx_id = chain.ctx_id
+WHERE context_values.val_id = ?2
+ORDER BY chain.depth
+LIMIT 1
+";
+
+/// Keeps the contexts, values and completed calls of each run in a SQLite
+/// file until the run finishes or is cancelled.
+///
+/// Starting a run whose id, workflow and inputs match a stored one resumes
+/// it: its contexts are found again by [`ChildKey`] and its completed calls
+/// are replayed.
+pub struct SqliteStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    /// Open the store at `path`, creating the file if needed.
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock().expect("sqlite connection poisoned")
+    }
+}
+
+/// What a run computes; a stored run only resumes if this is unchanged.
+fn run_key(workflow: &Workflow, inputs: &[Value]) -> anyhow::Result<String> {
+    let inputs = inputs
+        .iter()
+        .map(literal::encode_value)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(serde_json::to_string(&(workflow, inputs))?)
+}
+
+fn child_key(key: ChildKey) -> String {
+    match key {
+        ChildKey::Output(n) => format!("output:{n}"),
+        ChildKey::Call(n) => format!("call:{n}"),
+        ChildKey::Join(n) => format!("join:{n}"),
+        ChildKey::Collect => "collect".to_string(),
+    }
+}
+
+fn encode_values(values: &[Value]) -> anyhow::Result<String> {
+    let values = values
+        .iter()
+        .map(literal::encode_value)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(serde_json::to_string(&values)?)
+}
+
+fn decode_values(json: &str) -> anyhow::Result<Vec<Value>> {
+    serde_json::from_str::<Vec<(String, JsonValue)>>(json)?
+        .iter()
+        .map(|(ty, value)| literal::decode(ty, value))
+        .collect()
+}
+
+fn delete_run(tx: &Transaction<'_>, run_id: usize) -> anyhow::Result<()> {
+    let contexts = "SELECT ctx_id FROM contexts WHERE run_id = ?1";
+    for table in ["context_values", "call_outputs", "completed_calls"] {
+        tx.execute(
+            &format!("DELETE FROM {table} WHERE ctx_id IN ({contexts})"),
+            [run_id],
+        )?;
+    }
+    tx.execute("DELETE FROM contexts WHERE run_id = ?1", [run_id])?;
+    tx.execute("DELETE FROM runs WHERE run_id = ?1", [run_id])?;
+    Ok(())
+}
+
+#[async_trait]
+impl ContextGraph for SqliteStore {
+    async fn parent(&self, ctx_id: ContextId) -> anyhow::Result<Option<ContextId>> {
+        self.conn()
+            .query_row(
+                "SELECT parent FROM contexts WHERE ctx_id = ?1",
+                [ctx_id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| anyhow::anyhow!("missing context {ctx_id}"))
+    }
+}
+
+#[async_trait]
+impl ValueStore for SqliteStore {
+    type Value = Value;
+
+    async fn get_value(&self, ctx_id: ContextId, val_id: ValueId) -> anyhow::Result<Self::Value> {
+        let (ty, value): (String, String) = self
+            .conn()
+            .query_row(GET_VALUE, [ctx_id, val_id], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .optional()?
+            .ok_or_else(|| anyhow::anyhow!("missing value {val_id} in ctx {ctx_id}"))?;
+        literal::decode(&ty, &serde_json::from_str(&value)?)
+    }
+
+    async fn get_values(
+        &self,
+        ctx_id: ContextId,
+        val_ids: &[ValueId],
+    ) -> anyhow::Result<Vec<Self::Value>> {
+        let mut out = Vec::with_capacity(val_ids.len());
+        for &val_id in val_ids {
+            out.push(self.get_value(ctx_id, val_id).await?);
+        }
+        Ok(out)
+    }
+
+    async fn set_value(
+        &self,
+        ctx_id: ContextId,
+        val_id: ValueId,
+        value: Self::Value,
+    ) -> anyhow::Result<ContextId> {
+        let (ty, value) = literal::encode_value(&value)
+            .map_err(|err| anyhow::anyhow!("cannot store value {val_id}: {err}"))?;
+        self.conn().execute(
+            "INSERT OR REPLACE INTO context_values (ctx_id, val_id, ty, value)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![ctx_id, val_id, ty, value.to_string()],
+        )?;
+        Ok(ctx_id)
+    }
+}
+
+#[async_trait]
+impl RunStore for SqliteStore {
+    async fn open_run(
+        &self,
+        run_id: usize,
+        workflow: &Workflow,
+        inputs: &[Value],
+    ) -> anyhow::Result<ContextId> {
+        let key = run_key(workflow, inputs)?;
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let stored: Option<(String, ContextId)> = tx
+            .query_row(
+                "SELECT key, root FROM runs WHERE run_id = ?1",
+                [run_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        let root = match stored {
+            Some((stored_key, root)) if stored_key == key => root,
+            stored => {
+                if stored.is_some() {
+                    delete_run(&tx, run_id)?;
+                }
+                let root = tx.query_row(
+                    "INSERT INTO contexts (run_id) VALUES (?1) RETURNING ctx_id",
+                    [run_id],
+                    |row| row.get(0),
+                )?;
+                tx.execute(
+                    "INSERT INTO runs (run_id, key, root) VALUES (?1, ?2, ?3)",
+                    params![run_id, key, root],
+                )?;
+                root
+            }
+        };
+        tx.commit()?;
+        Ok(root)
+    }
+
+    async fn child(&self, parent: ContextId, key: ChildKey) -> anyhow::Result<ContextId> {
+        let key = child_key(key);
+        let conn = self.conn();
+        let existing = conn
+            .query_row(
+                "SELECT ctx_id FROM contexts WHERE parent = ?1 AND child_key = ?2",
+                params![parent, key],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if let Some(ctx_id) = existing {
+            return Ok(ctx_id);
+        }
+        conn.query_row(
+            "INSERT INTO contexts (run_id, parent, child_key)
+             SELECT run_id, ?1, ?2 FROM contexts WHERE ctx_id = ?1
+             RETURNING ctx_id",
+            params![parent, key],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| anyhow::anyhow!("missing context {parent}"))
+    }
+
+    async fn finish(&self, _ctx_id: ContextId) -> anyhow::Result<()> {
+        // Kept until the run is closed: a resumed run drives through it again.
+        Ok(())
+    }
+
+    async fn record_output(
+        &self,
+        op_id: OpId,
+        ctx_id: ContextId,
+        output: &CallOutput,
+    ) -> anyhow::Result<()> {
+        let (idx, values) = output;
+        self.conn().execute(
+            "INSERT OR REPLACE INTO call_outputs (op_id, ctx_id, idx, outputs)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![op_id, ctx_id, idx, encode_values(values)?],
+        )?;
+        Ok(())
+    }
+
+    async fn complete_call(&self, op_id: OpId, ctx_id: ContextId) -> anyhow::Result<()> {
+        self.conn().execute(
+            "INSERT OR IGNORE INTO completed_calls (op_id, ctx_id) VALUES (?1, ?2)",
+            [op_id, ctx_id],
+        )?;
+        Ok(())
+    }
+
+    async fn completed_call(
+        &self,
+        op_id: OpId,
+        ctx_id: ContextId,
+    ) -> anyhow::Result<Option<Vec<CallOutput>>> {
+        let conn = self.conn();
+        let completed = conn
+            .query_row(
+                "SELECT 1 FROM completed_calls WHERE op_id = ?1 AND ctx_id = ?2",
+                [op_id, ctx_id],
+                |_| Ok(()),
+            )
+            .optional()?
+            .is_some();
+        if !completed {
+            // The call runs again from its first output.
+            conn.execute(
+                "DELETE FROM call_outputs WHERE op_id = ?1 AND ctx_id = ?2",
+                [op_id, ctx_id],
+            )?;
+            return Ok(None);
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT idx, outputs FROM call_outputs WHERE op_id = ?1 AND ctx_id = ?2 ORDER BY idx",
+        )?;
+        let rows = stmt
+            .query_map([op_id, ctx_id], |row| {
+                Ok((row.get::<_, usize>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        let outputs = rows
+            .into_iter()
+            .map(|(idx, values)| Ok((idx, decode_values(&values)?)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Some(outputs))
+    }
+
+    async fn close_run(&self, root: ContextId) -> anyhow::Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let run_id = tx
+            .query_row("SELECT run_id FROM runs WHERE root = ?1", [root], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        if let Some(run_id) = run_id {
+            delete_run(&tx, run_id)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+}
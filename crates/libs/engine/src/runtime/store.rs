@@ -3,7 +3,8 @@ use std::sync::RwLock;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
 use async_trait::async_trait;
-use namu_core::{ContextId, ValueId};
+use namu_core::ir::Workflow;
+use namu_core::{ContextId, OpId, Value, ValueId};
 
 use crate::runtime::graph::ContextGraph;
 
@@ -25,9 +26,76 @@ pub trait ValueStore: Send + Sync {
     ) -> anyhow::Result<ContextId>;
 }
 
+/// Why a child context was created; unique among the children of a parent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChildKey {
+    /// Output `n` of the call dispatched in the parent.
+    Output(usize),
+    /// Call `n` of the calls dispatched together from the parent.
+    Call(usize),
+    /// Combination `n` of the joined outputs of those calls.
+    Join(usize),
+    /// Where the items collected from the parent's stream continue.
+    Collect,
+}
+
+/// An output of a call: its index among the call's outputs, failed ones
+/// included, and its values unpacked.
+pub type CallOutput = (usize, Vec<Value>);
+
+/// Contexts, values and completed calls of the runs of a
+/// [`crate::adapters::simple::SimpleEngine`].
+///
+/// A store that outlives the process hands a restarted run its old contexts
+/// (children are looked up by [`ChildKey`]) and the outputs of the calls it
+/// completed, so the engine replays those instead of calling the tasks again.
+#[async_trait]
+pub trait RunStore: ValueStore<Value = Value> + ContextGraph {
+    /// Root context of run `run_id`. A stored run of another workflow or
+    /// other inputs is dropped first.
+    async fn open_run(
+        &self,
+        run_id: usize,
+        workflow: &Workflow,
+        inputs: &[Value],
+    ) -> anyhow::Result<ContextId>;
+
+    /// The child of `parent` for `key`, created unless it exists.
+    async fn child(&self, parent: ContextId, key: ChildKey) -> anyhow::Result<ContextId>;
+
+    /// Mark `ctx_id` finished; its values stay readable from its children.
+    async fn finish(&self, ctx_id: ContextId) -> anyhow::Result<()>;
+
+    /// Record an output of the call of `op_id` dispatched in `ctx_id`.
+    async fn record_output(
+        &self,
+        op_id: OpId,
+        ctx_id: ContextId,
+        output: &CallOutput,
+    ) -> anyhow::Result<()>;
+
+    /// Record that the call of `op_id` in `ctx_id` sent all its outputs.
+    async fn complete_call(&self, op_id: OpId, ctx_id: ContextId) -> anyhow::Result<()>;
+
+    /// Outputs of the call of `op_id` in `ctx_id` if it completed.
+    async fn completed_call(
+        &self,
+        op_id: OpId,
+        ctx_id: ContextId,
+    ) -> anyhow::Result<Option<Vec<CallOutput>>>;
+
+    /// Drop everything run `root` left, once it finished or was cancelled.
+    async fn close_run(&self, root: ContextId) -> anyhow::Result<()>;
+}
+
 #[derive(Debug, Clone)]
 struct ContextNode<V> {
     parent: Option<ContextId>,
+    /// The root of the tree this context belongs to, i.e. its run.
+    root: ContextId,
+    /// Live child contexts; a finished context is dropped once this is zero.
+    children: usize,
+    finished: bool,
     values: HashMap<ValueId, V>,
 }
 
@@ -62,18 +130,66 @@ where
     fn create_child_inner(&self, parent: Option<ContextId>) -> ContextId {
         let id = self.next_id.fetch_add(1, Ordering::Relaxed);
         let mut nodes = self.nodes.write().expect("store lock poisoned");
+        let root = match parent.and_then(|parent| nodes.get_mut(&parent)) {
+            Some(parent) => {
+                parent.children += 1;
+                parent.root
+            }
+            None => id,
+        };
         nodes.insert(
             id,
             ContextNode {
                 parent,
+                root,
+                children: 0,
+                finished: false,
                 values: HashMap::new(),
             },
         );
         id
     }
 
-    pub fn finish_context(&self, _ctx_id: ContextId) {
-        // No-op for now; child contexts may still depend on parent values.
+    /// Mark `ctx_id` finished. Its values stay readable while a descendant
+    /// is alive; once none is, it is dropped, and so is every finished
+    /// ancestor left without children.
+    pub fn finish_context(&self, ctx_id: ContextId) {
+        let mut nodes = self.nodes.write().expect("store lock poisoned");
+        let Some(node) = nodes.get_mut(&ctx_id) else {
+            return;
+        };
+        node.finished = true;
+
+        let mut cursor = Some(ctx_id);
+        while let Some(id) = cursor {
+            let Some(node) = nodes.get(&id) else {
+                break;
+            };
+            if !node.finished || node.children > 0 {
+                break;
+            }
+            cursor = node.parent;
+            nodes.remove(&id);
+            if let Some(parent) = cursor.and_then(|parent| nodes.get_mut(&parent)) {
+                parent.children -= 1;
+            }
+        }
+    }
+
+    /// Drop every context of the tree rooted at `root`, finished or not,
+    /// e.g. once its run was cancelled.
+    pub fn remove_tree(&self, root: ContextId) {
+        let mut nodes = self.nodes.write().expect("store lock poisoned");
+        nodes.retain(|_, node| node.root != root);
+    }
+
+    /// Number of contexts currently held.
+    pub fn len(&self) -> usize {
+        self.nodes.read().expect("store lock poisoned").len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 }
 
@@ -139,3 +255,85 @@ where
         Ok(ctx_id)
     }
 }
+
+/// Keeps nothing across restarts: keys are not needed to find a child again
+/// and no call is ever replayed.
+#[async_trait]
+impl RunStore for InMemoryStore<Value> {
+    async fn open_run(
+        &self,
+        _run_id: usize,
+        _workflow: &Workflow,
+        _inputs: &[Value],
+    ) -> anyhow::Result<ContextId> {
+        Ok(self.create_root())
+    }
+
+    async fn child(&self, parent: ContextId, _key: ChildKey) -> anyhow::Result<ContextId> {
+        Ok(self.create_child(parent))
+    }
+
+    async fn finish(&self, ctx_id: ContextId) -> anyhow::Result<()> {
+        self.finish_context(ctx_id);
+        Ok(())
+    }
+
+    async fn record_output(
+        &self,
+        _op_id: OpId,
+        _ctx_id: ContextId,
+        _output: &CallOutput,
+    ) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn complete_call(&self, _op_id: OpId, _ctx_id: ContextId) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn completed_call(
+        &self,
+        _op_id: OpId,
+        _ctx_id: ContextId,
+    ) -> anyhow::Result<Option<Vec<CallOutput>>> {
+        Ok(None)
+    }
+
+    async fn close_run(&self, root: ContextId) -> anyhow::Result<()> {
+        self.remove_tree(root);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn finished_context_waits_for_children() {
+        let store = InMemoryStore::<i32>::new();
+        let root = store.create_root();
+        store.set_value(root, 0, 1).await.unwrap();
+        let child = store.create_child(root);
+
+        store.finish_context(root);
+        assert_eq!(store.get_value(child, 0).await.unwrap(), 1);
+
+        store.finish_context(child);
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn remove_tree_drops_unfinished_contexts() {
+        let store = InMemoryStore::<i32>::new();
+        let root = store.create_root();
+        store.create_child(root);
+        let other = store.create_root();
+
+        store.remove_tree(root);
+        assert_eq!(store.len(), 1);
+
+        store.finish_context(other);
+        assert!(store.is_empty());
+    }
+}
@@ -1 +1,2 @@
+pub use crate::runtime::sqlite::SqliteStore;
 pub use crate::runtime::store::*;
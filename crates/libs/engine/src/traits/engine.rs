@@ -1,6 +1,7 @@
 use async_trait::async_trait;
+use namu_core::Value;
 use namu_core::ir::Workflow;
-use namu_core::registry::{PackFn, TaskImpl, UnpackFn};
+use namu_core::registry::{AsyncTaskImpl, PackFn, TaskImpl, UnpackFn};
 
 use crate::kernel::KernelPlan;
 
@@ -10,8 +11,13 @@ pub trait Engine: Send + Sync {
     type RunId: Send + Sync + Copy + 'static;
 
     async fn create_workflow(&self, workflow: Workflow) -> Self::WorkflowId;
-    async fn create_run(&self, workflow_id: Self::WorkflowId) -> Self::RunId;
+    /// Create a run of `workflow_id`; `inputs` follow the workflow's declared parameter order.
+    async fn create_run(&self, workflow_id: Self::WorkflowId, inputs: Vec<Value>) -> Self::RunId;
     async fn run(&self, run_id: Self::RunId) -> anyhow::Result<()>;
+    /// Stop a run that has not finished; its `run` returns without waiting
+    /// for outstanding calls, and tasks see the cancellation through
+    /// [`TaskContext::is_cancelled`](namu_core::TaskContext::is_cancelled).
+    async fn cancel(&self, run_id: Self::RunId) -> anyhow::Result<()>;
 }
 
 #[async_trait]
@@ -23,6 +29,16 @@ pub trait TaskRegistry: Send + Sync {
         pack: Option<PackFn>,
         unpack: Option<UnpackFn>,
     );
+
+    /// Like [`TaskRegistry::add_task`], for a task driven on the engine's
+    /// async runtime rather than a thread of its own.
+    async fn add_async_task(
+        &self,
+        task_name: &str,
+        task: AsyncTaskImpl,
+        pack: Option<PackFn>,
+        unpack: Option<UnpackFn>,
+    );
 }
 
 #[async_trait]
@@ -35,9 +51,17 @@ pub trait OrchestratorEngine: Send + Sync {
         ctx_id: namu_core::ContextId,
         start_op: usize,
         pred_op: Option<usize>,
-    ) -> anyhow::Result<KernelPlan>;
+        done: &mut Vec<usize>,
+    ) -> anyhow::Result<Vec<KernelPlan>>;
 
-    async fn dispatch(&self, run_id: uuid::Uuid, action: KernelPlan) -> anyhow::Result<()>;
+    /// Dispatch the plans `drive` produced for `ctx_id`; several dispatches are joined.
+    async fn dispatch(
+        &self,
+        run_id: uuid::Uuid,
+        ctx_id: namu_core::ContextId,
+        actions: Vec<KernelPlan>,
+        done: &[usize],
+    ) -> anyhow::Result<()>;
 
     async fn apply_task_output(
         &self,
@@ -57,5 +81,5 @@ pub trait WorkerEngine: Send + Sync {
         manifest: &namu_proto::TaskManifest,
         artifact_path: &std::path::Path,
         input: &Self::Value,
-    ) -> anyhow::Result<Result<Self::Value, String>>;
+    ) -> anyhow::Result<Result<Self::Value, namu_core::TaskError>>;
 }
@@ -1,2 +1,3 @@
 pub mod engine;
+pub mod observer;
 pub mod scheduler;
@@ -0,0 +1,43 @@
+use namu_core::{ContextId, OpId, TaskError};
+
+/// How a run ended, see [`RunObserver::on_run_finished`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunOutcome {
+    /// Every context finished; some calls may have failed on the way.
+    Finished,
+    Cancelled,
+}
+
+/// Hooks into the progress of runs, for logging, metrics, progress bars or
+/// test assertions. Every callback does nothing by default.
+///
+/// `R` is the run id of the engine observed: `usize` for the
+/// [`crate::adapters::simple::SimpleEngine`], `Uuid` for the master.
+/// Callbacks run inline while the engine handles the event, so they should
+/// return quickly.
+pub trait RunObserver<R = usize>: Send + Sync {
+    /// The call of `op_id` in `ctx_id` was handed to `task_id`.
+    fn on_dispatch(&self, _run_id: R, _op_id: OpId, _ctx_id: ContextId, _task_id: &str) {}
+
+    /// That call produced an output; a stream produces one per item.
+    fn on_output(&self, _run_id: R, _op_id: OpId, _ctx_id: ContextId, _task_id: &str) {}
+
+    /// That call, or one output of it, failed with `error`.
+    fn on_error(
+        &self,
+        _run_id: R,
+        _op_id: OpId,
+        _ctx_id: ContextId,
+        _task_id: &str,
+        _error: &TaskError,
+    ) {
+    }
+
+    /// `ctx_id` was created, below `parent` unless it is the run's root.
+    fn on_context_created(&self, _run_id: R, _ctx_id: ContextId, _parent: Option<ContextId>) {}
+
+    /// Nothing runs in `ctx_id` anymore; its children may still.
+    fn on_context_finished(&self, _run_id: R, _ctx_id: ContextId) {}
+
+    fn on_run_finished(&self, _run_id: R, _outcome: RunOutcome) {}
+}
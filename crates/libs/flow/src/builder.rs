@@ -10,9 +10,10 @@ use std::marker::PhantomData;
 use std::sync::Arc;
 
 use namu_core::ValueId;
+use serde::Serialize;
 
 use crate::graph::{Graph, NodeArena, TracedValue, ValueArena};
-use crate::ir::{BasicBlock, BlockId, NodeKind, Terminator};
+use crate::ir::{BasicBlock, BlockId, NodeId, NodeKind, Terminator};
 
 // --- Builder API ---
 
@@ -21,6 +22,10 @@ struct BuilderInner {
     val_arena: ValueArena,
     blocks: Vec<BasicBlock>,
     current_block_id: BlockId,
+    /// Stream calls not collected yet, innermost last.
+    fan_outs: Vec<NodeId>,
+    /// Parts of the workflow that could not be built, see [`Graph::errors`].
+    errors: Vec<String>,
 }
 pub struct Builder<T> {
     inner: RefCell<BuilderInner>,
@@ -41,6 +46,8 @@ impl<T> Builder<T> {
                 val_arena: ValueArena::default(),
                 blocks: vec![BasicBlock::default()],
                 current_block_id: 0,
+                fan_outs: Vec::new(),
+                errors: Vec::new(),
             }),
             _phantom: PhantomData,
         }
@@ -72,17 +79,57 @@ impl<T> Builder<T> {
         self.add_node(kind, arity)
     }
 
-    pub fn literal<L: Debug + Send + Sync + 'static>(&self, value: L) -> ValueId {
-        let debug_repr = format!("{:?}", value);
-        let kind = NodeKind::literal(Arc::new(value), debug_repr);
+    /// Call a stream task. What is built after it runs once per item, until
+    /// [`Builder::collect`] gathers the items back.
+    pub fn call_stream(&self, task_id: String, inputs: Vec<ValueId>) -> ValueId {
+        let output = self.call(task_id, inputs, 1)[0];
+        let mut inner = self.inner.borrow_mut();
+        let node_id = inner.node_arena.nodes.len() - 1;
+        inner.fan_outs.push(node_id);
+        output
+    }
+
+    /// Gather `input` from every item of the innermost open fan-out.
+    pub fn collect(&self, input: ValueId, ty: String) -> ValueId {
+        let from = self
+            .inner
+            .borrow_mut()
+            .fan_outs
+            .pop()
+            .expect("collect needs a stream call to gather from");
+        let kind = NodeKind::collect(from, input, ty);
         self.add_node(kind, 1)[0]
     }
 
+    pub fn literal<L: Serialize + Debug + Send + Sync + 'static>(
+        &self,
+        value: L,
+    ) -> anyhow::Result<ValueId> {
+        let debug_repr = format!("{:?}", value);
+        let (ty, json) = namu_core::literal::encode(&value)
+            .map_err(|err| anyhow::anyhow!("literal {debug_repr}: {err}"))?;
+        let kind = NodeKind::literal(Arc::new(value), debug_repr, ty, json);
+        Ok(self.add_node(kind, 1)[0])
+    }
+
+    /// Record a part of the workflow that could not be built; `value` stands
+    /// in for its output so building can go on and report every error.
+    pub fn fail(&self, err: anyhow::Error) -> ValueId {
+        let mut inner = self.inner.borrow_mut();
+        inner.errors.push(format!("{err:#}"));
+        inner.val_arena.new_value()
+    }
+
     pub fn phi(&self, from: Vec<(BlockId, ValueId)>) -> ValueId {
         let kind = NodeKind::phi(from);
         self.add_node(kind, 1)[0]
     }
 
+    pub fn input(&self, name: &str, ty: &str) -> ValueId {
+        let kind = NodeKind::input(name.to_string(), ty.to_string());
+        self.add_node(kind, 1)[0]
+    }
+
     fn seal_block(&self, terminator: Terminator) {
         let mut inner = self.inner.borrow_mut();
         let current_block_id = inner.current_block_id;
@@ -105,7 +152,9 @@ impl<T> Builder<T> {
 
     pub fn build(self) -> Graph<T> {
         let inner = self.inner.into_inner();
-        Graph::new(inner.node_arena, inner.blocks)
+        let mut graph = Graph::new(inner.node_arena, inner.blocks);
+        graph.errors = inner.errors;
+        graph
     }
 }
 
@@ -118,6 +167,15 @@ pub fn call<Env: 'static, T: 'static>(
     TracedValue::new(outs[0])
 }
 
+pub fn call_stream<Env: 'static, T: 'static>(
+    builder: &Builder<Env>,
+    task_id: &str,
+    inputs: Vec<ValueId>,
+) -> TracedValue<T> {
+    let out = builder.call_stream(task_id.to_string(), inputs);
+    TracedValue::new(out)
+}
+
 macro_rules! define_call {
     ($fname:ident, $arity:expr, [$($idx:tt),*], [$($T:ident),*]) => {
         #[allow(non_snake_case)]
@@ -151,11 +209,25 @@ define_call!(
     [A, B, C, D, E, F, G, H, I]
 );
 
-pub fn literal<T: Debug + Send + Sync + 'static, U>(
+pub fn literal<T: Serialize + Debug + Send + Sync + 'static, U>(
     builder: &Builder<U>,
     value: T,
 ) -> TracedValue<T> {
-    let id = builder.literal(value);
+    let id = builder
+        .literal(value)
+        .unwrap_or_else(|err| builder.fail(err));
+    TracedValue::new(id)
+}
+
+pub fn input<T: 'static, U>(builder: &Builder<U>, name: &str, ty: &str) -> TracedValue<T> {
+    let ty = namu_core::literal::type_tag::<T>().unwrap_or(ty);
+    let id = builder.input(name, ty);
+    TracedValue::new(id)
+}
+
+pub fn collect<G, T: 'static>(builder: &Builder<G>, value: TracedValue<T>) -> TracedValue<Vec<T>> {
+    let ty = namu_core::literal::type_tag::<T>().unwrap_or_else(type_name::<T>);
+    let id = builder.collect(value.id, ty.to_string());
     TracedValue::new(id)
 }
 
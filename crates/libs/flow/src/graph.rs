@@ -1,7 +1,7 @@
 use std::marker::PhantomData;
 
 use namu_core::ValueId;
-use namu_core::ir::{Call, Literal, Next, Operation, Phi, Workflow};
+use namu_core::ir::{Call, Collect, Input, Literal, Next, Operation, Phi, Workflow};
 
 use crate::ir::{BasicBlock, NodeId};
 use crate::{Node, NodeKind, Terminator};
@@ -55,6 +55,9 @@ impl ValueArena {
 pub struct Graph<T> {
     pub arena: NodeArena,
     pub blocks: Vec<BasicBlock>,
+    /// Why parts of the workflow could not be built, e.g. literals of a type
+    /// without a type tag. A graph with errors cannot be serialized.
+    pub errors: Vec<String>,
     _phantom: PhantomData<T>,
 }
 
@@ -63,13 +66,35 @@ impl<T> Graph<T> {
         Self {
             arena,
             blocks,
+            errors: Vec::new(),
             _phantom: PhantomData,
         }
     }
 
+    /// The workflow IR, or the errors met while building the graph.
+    pub fn try_to_serializable(&self, name: String) -> anyhow::Result<Workflow> {
+        if !self.errors.is_empty() {
+            anyhow::bail!(
+                "workflow {name} could not be built: {}",
+                self.errors.join("; ")
+            );
+        }
+        Ok(self.to_workflow(name))
+    }
+
+    /// Like [`Graph::try_to_serializable`].
+    ///
+    /// # Panics
+    /// If the graph has errors.
     pub fn to_serializable(&self, name: String) -> Workflow {
+        self.try_to_serializable(name)
+            .unwrap_or_else(|err| panic!("{err:#}"))
+    }
+
+    fn to_workflow(&self, name: String) -> Workflow {
         // Mapping helpers
         let mut ops: Vec<Operation> = Vec::new();
+        let mut inputs: Vec<Input> = Vec::new();
         let mut block_first_op: Vec<Option<usize>> = vec![None; self.blocks.len()];
         let mut block_last_op: Vec<Option<usize>> = vec![None; self.blocks.len()];
 
@@ -94,7 +119,15 @@ impl<T> Graph<T> {
                 }
 
                 match &node.kind {
-                    NodeKind::Literal { debug_repr, .. } => {
+                    NodeKind::Input { name, ty } => {
+                        // Inputs are seeded by the engine; they never become ops.
+                        inputs.push(Input {
+                            name: name.clone(),
+                            ty: ty.clone(),
+                            output: node.outputs[0],
+                        });
+                    }
+                    NodeKind::Literal { ty, json, .. } => {
                         // Flush if we've already encountered a Call in this batch.
                         if pending_call.is_some() {
                             let op_idx = push_pending_op(
@@ -116,7 +149,8 @@ impl<T> Graph<T> {
 
                         pending_literals.push(Literal {
                             output: node.outputs[0],
-                            value: debug_repr.clone(),
+                            ty: ty.clone(),
+                            value: json.clone(),
                         });
                     }
                     NodeKind::Phi { from } => {
@@ -170,6 +204,48 @@ impl<T> Graph<T> {
                             outputs: node.outputs.clone(),
                         });
                     }
+                    NodeKind::Collect { from, input, ty } => {
+                        // The collect ends the fanned-out part, so it gets an
+                        // op of its own after any pending call.
+                        if pending_call.is_some() {
+                            let op_idx = push_pending_op(
+                                &mut ops,
+                                &mut pending_literals,
+                                &mut pending_phis,
+                                &mut pending_call,
+                            );
+
+                            if let Some(prev_idx) = prev_op_idx {
+                                ops[prev_idx].next = Next::Jump { next: op_idx };
+                            } else {
+                                block_first_op[block_idx] = Some(op_idx);
+                            }
+
+                            prev_op_idx = Some(op_idx);
+                        }
+
+                        let op_idx = push_pending_op(
+                            &mut ops,
+                            &mut pending_literals,
+                            &mut pending_phis,
+                            &mut pending_call,
+                        );
+                        // `from` is a node id until the fourth pass.
+                        ops[op_idx].collect = Some(Collect {
+                            from: *from,
+                            input: *input,
+                            ty: ty.clone(),
+                            output: node.outputs[0],
+                        });
+
+                        if let Some(prev_idx) = prev_op_idx {
+                            ops[prev_idx].next = Next::Jump { next: op_idx };
+                        } else {
+                            block_first_op[block_idx] = Some(op_idx);
+                        }
+
+                        prev_op_idx = Some(op_idx);
+                    }
                 }
             }
 
@@ -200,12 +276,10 @@ impl<T> Graph<T> {
                 let placeholder_value = next_value_id;
                 next_value_id += 1;
                 let op = Operation {
-                    literals: vec![Literal {
-                        output: placeholder_value,
-                        value: "()".to_string(),
-                    }],
+                    literals: vec![Literal::unit(placeholder_value)],
                     phis: Vec::new(),
                     call: None,
+                    collect: None,
                     next: Next::Return { var: None },
                 };
                 ops.push(op);
@@ -248,8 +322,26 @@ impl<T> Graph<T> {
             }
         }
 
+        // Fourth pass: patch collect sources (NodeId -> OpId of the stream call)
+        let call_ops: Vec<(ValueId, usize)> = ops
+            .iter()
+            .enumerate()
+            .filter_map(|(op_idx, op)| Some((*op.call.as_ref()?.outputs.first()?, op_idx)))
+            .collect();
+        for op in &mut ops {
+            if let Some(collect) = &mut op.collect {
+                let output = self.arena.nodes[collect.from].outputs[0];
+                collect.from = call_ops
+                    .iter()
+                    .find(|(value, _)| *value == output)
+                    .map(|(_, op_idx)| *op_idx)
+                    .expect("collect source is not a call");
+            }
+        }
+
         Workflow {
             name,
+            inputs,
             operations: ops,
         }
     }
@@ -261,6 +353,9 @@ impl<T> Graph<T> {
             for &node_id in &block.instructions {
                 let node = &self.arena.nodes[node_id];
                 let line = match &node.kind {
+                    NodeKind::Input { name, ty } => {
+                        format!("  let var{} = input {}: {};\n", node_id, name, ty)
+                    }
                     NodeKind::Literal { debug_repr, .. } => {
                         format!("  let var{} = {};\n", node_id, debug_repr)
                     }
@@ -274,6 +369,12 @@ impl<T> Graph<T> {
                             parent_vars.join(", ")
                         )
                     }
+                    NodeKind::Collect { from, input, .. } => {
+                        format!(
+                            "  let var{} = collect(var{}, from var{});\n",
+                            node_id, input, from
+                        )
+                    }
                     NodeKind::Phi { from } => {
                         let from_str: Vec<String> = from
                             .iter()
@@ -324,6 +425,7 @@ fn push_pending_op(
         literals: std::mem::take(lits),
         phis: std::mem::take(phis),
         call: call.take(),
+        collect: None,
         next: Next::Return { var: None }, // placeholder
     };
     ops.push(op);
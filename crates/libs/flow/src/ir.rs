@@ -21,10 +21,24 @@ pub enum NodeKind {
     Literal {
         value: Value,
         debug_repr: String,
+        /// Type tag and JSON encoding emitted into the serialized IR.
+        ty: String,
+        json: serde_json::Value,
     },
     Phi {
         from: Vec<(BlockId, ValueId)>,
     },
+    Input {
+        name: String,
+        ty: String,
+    },
+    Collect {
+        /// The stream call whose items are gathered.
+        from: NodeId,
+        input: ValueId,
+        /// Type tag of `input`, emitted into the serialized IR.
+        ty: String,
+    },
 }
 
 impl NodeKind {
@@ -32,13 +46,26 @@ impl NodeKind {
         Self::Call { task_id, inputs }
     }
 
-    pub fn literal(value: Value, debug_repr: String) -> Self {
-        Self::Literal { value, debug_repr }
+    pub fn literal(value: Value, debug_repr: String, ty: String, json: serde_json::Value) -> Self {
+        Self::Literal {
+            value,
+            debug_repr,
+            ty,
+            json,
+        }
     }
 
     pub fn phi(from: Vec<(BlockId, ValueId)>) -> Self {
         Self::Phi { from }
     }
+
+    pub fn input(name: String, ty: String) -> Self {
+        Self::Input { name, ty }
+    }
+
+    pub fn collect(from: NodeId, input: ValueId, ty: String) -> Self {
+        Self::Collect { from, input, ty }
+    }
 }
 
 pub struct Node {
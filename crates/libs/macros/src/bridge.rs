@@ -14,6 +14,7 @@ struct Args {
     name: Option<String>,
     author: Option<String>,
     version: Option<String>,
+    concurrency: Option<usize>,
 }
 
 pub fn task_bridge_impl(input: TokenStream) -> TokenStream {
@@ -170,15 +171,19 @@ pub fn task_bridge_impl(input: TokenStream) -> TokenStream {
     // Build optional inventory submission
     let inventory_tokens = if let (Some(name_lit), Some(author_lit)) = (args.name, args.author) {
         let version_lit = args.version.unwrap_or_else(|| "0.1".to_string());
+        let concurrency = args.concurrency.unwrap_or(1);
         quote! {
             ::namu::__macro_exports::inventory::submit! {
                 ::namu::__macro_exports::TaskEntry {
                     name:   #name_lit,
                     author: #author_lit,
-                    create: || Box::new(<#task_path as ::core::default::Default>::default()),
+                    create: ::namu::__macro_exports::TaskCreate::Sync(|| {
+                        Box::new(<#task_path as ::core::default::Default>::default())
+                    }),
                     pack:   Some(pack),
                     unpack: Some(unpack),
                     version: #version_lit,
+                    concurrency: #concurrency,
                 }
             }
         }
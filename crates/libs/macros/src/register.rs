@@ -11,6 +11,8 @@ struct Args {
     name: String,
     author: String,
     version: Option<String>,
+    /// Overrides the task's `#[task(concurrency = n)]`.
+    concurrency: Option<usize>,
 }
 
 pub fn register_task_impl(input: TokenStream) -> TokenStream {
@@ -26,16 +28,21 @@ pub fn register_task_impl(input: TokenStream) -> TokenStream {
     let name_lit = args.name;
     let author_lit = args.author;
     let version_lit = args.version.unwrap_or_else(|| "0.1".to_string());
+    let concurrency = match args.concurrency {
+        Some(concurrency) => quote! { #concurrency },
+        None => quote! { #method_path::CONCURRENCY },
+    };
 
     let expanded = quote! {
         ::namu::__macro_exports::inventory::submit! {
             ::namu::__macro_exports::TaskEntry {
                 name:   #name_lit,
                 author: #author_lit,
-                create: || Box::new(#method_path::Task),
+                create: #method_path::CREATE,
                 pack:   Some(#method_path::pack),
                 unpack: Some(#method_path::unpack),
                 version: #version_lit,
+                concurrency: #concurrency,
             }
         }
     };
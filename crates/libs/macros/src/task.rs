@@ -10,7 +10,17 @@
 //!   - `#[task]` on a struct/enum/impl: Registers a direct task implementation.
 //!   - `#[task(batch)]`: Defines a `BatchedTask` with a default batch size.
 //!   - `#[task(batch, batch_size = 16)]`: Defines a `BatchedTask` with a specific batch size.
+//!   - `#[task(batch, max_wait_ms = 20)]`: Calls a partial batch once its first input has waited
+//!     this long, instead of waiting for a full batch.
 //!   - `#[task(stream)]`: Defines a `StreamTask`.
+//!   - `#[task]` on an `async fn`: Defines the `Async*Task` counterpart of its kind, which an
+//!     engine drives on its async runtime instead of a thread of its own. An async stream task
+//!     returns `Result<impl Stream<Item = Result<...>>>`.
+//!   - `#[task(single, concurrency = 8)]`: Lets an in-process engine run eight replicas of the task
+//!     at once. Defaults to one.
+//!   - `#[task(single, export)]`: Also exports the worker C ABI (`namu_task_create`,
+//!     `namu_task_call`, `namu_task_destroy`), so the crate builds as a `cdylib` or `wasm32-wasip1`
+//!     artifact. Only one task per crate can be exported.
 //!
 //! ## Generated Code
 //! 1. **Renamed Original Function**: The user's function is preserved with a prefix (e.g.,
@@ -27,6 +37,8 @@
 //!    function is what's called inside a `#[workflow]`. It takes `TracedValue`s as input, registers
 //!    the task with the executor's registry, and adds a `Call` node to the graph via the `Builder`
 //!    API.
+//! 6. **C ABI Exports** (with `export`): `extern "C"` functions that decode the JSON arguments,
+//!    call the original function and encode its `Result` through `namu_core::ffi`.
 
 use proc_macro::TokenStream;
 use proc_macro_error2::abort;
@@ -52,6 +64,9 @@ enum TaskType {
 struct TaskArgs {
     task_type: Option<TaskType>,
     batch_size: Option<usize>,
+    max_wait_ms: Option<u64>,
+    concurrency: Option<usize>,
+    export: bool,
 }
 
 impl Parse for TaskArgs {
@@ -75,19 +90,43 @@ impl Parse for TaskArgs {
             }
         }
 
-        if !input.is_empty() {
+        while !input.is_empty() {
             input.parse::<Token![,]>()?;
-        }
-
-        if !input.is_empty() {
-            let lookahead = input.lookahead1();
-            if lookahead.peek(Ident) {
-                let ident: Ident = input.parse()?;
-                if ident == "batch_size" {
+            if input.is_empty() {
+                break;
+            }
+            let ident: Ident = input.parse()?;
+            match ident.to_string().as_str() {
+                "batch_size" => {
                     input.parse::<Token![=]>()?;
                     let lit: LitInt = input.parse()?;
                     args.batch_size = Some(lit.base10_parse()?);
                 }
+                "max_wait_ms" => {
+                    input.parse::<Token![=]>()?;
+                    let lit: LitInt = input.parse()?;
+                    args.max_wait_ms = Some(lit.base10_parse()?);
+                }
+                "concurrency" => {
+                    input.parse::<Token![=]>()?;
+                    let lit: LitInt = input.parse()?;
+                    let concurrency = lit.base10_parse()?;
+                    if concurrency == 0 {
+                        return Err(syn::Error::new(
+                            lit.span(),
+                            "`concurrency` must be at least 1",
+                        ));
+                    }
+                    args.concurrency = Some(concurrency);
+                }
+                "export" => args.export = true,
+                _ => {
+                    return Err(syn::Error::new(
+                        ident.span(),
+                        "expected `batch_size = <n>`, `max_wait_ms = <n>`, `concurrency = <n>` or \
+                         `export`",
+                    ));
+                }
             }
         }
 
@@ -149,13 +188,15 @@ fn extract_vec_inner_type(ty: &Type) -> &Type {
     inner
 }
 
-/// Extracts the item type `T` from a type that is `impl Iterator<Item = T>`.
-fn extract_iterator_item_type(ty: &Type) -> &Type {
+/// Extracts the item type `T` from a type that is `impl Iterator<Item = T>`, or
+/// `impl Stream<Item = T>` for an async task.
+fn extract_iterator_item_type(ty: &Type, is_async: bool) -> &Type {
+    let trait_name = if is_async { "Stream" } else { "Iterator" };
     if let Type::ImplTrait(impl_trait) = ty {
         for bound in &impl_trait.bounds {
             if let syn::TypeParamBound::Trait(trait_bound) = bound
                 && let Some(segment) = trait_bound.path.segments.last()
-                && segment.ident == "Iterator"
+                && segment.ident == trait_name
                 && let PathArguments::AngleBracketed(args) = &segment.arguments
             {
                 for arg in &args.args {
@@ -170,18 +211,24 @@ fn extract_iterator_item_type(ty: &Type) -> &Type {
     }
     abort!(
         ty,
-        "Stream task must return `impl Iterator<Item = Result<...>>`"
+        "Stream task must return `impl {}<Item = Result<...>>`",
+        trait_name
     );
 }
 
 // --- Generator Functions ---
 
+/// Batch size of a `#[task(batch)]` that does not set `batch_size`.
+const DEFAULT_BATCH_SIZE: usize = 16;
+
 struct TaskDefinition<'a> {
     func_name: &'a Ident,
     struct_name: &'a Ident,
     impl_func_name: &'a Ident,
     task_type: TaskType,
+    is_async: bool,
     batch_size: Option<usize>,
+    max_wait_ms: Option<u64>,
     arg_names: &'a [Ident],
     arg_types: &'a [Box<Type>],
     return_ty: &'a Type,
@@ -189,7 +236,37 @@ struct TaskDefinition<'a> {
 
 // Generates the unit-struct plus the blanket `Task` impl that simply forwards to the
 // given specialization trait's `run` method (e.g. `SingleTask`, `BatchedTask`, `StreamTask`).
-fn generate_common_task_prelude(struct_name: &Ident, specialization_trait: &Ident) -> TokenStream2 {
+// Async tasks get an `AsyncTask` impl forwarding to `AsyncSingleTask` and so on instead.
+fn generate_common_task_prelude(
+    struct_name: &Ident,
+    specialization_trait: &str,
+    is_async: bool,
+) -> TokenStream2 {
+    if is_async {
+        let specialization_trait = format_ident!("Async{specialization_trait}");
+        return quote! {
+            #[allow(non_camel_case_types)]
+            #[derive(Clone, Copy)]
+            pub struct #struct_name;
+
+            #[::namu::__macro_exports::async_trait]
+            impl<C> ::namu::__macro_exports::AsyncTask<C> for #struct_name
+            where
+                C: ::namu::__macro_exports::TaskContext + 'static,
+            {
+                async fn prepare(&mut self) -> ::namu::__macro_exports::Result<()> { Ok(()) }
+
+                fn clone_box(&self) -> Box<dyn ::namu::__macro_exports::AsyncTask<C> + Send + Sync> {
+                    Box::new(*self)
+                }
+
+                async fn run(&mut self, context: C) -> ::namu::__macro_exports::Result<()> {
+                    ::namu::__macro_exports::#specialization_trait::run(self, context).await
+                }
+            }
+        };
+    }
+    let specialization_trait = format_ident!("{specialization_trait}");
     quote! {
         #[allow(non_camel_case_types)]
         #[derive(Clone, Copy)]
@@ -240,10 +317,26 @@ fn generate_single_task_impl(def: &TaskDefinition) -> TokenStream2 {
     let call_args = quote! { #(#arg_names),* };
 
     // Generate the common struct + Task impl once
-    let common = {
-        let trait_ident = format_ident!("SingleTask");
-        generate_common_task_prelude(struct_name, &trait_ident)
-    };
+    let common = generate_common_task_prelude(struct_name, "SingleTask", def.is_async);
+
+    if def.is_async {
+        return quote! {
+            #common
+
+            #[::namu::__macro_exports::async_trait]
+            impl<C> ::namu::__macro_exports::AsyncSingleTask<C> for #struct_name
+            where
+                C: ::namu::__macro_exports::TaskContext + 'static,
+            {
+                type Input = #input_type;
+                type Output = #output_type;
+                async fn call(&mut self, input: Self::Input) -> ::namu::__macro_exports::Result<Self::Output> {
+                    #input_destructuring
+                    #impl_func_name(#call_args).await
+                }
+            }
+        };
+    }
 
     quote! {
         #common
@@ -272,7 +365,14 @@ fn generate_batch_task_impl(def: &TaskDefinition) -> TokenStream2 {
             "Batch task must have exactly one argument: Vec<Input>"
         );
     }
-    let batch_size = def.batch_size.unwrap_or(16);
+    let batch_size = def.batch_size.unwrap_or(DEFAULT_BATCH_SIZE);
+    let max_wait = def.max_wait_ms.map(|ms| {
+        quote! {
+            fn max_wait(&self) -> Option<::std::time::Duration> {
+                Some(::std::time::Duration::from_millis(#ms))
+            }
+        }
+    });
     let input_vec_type = &def.arg_types[0];
     let input_type = extract_vec_inner_type(input_vec_type);
 
@@ -280,10 +380,30 @@ fn generate_batch_task_impl(def: &TaskDefinition) -> TokenStream2 {
     let output_type = extract_result_type(output_result_type);
 
     // Common struct + Task impl
-    let common = {
-        let trait_ident = format_ident!("BatchedTask");
-        generate_common_task_prelude(struct_name, &trait_ident)
-    };
+    let common = generate_common_task_prelude(struct_name, "BatchedTask", def.is_async);
+
+    if def.is_async {
+        return quote! {
+            #common
+
+            #[::namu::__macro_exports::async_trait]
+            impl<C> ::namu::__macro_exports::AsyncBatchedTask<C> for #struct_name
+            where
+                C: ::namu::__macro_exports::TaskContext + 'static,
+            {
+                type Input = #input_type;
+                type Output = #output_type;
+
+                fn batch_size(&self) -> usize { #batch_size }
+
+                #max_wait
+
+                async fn call(&mut self, input: Vec<Self::Input>) -> Vec<::namu::__macro_exports::Result<Self::Output>> {
+                    #impl_func_name(input).await
+                }
+            }
+        };
+    }
 
     quote! {
         #common
@@ -297,6 +417,8 @@ fn generate_batch_task_impl(def: &TaskDefinition) -> TokenStream2 {
 
             fn batch_size(&self) -> usize { #batch_size }
 
+            #max_wait
+
             fn call(&mut self, input: Vec<Self::Input>) -> Vec<::namu::__macro_exports::Result<Self::Output>> {
                 #impl_func_name(input)
             }
@@ -331,13 +453,38 @@ fn generate_stream_task_impl(def: &TaskDefinition) -> TokenStream2 {
     let call_args = quote! { #(#arg_names),* };
 
     let output_iterator_type = extract_result_type(def.return_ty);
-    let output_type = extract_result_type(extract_iterator_item_type(output_iterator_type));
+    let output_type = extract_result_type(extract_iterator_item_type(
+        output_iterator_type,
+        def.is_async,
+    ));
 
     // Common struct + Task impl
-    let common = {
-        let trait_ident = format_ident!("StreamTask");
-        generate_common_task_prelude(struct_name, &trait_ident)
-    };
+    let common = generate_common_task_prelude(struct_name, "StreamTask", def.is_async);
+
+    if def.is_async {
+        // The items follow once the call resolves; a failed call is its only item.
+        return quote! {
+            #common
+
+            #[::namu::__macro_exports::async_trait]
+            impl<C> ::namu::__macro_exports::AsyncStreamTask<C> for #struct_name
+            where
+                C: ::namu::__macro_exports::TaskContext + 'static,
+            {
+                type Input = #input_type;
+                type Output = #output_type;
+                fn call(&mut self, input: Self::Input) -> impl ::namu::__macro_exports::futures::Stream<Item = ::namu::__macro_exports::Result<Self::Output>> + Send {
+                    use ::namu::__macro_exports::futures::StreamExt;
+                    #input_destructuring
+                    ::namu::__macro_exports::futures::stream::once(#impl_func_name(#call_args))
+                        .flat_map(|items| match items {
+                            Ok(items) => items.left_stream(),
+                            Err(err) => ::namu::__macro_exports::futures::stream::iter([Err(err)]).right_stream(),
+                        })
+                }
+            }
+        };
+    }
 
     quote! {
         #common
@@ -383,13 +530,18 @@ fn generate_constructor(def: &TaskDefinition, original_sig: &syn::Signature) ->
         }
         TaskType::Stream => {
             let output_iterator_type = extract_result_type(def.return_ty);
-            let ty = extract_result_type(extract_iterator_item_type(output_iterator_type)).clone();
+            let ty = extract_result_type(extract_iterator_item_type(
+                output_iterator_type,
+                def.is_async,
+            ))
+            .clone();
             (ty.clone(), false, Vec::new())
         }
         _ => unreachable!(),
     };
 
     let mut constructor_sig = original_sig.clone();
+    constructor_sig.asyncness = None;
     constructor_sig.generics = parse_quote! { <G: 'static> };
     constructor_sig.inputs.clear();
     constructor_sig
@@ -428,6 +580,8 @@ fn generate_constructor(def: &TaskDefinition, original_sig: &syn::Signature) ->
     let call_fn_ident = if is_tuple {
         let arity = tuple_elems.len();
         format_ident!("call{}", arity)
+    } else if matches!(def.task_type, TaskType::Stream) {
+        format_ident!("call_stream")
     } else {
         format_ident!("call")
     };
@@ -515,7 +669,11 @@ fn generate_unpack_fn(def: &TaskDefinition) -> TokenStream2 {
         }
         TaskType::Stream => {
             let output_iterator_type = extract_result_type(def.return_ty);
-            let ty = extract_result_type(extract_iterator_item_type(output_iterator_type)).clone();
+            let ty = extract_result_type(extract_iterator_item_type(
+                output_iterator_type,
+                def.is_async,
+            ))
+            .clone();
             (ty, false, Vec::new())
         }
         _ => unreachable!(),
@@ -545,6 +703,116 @@ fn generate_unpack_fn(def: &TaskDefinition) -> TokenStream2 {
     }
 }
 
+// --- C ABI exports ---
+
+fn generate_ffi_exports(def: &TaskDefinition) -> TokenStream2 {
+    let impl_func_name = def.impl_func_name;
+    let arg_names = def.arg_names;
+    let arg_types = def.arg_types;
+
+    let decode_input = match def.task_type {
+        TaskType::Batch => {
+            let name = &arg_names[0];
+            let ty = &arg_types[0];
+            quote! { let #name: #ty = ::namu::__macro_exports::ffi::decode(input)?; }
+        }
+        _ if arg_names.is_empty() => quote! { let _ = input; },
+        _ if arg_names.len() == 1 => {
+            let name = &arg_names[0];
+            let ty = &arg_types[0];
+            quote! { let #name: #ty = ::namu::__macro_exports::ffi::decode(input)?; }
+        }
+        _ => quote! {
+            let (#(#arg_names),*): (#(#arg_types),*) = ::namu::__macro_exports::ffi::decode(input)?;
+        },
+    };
+
+    let call_args = quote! { #(#arg_names),* };
+    let run = match def.task_type {
+        TaskType::Single => quote! {
+            ::namu::__macro_exports::ffi::encode(&#impl_func_name(#call_args)?)
+        },
+        TaskType::Batch => {
+            let name = &arg_names[0];
+            quote! {
+                let __input_count = #name.len();
+                ::namu::__macro_exports::ffi::encode_batch(#impl_func_name(#name), __input_count)
+            }
+        }
+        TaskType::Stream => quote! {
+            let items = #impl_func_name(#call_args)?
+                .collect::<::namu::__macro_exports::Result<Vec<_>>>()?;
+            ::namu::__macro_exports::ffi::encode(&items)
+        },
+        TaskType::Direct => unreachable!(),
+    };
+
+    // `namu build` records these in the manifest's batch policy.
+    let batch_policy = match def.task_type {
+        TaskType::Batch => {
+            let batch_size = def.batch_size.unwrap_or(DEFAULT_BATCH_SIZE) as u64;
+            let max_wait = def.max_wait_ms.map(|ms| {
+                quote! {
+                    #[unsafe(no_mangle)]
+                    pub extern "C" fn namu_task_max_wait_ms() -> u64 {
+                        #ms
+                    }
+                }
+            });
+            quote! {
+                #[unsafe(no_mangle)]
+                pub extern "C" fn namu_task_batch_size() -> u64 {
+                    #batch_size
+                }
+
+                #max_wait
+            }
+        }
+        _ => quote! {},
+    };
+
+    quote! {
+        #batch_policy
+
+        #[unsafe(no_mangle)]
+        pub extern "C" fn namu_task_create() -> *mut ::std::ffi::c_void {
+            ::namu::__macro_exports::ffi::create()
+        }
+
+        /// # Safety
+        /// `handle` must come from `namu_task_create` and not be used afterwards.
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn namu_task_destroy(handle: *mut ::std::ffi::c_void) {
+            unsafe { ::namu::__macro_exports::ffi::destroy(handle) }
+        }
+
+        /// # Safety
+        /// See `namu_core::ffi::call`.
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn namu_task_call(
+            handle: *mut ::std::ffi::c_void,
+            input_ptr: *const u8,
+            input_len: usize,
+            output_ptr: *mut u8,
+            output_len: *mut usize,
+        ) -> i32 {
+            unsafe {
+                ::namu::__macro_exports::ffi::call(
+                    handle,
+                    input_ptr,
+                    input_len,
+                    output_ptr,
+                    output_len,
+                    |input| {
+                        #decode_input
+                        #run
+                    },
+                )
+            }
+        }
+    }
+}
+
 // --- Main Macro Logic ---
 
 pub fn task(attr: TokenStream, item: TokenStream) -> TokenStream {
@@ -622,7 +890,9 @@ pub fn task(attr: TokenStream, item: TokenStream) -> TokenStream {
         struct_name: &struct_name,
         impl_func_name: &impl_func_name,
         task_type,
+        is_async: func.sig.asyncness.is_some(),
         batch_size: args.batch_size,
+        max_wait_ms: args.max_wait_ms,
         arg_names: &arg_names,
         arg_types: &arg_types,
         return_ty,
@@ -639,8 +909,25 @@ pub fn task(attr: TokenStream, item: TokenStream) -> TokenStream {
 
     let pack_fn = generate_pack_fn(&def);
     let unpack_fn = generate_unpack_fn(&def);
+    let ffi_exports = if args.export {
+        if def.is_async {
+            abort!(
+                func.sig.asyncness,
+                "`export` is not supported for async tasks"
+            );
+        }
+        generate_ffi_exports(&def)
+    } else {
+        TokenStream2::new()
+    };
 
     let module_ident = func_name;
+    let concurrency = args.concurrency.unwrap_or(1);
+    let create = if def.is_async {
+        format_ident!("Async")
+    } else {
+        format_ident!("Sync")
+    };
 
     TokenStream::from(quote! {
         #[allow(non_snake_case)]
@@ -650,6 +937,13 @@ pub fn task(attr: TokenStream, item: TokenStream) -> TokenStream {
             #task_trait_impl
             #pack_fn
             #unpack_fn
+            #ffi_exports
+
+            /// Replicas an in-process engine runs of this task.
+            pub const CONCURRENCY: usize = #concurrency;
+
+            pub const CREATE: ::namu::__macro_exports::TaskCreate =
+                ::namu::__macro_exports::TaskCreate::#create(|| Box::new(#struct_name));
         }
 
         #constructor
@@ -22,8 +22,9 @@ pub fn r#type(_attr: TokenStream, item: TokenStream) -> TokenStream {
         ::namu::__macro_exports::inventory::submit! {
             ::namu::__macro_exports::TypeEntry {
                 name: #type_name,
-                type_id: stringify!(#name),
+                type_id: ::std::any::TypeId::of::<#name>,
                 deserialize: #deser_fn,
+                collect: ::namu::__macro_exports::collect_as::<#name>,
             }
         }
     };
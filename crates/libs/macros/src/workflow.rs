@@ -4,17 +4,21 @@ use darling::FromMeta;
 use darling::ast::NestedMeta;
 use proc_macro::TokenStream;
 use proc_macro_error2::abort;
-use quote::{format_ident, quote};
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{ToTokens, format_ident, quote};
 use syn::punctuated::Punctuated;
 use syn::visit_mut::{self, VisitMut};
 use syn::{
-    Block, Expr, ExprIf, Ident, ItemFn, Pat, ReturnType, Stmt, Token, parse_macro_input,
-    parse_quote,
+    Block, Expr, ExprIf, FnArg, Ident, ItemFn, Pat, ReturnType, Stmt, Token, UnOp,
+    parse_macro_input, parse_quote,
 };
 
 struct WorkflowVisitor {
     // We use BTreeSet for deterministic ordering of variables.
     scopes: Vec<BTreeSet<Ident>>,
+    /// Every name bound by a parameter or `let` in each open scope, mutable
+    /// or not; any other name in the body is a constant.
+    bindings: Vec<BTreeSet<Ident>>,
     next_control_flow_id: usize,
     builder_ident: Ident,
     last_expr_has_value: bool,
@@ -29,6 +33,7 @@ impl WorkflowVisitor {
     fn new(builder_ident: Ident) -> Self {
         Self {
             scopes: vec![],
+            bindings: vec![],
             next_control_flow_id: 0,
             builder_ident,
             last_expr_has_value: false,
@@ -43,10 +48,20 @@ impl WorkflowVisitor {
 
     fn enter_scope(&mut self) {
         self.scopes.push(Default::default());
+        self.bindings.push(Default::default());
     }
 
     fn exit_scope(&mut self) {
         self.scopes.pop();
+        self.bindings.pop();
+    }
+
+    fn insert_binding(&mut self, name: Ident) {
+        self.bindings.last_mut().unwrap().insert(name);
+    }
+
+    fn is_binding(&self, name: &Ident) -> bool {
+        self.bindings.iter().any(|scope| scope.contains(name))
     }
 
     fn insert_var(&mut self, name: Ident) {
@@ -56,6 +71,96 @@ impl WorkflowVisitor {
     fn list_vars(&self) -> Vec<Ident> {
         self.scopes.iter().flat_map(|x| x.iter().cloned()).collect()
     }
+
+    fn wrap_literal(&self, expr: &Expr) -> Expr {
+        let builder_ident = &self.builder_ident;
+        parse_quote! { ::namu::__macro_exports::literal(&#builder_ident, #expr) }
+    }
+
+    /// Wrap a `vec!`, struct or constructor literal, which must be built from
+    /// constants only: workflow values are not known until the workflow runs.
+    fn wrap_constant(&mut self, expr: &mut Expr, what: &str) {
+        if let Some(part) = self.first_non_constant(expr) {
+            abort!(
+                part,
+                "{} literals in workflows must be built from constants; \
+                 pass workflow values to a task that builds the {} instead",
+                what,
+                what
+            );
+        }
+        *expr = self.wrap_literal(expr);
+        self.last_expr_has_value = true;
+    }
+}
+
+impl WorkflowVisitor {
+    /// The first part of `expr` that is not a constant, if any.
+    ///
+    /// Constants are literals and paths that do not name a binding in scope
+    /// (`None`, `Color::Red`, `MAX`, `limit`), combined by operators, casts,
+    /// arrays, tuples, `vec!`, struct literals and constructor calls (see
+    /// [`is_constructor`]). Bindings and task calls are workflow values.
+    fn first_non_constant(&self, expr: &Expr) -> Option<TokenStream2> {
+        match expr {
+            Expr::Lit(_) => None,
+            Expr::Path(path) => match path.path.get_ident() {
+                Some(ident) if self.is_binding(ident) => Some(expr.to_token_stream()),
+                _ => None,
+            },
+            Expr::Unary(unary) => self.first_non_constant(&unary.expr),
+            Expr::Binary(binary) => self
+                .first_non_constant(&binary.left)
+                .or_else(|| self.first_non_constant(&binary.right)),
+            Expr::Cast(cast) => self.first_non_constant(&cast.expr),
+            Expr::Paren(paren) => self.first_non_constant(&paren.expr),
+            Expr::Group(group) => self.first_non_constant(&group.expr),
+            Expr::Reference(reference) => self.first_non_constant(&reference.expr),
+            Expr::Array(array) => array
+                .elems
+                .iter()
+                .find_map(|elem| self.first_non_constant(elem)),
+            Expr::Tuple(tuple) => tuple
+                .elems
+                .iter()
+                .find_map(|elem| self.first_non_constant(elem)),
+            Expr::Repeat(repeat) => self.first_non_constant(&repeat.expr),
+            Expr::Struct(expr_struct) => expr_struct
+                .fields
+                .iter()
+                .map(|field| &field.expr)
+                .chain(expr_struct.rest.as_deref())
+                .find_map(|expr| self.first_non_constant(expr)),
+            Expr::Call(call) if is_constructor(&call.func) => call
+                .args
+                .iter()
+                .find_map(|arg| self.first_non_constant(arg)),
+            Expr::Macro(mac) if mac.mac.path.is_ident("vec") => {
+                let tokens = &mac.mac.tokens;
+                match syn::parse2::<Expr>(quote! { [#tokens] }) {
+                    Ok(elems) => self.first_non_constant(&elems),
+                    Err(_) => Some(expr.to_token_stream()),
+                }
+            }
+            _ => Some(expr.to_token_stream()),
+        }
+    }
+}
+
+/// Whether a call of `func` builds a tuple struct or enum variant (`Some`,
+/// `Shape::Circle`) rather than calling a task. Both are paths, so they are
+/// told apart the way Rust names them: types and variants are `CamelCase`,
+/// functions `snake_case`.
+fn is_constructor(func: &Expr) -> bool {
+    let Expr::Path(path) = func else {
+        return false;
+    };
+    path.path.segments.last().is_some_and(|segment| {
+        segment
+            .ident
+            .to_string()
+            .starts_with(|c: char| c.is_ascii_uppercase())
+    })
 }
 
 impl VisitMut for WorkflowVisitor {
@@ -74,17 +179,35 @@ impl VisitMut for WorkflowVisitor {
 
     fn visit_expr_mut(&mut self, i: &mut Expr) {
         match i {
+            Expr::Call(call_expr) if is_constructor(&call_expr.func) => {
+                self.wrap_constant(i, "tuple struct or variant");
+            }
             Expr::Call(call_expr) => {
                 for arg in call_expr.args.iter_mut() {
                     self.visit_expr_mut(arg);
                 }
 
+                // `collect(expr)` gathers the items of the innermost stream call.
+                if matches!(&*call_expr.func, Expr::Path(path) if path.path.is_ident("collect")) {
+                    if call_expr.args.len() != 1 {
+                        abort!(call_expr, "collect takes exactly one argument");
+                    }
+                    call_expr.func = parse_quote! { ::namu::__macro_exports::collect };
+                }
+
                 let builder_ident = &self.builder_ident;
                 call_expr.args.insert(0, parse_quote! { &#builder_ident });
 
                 self.last_expr_has_value = true;
             }
-            Expr::Path(_) => {
+            Expr::Path(path) => {
+                if !path
+                    .path
+                    .get_ident()
+                    .is_some_and(|ident| self.is_binding(ident))
+                {
+                    *i = self.wrap_literal(i);
+                }
                 self.last_expr_has_value = true;
             }
             Expr::Assign(expr) => {
@@ -100,11 +223,23 @@ impl VisitMut for WorkflowVisitor {
                 *i = self.handle_while(while_expr);
                 self.last_expr_has_value = false;
             }
-            Expr::Lit(lit) => {
-                let builder_ident = &self.builder_ident;
-                *i = parse_quote! { ::namu::__macro_exports::literal(&#builder_ident, #lit) };
+            Expr::Lit(_) => {
+                *i = self.wrap_literal(i);
                 self.last_expr_has_value = true;
             }
+            // Constant expressions that are baked into the IR as a single typed literal.
+            Expr::Unary(unary)
+                if matches!(unary.op, UnOp::Neg(_)) && self.first_non_constant(i).is_none() =>
+            {
+                *i = self.wrap_literal(i);
+                self.last_expr_has_value = true;
+            }
+            Expr::Macro(mac) if mac.mac.path.is_ident("vec") => {
+                self.wrap_constant(i, "vec");
+            }
+            Expr::Struct(_) => {
+                self.wrap_constant(i, "struct");
+            }
             _ => visit_mut::visit_expr_mut(self, i),
         }
     }
@@ -117,6 +252,7 @@ impl VisitMut for WorkflowVisitor {
                         if let Some(init) = &mut local.init {
                             let is_mut = pat_ident.mutability.is_some();
                             self.visit_expr_mut(&mut init.expr);
+                            self.insert_binding(pat_ident.ident.clone());
                             if is_mut {
                                 self.insert_var(pat_ident.ident.clone());
                             }
@@ -137,6 +273,7 @@ impl VisitMut for WorkflowVisitor {
                         // Track mut variables inside tuple pattern
                         for elem in pat_tuple.elems.iter() {
                             if let Pat::Ident(pat_ident) = elem {
+                                self.insert_binding(pat_ident.ident.clone());
                                 if pat_ident.mutability.is_some() {
                                     self.insert_var(pat_ident.ident.clone());
                                 }
@@ -418,6 +555,33 @@ pub fn workflow(attr: TokenStream, item: TokenStream) -> TokenStream {
     let builder_ident = format_ident!("__builder");
     let mut visitor = WorkflowVisitor::new(builder_ident.clone());
     visitor.enter_scope();
+
+    // Workflow parameters become graph inputs bound before the body runs.
+    let mut input_bindings = Vec::new();
+    for arg in &func.sig.inputs {
+        let FnArg::Typed(pat_type) = arg else {
+            abort!(arg, "workflows cannot take `self`");
+        };
+        let Pat::Ident(pat_ident) = &*pat_type.pat else {
+            abort!(
+                pat_type.pat,
+                "only simple idents are supported as workflow parameters"
+            );
+        };
+        let name = &pat_ident.ident;
+        let name_str = name.to_string();
+        let ty = &pat_type.ty;
+        let ty_str = quote!(#ty).to_string().replace(' ', "");
+        let mutability = &pat_ident.mutability;
+        visitor.insert_binding(name.clone());
+        if mutability.is_some() {
+            visitor.insert_var(name.clone());
+        }
+        input_bindings.push(quote! {
+            let #mutability #name = ::namu::__macro_exports::input::<#ty, _>(&#builder_ident, #name_str, #ty_str);
+        });
+    }
+
     visitor.visit_block_mut(&mut func_body);
     visitor.exit_scope();
 
@@ -452,14 +616,15 @@ pub fn workflow(attr: TokenStream, item: TokenStream) -> TokenStream {
         #[allow(unused_braces)]
         pub fn #func_name() -> ::namu::__macro_exports::Graph<#return_type> {
             let #builder_ident = ::namu::__macro_exports::Builder::<#return_type>::new();
+            #(#input_bindings)*
 
             #body_and_seal
 
             #builder_ident.build()
         }
 
-        fn #build_ident() -> ::namu::__macro_exports::Workflow {
-            #func_name().to_serializable(#workflow_id.to_string())
+        fn #build_ident() -> ::namu::__macro_exports::Result<::namu::__macro_exports::Workflow> {
+            #func_name().try_to_serializable(#workflow_id.to_string())
         }
 
         ::namu::__macro_exports::inventory::submit! {
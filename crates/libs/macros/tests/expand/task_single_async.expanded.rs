@@ -0,0 +1,104 @@
+use namu_macros::task;
+#[allow(non_snake_case)]
+pub mod async_task {
+    use super::*;
+    async fn task_impl(a: i32, b: i32) -> anyhow::Result<i32> {
+        Ok(a + b)
+    }
+    #[allow(non_camel_case_types)]
+    pub struct Task;
+    #[automatically_derived]
+    #[allow(non_camel_case_types)]
+    impl ::core::clone::Clone for Task {
+        #[inline]
+        fn clone(&self) -> Task {
+            *self
+        }
+    }
+    #[automatically_derived]
+    #[allow(non_camel_case_types)]
+    impl ::core::marker::Copy for Task {}
+    #[::namu::__macro_exports::async_trait]
+    impl<C> ::namu::__macro_exports::AsyncTask<C> for Task
+    where
+        C: ::namu::__macro_exports::TaskContext + 'static,
+    {
+        async fn prepare(&mut self) -> ::namu::__macro_exports::Result<()> {
+            Ok(())
+        }
+        fn clone_box(
+            &self,
+        ) -> Box<dyn ::namu::__macro_exports::AsyncTask<C> + Send + Sync> {
+            Box::new(*self)
+        }
+        async fn run(&mut self, context: C) -> ::namu::__macro_exports::Result<()> {
+            ::namu::__macro_exports::AsyncSingleTask::run(self, context).await
+        }
+    }
+    #[::namu::__macro_exports::async_trait]
+    impl<C> ::namu::__macro_exports::AsyncSingleTask<C> for Task
+    where
+        C: ::namu::__macro_exports::TaskContext + 'static,
+    {
+        type Input = (i32, i32);
+        type Output = i32;
+        async fn call(
+            &mut self,
+            input: Self::Input,
+        ) -> ::namu::__macro_exports::Result<Self::Output> {
+            let (a, b) = input;
+            task_impl(a, b).await
+        }
+    }
+    #[allow(dead_code)]
+    pub fn pack(
+        mut inputs: Vec<::namu::__macro_exports::Value>,
+    ) -> ::namu::__macro_exports::Value {
+        if true {
+            match (&inputs.len(), &2usize) {
+                (left_val, right_val) => {
+                    if !(*left_val == *right_val) {
+                        let kind = ::core::panicking::AssertKind::Eq;
+                        ::core::panicking::assert_failed(
+                            kind,
+                            &*left_val,
+                            &*right_val,
+                            ::core::option::Option::None,
+                        );
+                    }
+                }
+            };
+        }
+        let v0 = {
+            let val = inputs.remove(0);
+            (*val.downcast_ref::<i32>().expect("pack downcast failed")).clone()
+        };
+        let v1 = {
+            let val = inputs.remove(0);
+            (*val.downcast_ref::<i32>().expect("pack downcast failed")).clone()
+        };
+        ::namu::__macro_exports::Value::new((v0, v1))
+    }
+    #[allow(dead_code)]
+    pub fn unpack(
+        val: ::namu::__macro_exports::Value,
+    ) -> Vec<::namu::__macro_exports::Value> {
+        <[_]>::into_vec(::alloc::boxed::box_new([val]))
+    }
+    /// Replicas an in-process engine runs of this task.
+    pub const CONCURRENCY: usize = 1usize;
+    pub const CREATE: ::namu::__macro_exports::TaskCreate = ::namu::__macro_exports::TaskCreate::Async(||
+    Box::new(Task));
+}
+#[allow(non_snake_case)]
+pub fn async_task<G: 'static>(
+    builder: &::namu::__macro_exports::Builder<G>,
+    a: ::namu::__macro_exports::TracedValue<i32>,
+    b: ::namu::__macro_exports::TracedValue<i32>,
+) -> ::namu::__macro_exports::TracedValue<i32> {
+    ::namu::__macro_exports::call(
+        &builder,
+        "async_task",
+        <[_]>::into_vec(::alloc::boxed::box_new([a.id, b.id])),
+    )
+}
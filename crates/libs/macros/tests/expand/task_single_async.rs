@@ -0,0 +1,6 @@
+use namu_macros::task;
+
+#[task(single)]
+async fn async_task(a: i32, b: i32) -> anyhow::Result<i32> {
+    Ok(a + b)
+}
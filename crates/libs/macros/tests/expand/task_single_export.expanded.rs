@@ -0,0 +1,138 @@
+use namu_macros::task;
+#[allow(non_snake_case)]
+pub mod add {
+    use super::*;
+    pub fn task_impl(a: i32, b: i32) -> anyhow::Result<i32> {
+        Ok(a + b)
+    }
+    #[allow(non_camel_case_types)]
+    pub struct Task;
+    #[automatically_derived]
+    #[allow(non_camel_case_types)]
+    impl ::core::clone::Clone for Task {
+        #[inline]
+        fn clone(&self) -> Task {
+            *self
+        }
+    }
+    #[automatically_derived]
+    #[allow(non_camel_case_types)]
+    impl ::core::marker::Copy for Task {}
+    impl<C> ::namu::__macro_exports::Task<C> for Task
+    where
+        C: ::namu::__macro_exports::TaskContext,
+    {
+        fn prepare(&mut self) -> ::namu::__macro_exports::Result<()> {
+            Ok(())
+        }
+        fn clone_boxed(
+            &self,
+        ) -> Box<dyn ::namu::__macro_exports::Task<C> + Send + Sync> {
+            Box::new(*self)
+        }
+        fn run(&mut self, context: C) -> ::namu::__macro_exports::Result<()> {
+            ::namu::__macro_exports::SingleTask::run(self, context)
+        }
+    }
+    impl<C> ::namu::__macro_exports::SingleTask<C> for Task
+    where
+        C: ::namu::__macro_exports::TaskContext,
+    {
+        type Input = (i32, i32);
+        type Output = i32;
+        fn call(
+            &mut self,
+            input: Self::Input,
+        ) -> ::namu::__macro_exports::Result<Self::Output> {
+            let (a, b) = input;
+            task_impl(a, b)
+        }
+    }
+    #[allow(dead_code)]
+    pub fn pack(
+        mut inputs: Vec<::namu::__macro_exports::Value>,
+    ) -> ::namu::__macro_exports::Value {
+        if true {
+            match (&inputs.len(), &2usize) {
+                (left_val, right_val) => {
+                    if !(*left_val == *right_val) {
+                        let kind = ::core::panicking::AssertKind::Eq;
+                        ::core::panicking::assert_failed(
+                            kind,
+                            &*left_val,
+                            &*right_val,
+                            ::core::option::Option::None,
+                        );
+                    }
+                }
+            };
+        }
+        let v0 = {
+            let val = inputs.remove(0);
+            (*val.downcast_ref::<i32>().expect("pack downcast failed")).clone()
+        };
+        let v1 = {
+            let val = inputs.remove(0);
+            (*val.downcast_ref::<i32>().expect("pack downcast failed")).clone()
+        };
+        ::namu::__macro_exports::Value::new((v0, v1))
+    }
+    #[allow(dead_code)]
+    pub fn unpack(
+        val: ::namu::__macro_exports::Value,
+    ) -> Vec<::namu::__macro_exports::Value> {
+        <[_]>::into_vec(::alloc::boxed::box_new([val]))
+    }
+    #[unsafe(no_mangle)]
+    pub extern "C" fn namu_task_create() -> *mut ::std::ffi::c_void {
+        ::namu::__macro_exports::ffi::create()
+    }
+    /// # Safety
+    /// `handle` must come from `namu_task_create` and not be used afterwards.
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C" fn namu_task_destroy(handle: *mut ::std::ffi::c_void) {
+        unsafe { ::namu::__macro_exports::ffi::destroy(handle) }
+    }
+    /// # Safety
+    /// See `namu_core::ffi::call`.
+    #[unsafe(no_mangle)]
+    pub unsafe extern "C" fn namu_task_call(
+        handle: *mut ::std::ffi::c_void,
+        input_ptr: *const u8,
+        input_len: usize,
+        output_ptr: *mut u8,
+        output_len: *mut usize,
+    ) -> i32 {
+        unsafe {
+            ::namu::__macro_exports::ffi::call(
+                handle,
+                input_ptr,
+                input_len,
+                output_ptr,
+                output_len,
+                |input| {
+                    let (a, b): (i32, i32) = ::namu::__macro_exports::ffi::decode(
+                        input,
+                    )?;
+                    ::namu::__macro_exports::ffi::encode(&task_impl(a, b)?)
+                },
+            )
+        }
+    }
+    /// Replicas an in-process engine runs of this task.
+    pub const CONCURRENCY: usize = 1usize;
+    pub const CREATE: ::namu::__macro_exports::TaskCreate = ::namu::__macro_exports::TaskCreate::Sync(||
+    Box::new(Task));
+}
+#[allow(non_snake_case)]
+pub fn add<G: 'static>(
+    builder: &::namu::__macro_exports::Builder<G>,
+    a: ::namu::__macro_exports::TracedValue<i32>,
+    b: ::namu::__macro_exports::TracedValue<i32>,
+) -> ::namu::__macro_exports::TracedValue<i32> {
+    ::namu::__macro_exports::call(
+        &builder,
+        "add",
+        <[_]>::into_vec(::alloc::boxed::box_new([a.id, b.id])),
+    )
+}
@@ -0,0 +1,6 @@
+use namu_macros::task;
+
+#[task(single, export)]
+pub fn add(a: i32, b: i32) -> anyhow::Result<i32> {
+    Ok(a + b)
+}
@@ -60,6 +60,10 @@ pub mod no_args_task {
     ) -> Vec<::namu::__macro_exports::Value> {
         <[_]>::into_vec(::alloc::boxed::box_new([val]))
     }
+    /// Replicas an in-process engine runs of this task.
+    pub const CONCURRENCY: usize = 1usize;
+    pub const CREATE: ::namu::__macro_exports::TaskCreate = ::namu::__macro_exports::TaskCreate::Sync(||
+    Box::new(Task));
 }
 #[allow(non_snake_case)]
 pub fn no_args_task<G: 'static>(
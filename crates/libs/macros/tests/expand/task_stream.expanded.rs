@@ -77,13 +77,17 @@ pub mod stream_task {
     ) -> Vec<::namu::__macro_exports::Value> {
         <[_]>::into_vec(::alloc::boxed::box_new([val]))
     }
+    /// Replicas an in-process engine runs of this task.
+    pub const CONCURRENCY: usize = 1usize;
+    pub const CREATE: ::namu::__macro_exports::TaskCreate = ::namu::__macro_exports::TaskCreate::Sync(||
+    Box::new(Task));
 }
 #[allow(non_snake_case)]
 pub fn stream_task<G: 'static>(
     builder: &::namu::__macro_exports::Builder<G>,
     input: ::namu::__macro_exports::TracedValue<i32>,
 ) -> ::namu::__macro_exports::TracedValue<i32> {
-    ::namu::__macro_exports::call(
+    ::namu::__macro_exports::call_stream(
         &builder,
         "stream_task",
         <[_]>::into_vec(::alloc::boxed::box_new([input.id])),
@@ -75,6 +75,10 @@ pub mod add_one {
     ) -> Vec<::namu::__macro_exports::Value> {
         <[_]>::into_vec(::alloc::boxed::box_new([val]))
     }
+    /// Replicas an in-process engine runs of this task.
+    pub const CONCURRENCY: usize = 1usize;
+    pub const CREATE: ::namu::__macro_exports::TaskCreate = ::namu::__macro_exports::TaskCreate::Sync(||
+    Box::new(Task));
 }
 #[allow(non_snake_case)]
 pub fn add_one<G: 'static>(
@@ -163,6 +167,10 @@ pub mod multiply_by_two {
     ) -> Vec<::namu::__macro_exports::Value> {
         <[_]>::into_vec(::alloc::boxed::box_new([val]))
     }
+    /// Replicas an in-process engine runs of this task.
+    pub const CONCURRENCY: usize = 1usize;
+    pub const CREATE: ::namu::__macro_exports::TaskCreate = ::namu::__macro_exports::TaskCreate::Sync(||
+    Box::new(Task));
 }
 #[allow(non_snake_case)]
 pub fn multiply_by_two<G: 'static>(
@@ -187,6 +195,8 @@ pub fn chained_tasks_workflow() -> ::namu::__macro_exports::Graph<i32> {
     ::namu::__macro_exports::return_value(&__builder, __result);
     __builder.build()
 }
-fn __namu_build_chained_tasks_workflow() -> ::namu::__macro_exports::Workflow {
-    chained_tasks_workflow().to_serializable("chained_tasks_workflow".to_string())
+fn __namu_build_chained_tasks_workflow() -> ::namu::__macro_exports::Result<
+    ::namu::__macro_exports::Workflow,
+> {
+    chained_tasks_workflow().try_to_serializable("chained_tasks_workflow".to_string())
 }
@@ -0,0 +1,217 @@
+use namu_macros::{task, workflow};
+#[allow(non_upper_case_globals)]
+const limit: i32 = 3;
+#[allow(non_snake_case)]
+pub mod unwrap_or_zero {
+    use super::*;
+    fn task_impl(a: Option<i32>) -> anyhow::Result<i32> {
+        Ok(a.unwrap_or(0))
+    }
+    #[allow(non_camel_case_types)]
+    pub struct Task;
+    #[automatically_derived]
+    #[allow(non_camel_case_types)]
+    impl ::core::clone::Clone for Task {
+        #[inline]
+        fn clone(&self) -> Task {
+            *self
+        }
+    }
+    #[automatically_derived]
+    #[allow(non_camel_case_types)]
+    impl ::core::marker::Copy for Task {}
+    impl<C> ::namu::__macro_exports::Task<C> for Task
+    where
+        C: ::namu::__macro_exports::TaskContext,
+    {
+        fn prepare(&mut self) -> ::namu::__macro_exports::Result<()> {
+            Ok(())
+        }
+        fn clone_boxed(
+            &self,
+        ) -> Box<dyn ::namu::__macro_exports::Task<C> + Send + Sync> {
+            Box::new(*self)
+        }
+        fn run(&mut self, context: C) -> ::namu::__macro_exports::Result<()> {
+            ::namu::__macro_exports::SingleTask::run(self, context)
+        }
+    }
+    impl<C> ::namu::__macro_exports::SingleTask<C> for Task
+    where
+        C: ::namu::__macro_exports::TaskContext,
+    {
+        type Input = Option<i32>;
+        type Output = i32;
+        fn call(
+            &mut self,
+            input: Self::Input,
+        ) -> ::namu::__macro_exports::Result<Self::Output> {
+            let a = input;
+            task_impl(a)
+        }
+    }
+    #[allow(dead_code)]
+    pub fn pack(
+        mut inputs: Vec<::namu::__macro_exports::Value>,
+    ) -> ::namu::__macro_exports::Value {
+        if true {
+            match (&inputs.len(), &1) {
+                (left_val, right_val) => {
+                    if !(*left_val == *right_val) {
+                        let kind = ::core::panicking::AssertKind::Eq;
+                        ::core::panicking::assert_failed(
+                            kind,
+                            &*left_val,
+                            &*right_val,
+                            ::core::option::Option::None,
+                        );
+                    }
+                }
+            };
+        }
+        inputs.pop().unwrap()
+    }
+    #[allow(dead_code)]
+    pub fn unpack(
+        val: ::namu::__macro_exports::Value,
+    ) -> Vec<::namu::__macro_exports::Value> {
+        <[_]>::into_vec(::alloc::boxed::box_new([val]))
+    }
+    /// Replicas an in-process engine runs of this task.
+    pub const CONCURRENCY: usize = 1usize;
+    pub const CREATE: ::namu::__macro_exports::TaskCreate = ::namu::__macro_exports::TaskCreate::Sync(||
+    Box::new(Task));
+}
+#[allow(non_snake_case)]
+pub fn unwrap_or_zero<G: 'static>(
+    builder: &::namu::__macro_exports::Builder<G>,
+    a: ::namu::__macro_exports::TracedValue<Option<i32>>,
+) -> ::namu::__macro_exports::TracedValue<i32> {
+    ::namu::__macro_exports::call(
+        &builder,
+        "unwrap_or_zero",
+        <[_]>::into_vec(::alloc::boxed::box_new([a.id])),
+    )
+}
+#[allow(non_snake_case)]
+pub mod add {
+    use super::*;
+    fn task_impl(a: i32, b: i32) -> anyhow::Result<i32> {
+        Ok(a + b)
+    }
+    #[allow(non_camel_case_types)]
+    pub struct Task;
+    #[automatically_derived]
+    #[allow(non_camel_case_types)]
+    impl ::core::clone::Clone for Task {
+        #[inline]
+        fn clone(&self) -> Task {
+            *self
+        }
+    }
+    #[automatically_derived]
+    #[allow(non_camel_case_types)]
+    impl ::core::marker::Copy for Task {}
+    impl<C> ::namu::__macro_exports::Task<C> for Task
+    where
+        C: ::namu::__macro_exports::TaskContext,
+    {
+        fn prepare(&mut self) -> ::namu::__macro_exports::Result<()> {
+            Ok(())
+        }
+        fn clone_boxed(
+            &self,
+        ) -> Box<dyn ::namu::__macro_exports::Task<C> + Send + Sync> {
+            Box::new(*self)
+        }
+        fn run(&mut self, context: C) -> ::namu::__macro_exports::Result<()> {
+            ::namu::__macro_exports::SingleTask::run(self, context)
+        }
+    }
+    impl<C> ::namu::__macro_exports::SingleTask<C> for Task
+    where
+        C: ::namu::__macro_exports::TaskContext,
+    {
+        type Input = (i32, i32);
+        type Output = i32;
+        fn call(
+            &mut self,
+            input: Self::Input,
+        ) -> ::namu::__macro_exports::Result<Self::Output> {
+            let (a, b) = input;
+            task_impl(a, b)
+        }
+    }
+    #[allow(dead_code)]
+    pub fn pack(
+        mut inputs: Vec<::namu::__macro_exports::Value>,
+    ) -> ::namu::__macro_exports::Value {
+        if true {
+            match (&inputs.len(), &2usize) {
+                (left_val, right_val) => {
+                    if !(*left_val == *right_val) {
+                        let kind = ::core::panicking::AssertKind::Eq;
+                        ::core::panicking::assert_failed(
+                            kind,
+                            &*left_val,
+                            &*right_val,
+                            ::core::option::Option::None,
+                        );
+                    }
+                }
+            };
+        }
+        let v0 = {
+            let val = inputs.remove(0);
+            (*val.downcast_ref::<i32>().expect("pack downcast failed")).clone()
+        };
+        let v1 = {
+            let val = inputs.remove(0);
+            (*val.downcast_ref::<i32>().expect("pack downcast failed")).clone()
+        };
+        ::namu::__macro_exports::Value::new((v0, v1))
+    }
+    #[allow(dead_code)]
+    pub fn unpack(
+        val: ::namu::__macro_exports::Value,
+    ) -> Vec<::namu::__macro_exports::Value> {
+        <[_]>::into_vec(::alloc::boxed::box_new([val]))
+    }
+    /// Replicas an in-process engine runs of this task.
+    pub const CONCURRENCY: usize = 1usize;
+    pub const CREATE: ::namu::__macro_exports::TaskCreate = ::namu::__macro_exports::TaskCreate::Sync(||
+    Box::new(Task));
+}
+#[allow(non_snake_case)]
+pub fn add<G: 'static>(
+    builder: &::namu::__macro_exports::Builder<G>,
+    a: ::namu::__macro_exports::TracedValue<i32>,
+    b: ::namu::__macro_exports::TracedValue<i32>,
+) -> ::namu::__macro_exports::TracedValue<i32> {
+    ::namu::__macro_exports::call(
+        &builder,
+        "add",
+        <[_]>::into_vec(::alloc::boxed::box_new([a.id, b.id])),
+    )
+}
+#[allow(unused_assignments)]
+#[allow(unused_braces)]
+pub fn constructor_literal_workflow() -> ::namu::__macro_exports::Graph<i32> {
+    let __builder = ::namu::__macro_exports::Builder::<i32>::new();
+    let __result = {
+        let first = ::namu::__macro_exports::literal(&__builder, Some(1));
+        let second = unwrap_or_zero(
+            &__builder,
+            ::namu::__macro_exports::literal(&__builder, Some(limit)),
+        );
+        add(&__builder, unwrap_or_zero(&__builder, first), second)
+    };
+    ::namu::__macro_exports::return_value(&__builder, __result);
+    __builder.build()
+}
+fn __namu_build_constructor_literal_workflow() -> ::namu::__macro_exports::Result<
+    ::namu::__macro_exports::Workflow,
+> {
+    constructor_literal_workflow()
+        .try_to_serializable("constructor_literal_workflow".to_string())
+}
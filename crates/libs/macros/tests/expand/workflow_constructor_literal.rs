@@ -0,0 +1,21 @@
+use namu_macros::{task, workflow};
+
+#[allow(non_upper_case_globals)]
+const limit: i32 = 3;
+
+#[task(single)]
+fn unwrap_or_zero(a: Option<i32>) -> anyhow::Result<i32> {
+    Ok(a.unwrap_or(0))
+}
+
+#[task(single)]
+fn add(a: i32, b: i32) -> anyhow::Result<i32> {
+    Ok(a + b)
+}
+
+#[workflow]
+fn constructor_literal_workflow() -> i32 {
+    let first = Some(1);
+    let second = unwrap_or_zero(Some(limit));
+    add(unwrap_or_zero(first), second)
+}
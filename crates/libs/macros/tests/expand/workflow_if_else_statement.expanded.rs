@@ -75,6 +75,10 @@ pub mod task_a {
     ) -> Vec<::namu::__macro_exports::Value> {
         <[_]>::into_vec(::alloc::boxed::box_new([val]))
     }
+    /// Replicas an in-process engine runs of this task.
+    pub const CONCURRENCY: usize = 1usize;
+    pub const CREATE: ::namu::__macro_exports::TaskCreate = ::namu::__macro_exports::TaskCreate::Sync(||
+    Box::new(Task));
 }
 #[allow(non_snake_case)]
 pub fn task_a<G: 'static>(
@@ -163,6 +167,10 @@ pub mod task_b {
     ) -> Vec<::namu::__macro_exports::Value> {
         <[_]>::into_vec(::alloc::boxed::box_new([val]))
     }
+    /// Replicas an in-process engine runs of this task.
+    pub const CONCURRENCY: usize = 1usize;
+    pub const CREATE: ::namu::__macro_exports::TaskCreate = ::namu::__macro_exports::TaskCreate::Sync(||
+    Box::new(Task));
 }
 #[allow(non_snake_case)]
 pub fn task_b<G: 'static>(
@@ -213,7 +221,9 @@ pub fn if_else_statement_workflow() -> ::namu::__macro_exports::Graph<()> {
     ::namu::__macro_exports::return_unit(&__builder);
     __builder.build()
 }
-fn __namu_build_if_else_statement_workflow() -> ::namu::__macro_exports::Workflow {
+fn __namu_build_if_else_statement_workflow() -> ::namu::__macro_exports::Result<
+    ::namu::__macro_exports::Workflow,
+> {
     if_else_statement_workflow()
-        .to_serializable("if_else_statement_workflow".to_string())
+        .try_to_serializable("if_else_statement_workflow".to_string())
 }
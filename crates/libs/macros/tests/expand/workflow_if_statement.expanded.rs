@@ -75,6 +75,10 @@ pub mod do_nothing {
     ) -> Vec<::namu::__macro_exports::Value> {
         <[_]>::into_vec(::alloc::boxed::box_new([val]))
     }
+    /// Replicas an in-process engine runs of this task.
+    pub const CONCURRENCY: usize = 1usize;
+    pub const CREATE: ::namu::__macro_exports::TaskCreate = ::namu::__macro_exports::TaskCreate::Sync(||
+    Box::new(Task));
 }
 #[allow(non_snake_case)]
 pub fn do_nothing<G: 'static>(
@@ -116,6 +120,8 @@ pub fn if_statement_workflow() -> ::namu::__macro_exports::Graph<()> {
     ::namu::__macro_exports::return_unit(&__builder);
     __builder.build()
 }
-fn __namu_build_if_statement_workflow() -> ::namu::__macro_exports::Workflow {
-    if_statement_workflow().to_serializable("if_statement_workflow".to_string())
+fn __namu_build_if_statement_workflow() -> ::namu::__macro_exports::Result<
+    ::namu::__macro_exports::Workflow,
+> {
+    if_statement_workflow().try_to_serializable("if_statement_workflow".to_string())
 }
@@ -75,6 +75,10 @@ pub mod is_positive {
     ) -> Vec<::namu::__macro_exports::Value> {
         <[_]>::into_vec(::alloc::boxed::box_new([val]))
     }
+    /// Replicas an in-process engine runs of this task.
+    pub const CONCURRENCY: usize = 1usize;
+    pub const CREATE: ::namu::__macro_exports::TaskCreate = ::namu::__macro_exports::TaskCreate::Sync(||
+    Box::new(Task));
 }
 #[allow(non_snake_case)]
 pub fn is_positive<G: 'static>(
@@ -148,6 +152,10 @@ pub mod action_if_true {
     ) -> Vec<::namu::__macro_exports::Value> {
         <[_]>::into_vec(::alloc::boxed::box_new([val]))
     }
+    /// Replicas an in-process engine runs of this task.
+    pub const CONCURRENCY: usize = 1usize;
+    pub const CREATE: ::namu::__macro_exports::TaskCreate = ::namu::__macro_exports::TaskCreate::Sync(||
+    Box::new(Task));
 }
 #[allow(non_snake_case)]
 pub fn action_if_true<G: 'static>(
@@ -184,7 +192,9 @@ pub fn if_with_task_in_condition_workflow() -> ::namu::__macro_exports::Graph<()
     ::namu::__macro_exports::return_unit(&__builder);
     __builder.build()
 }
-fn __namu_build_if_with_task_in_condition_workflow() -> ::namu::__macro_exports::Workflow {
+fn __namu_build_if_with_task_in_condition_workflow() -> ::namu::__macro_exports::Result<
+    ::namu::__macro_exports::Workflow,
+> {
     if_with_task_in_condition_workflow()
-        .to_serializable("if_with_task_in_condition_workflow".to_string())
+        .try_to_serializable("if_with_task_in_condition_workflow".to_string())
 }
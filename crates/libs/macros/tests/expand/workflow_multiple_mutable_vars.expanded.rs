@@ -83,6 +83,10 @@ pub mod less_than {
     ) -> Vec<::namu::__macro_exports::Value> {
         <[_]>::into_vec(::alloc::boxed::box_new([val]))
     }
+    /// Replicas an in-process engine runs of this task.
+    pub const CONCURRENCY: usize = 1usize;
+    pub const CREATE: ::namu::__macro_exports::TaskCreate = ::namu::__macro_exports::TaskCreate::Sync(||
+    Box::new(Task));
 }
 #[allow(non_snake_case)]
 pub fn less_than<G: 'static>(
@@ -180,6 +184,10 @@ pub mod add {
     ) -> Vec<::namu::__macro_exports::Value> {
         <[_]>::into_vec(::alloc::boxed::box_new([val]))
     }
+    /// Replicas an in-process engine runs of this task.
+    pub const CONCURRENCY: usize = 1usize;
+    pub const CREATE: ::namu::__macro_exports::TaskCreate = ::namu::__macro_exports::TaskCreate::Sync(||
+    Box::new(Task));
 }
 #[allow(non_snake_case)]
 pub fn add<G: 'static>(
@@ -325,7 +333,9 @@ pub fn multiple_mutable_vars_workflow() -> ::namu::__macro_exports::Graph<i32> {
     ::namu::__macro_exports::return_value(&__builder, __result);
     __builder.build()
 }
-fn __namu_build_multiple_mutable_vars_workflow() -> ::namu::__macro_exports::Workflow {
+fn __namu_build_multiple_mutable_vars_workflow() -> ::namu::__macro_exports::Result<
+    ::namu::__macro_exports::Workflow,
+> {
     multiple_mutable_vars_workflow()
-        .to_serializable("multiple_mutable_vars_workflow".to_string())
+        .try_to_serializable("multiple_mutable_vars_workflow".to_string())
 }
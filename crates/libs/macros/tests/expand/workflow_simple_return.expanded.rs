@@ -7,6 +7,8 @@ pub fn simple_return_workflow() -> ::namu::__macro_exports::Graph<i32> {
     ::namu::__macro_exports::return_value(&__builder, __result);
     __builder.build()
 }
-fn __namu_build_simple_return_workflow() -> ::namu::__macro_exports::Workflow {
-    simple_return_workflow().to_serializable("simple_return_workflow".to_string())
+fn __namu_build_simple_return_workflow() -> ::namu::__macro_exports::Result<
+    ::namu::__macro_exports::Workflow,
+> {
+    simple_return_workflow().try_to_serializable("simple_return_workflow".to_string())
 }
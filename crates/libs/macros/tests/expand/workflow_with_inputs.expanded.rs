@@ -0,0 +1,70 @@
+use namu_macros::workflow;
+#[allow(unused_assignments)]
+#[allow(unused_braces)]
+pub fn with_inputs_workflow() -> ::namu::__macro_exports::Graph<i32> {
+    let __builder = ::namu::__macro_exports::Builder::<i32>::new();
+    let mut a = ::namu::__macro_exports::input::<i32, _>(&__builder, "a", "i32");
+    let b = ::namu::__macro_exports::input::<i32, _>(&__builder, "b", "i32");
+    let __result = {
+        {
+            let __pre_while_a_0 = a;
+            let __while_header_block_0 = __builder.new_block();
+            let __while_body_block_0 = __builder.new_block();
+            let __while_exit_block_0 = __builder.new_block();
+            let __while_parent_predecessor_0 = __builder.current_block_id();
+            ::namu::__macro_exports::jump(&__builder, __while_header_block_0);
+            __builder.switch_to_block(__while_header_block_0);
+            let __a_phi_val_0 = {
+                let __phi_id = __builder
+                    .phi(
+                        <[_]>::into_vec(
+                            ::alloc::boxed::box_new([
+                                (__while_parent_predecessor_0, __pre_while_a_0.id),
+                            ]),
+                        ),
+                    );
+                ::namu::__macro_exports::TracedValue::new(__phi_id)
+            };
+            a = __a_phi_val_0;
+            let __a_phi_node_id_0 = __builder.arena().nodes.len() - 1;
+            let __while_cond = is_less(&__builder, a, b);
+            ::namu::__macro_exports::branch(
+                &__builder,
+                __while_cond,
+                __while_body_block_0,
+                __while_exit_block_0,
+            );
+            __builder.switch_to_block(__while_body_block_0);
+            {
+                a = add(&__builder, a, b);
+            };
+            let __post_body_a_0 = a;
+            let __body_predecessor_id_0 = __builder.current_block_id();
+            ::namu::__macro_exports::jump(&__builder, __while_header_block_0);
+            if let Some(
+                ::namu::__macro_exports::Node {
+                    kind: ::namu::__macro_exports::NodeKind::Phi { from },
+                    ..
+                },
+            ) = __builder.arena_mut().nodes.get_mut(__a_phi_node_id_0)
+            {
+                *from = <[_]>::into_vec(
+                    ::alloc::boxed::box_new([
+                        (__while_parent_predecessor_0, __pre_while_a_0.id),
+                        (__body_predecessor_id_0, __post_body_a_0.id),
+                    ]),
+                );
+            }
+            __builder.switch_to_block(__while_exit_block_0);
+            a = ::namu::__macro_exports::TracedValue::new(__a_phi_node_id_0);
+        }
+        a
+    };
+    ::namu::__macro_exports::return_value(&__builder, __result);
+    __builder.build()
+}
+fn __namu_build_with_inputs_workflow() -> ::namu::__macro_exports::Result<
+    ::namu::__macro_exports::Workflow,
+> {
+    with_inputs_workflow().try_to_serializable("with_inputs_workflow".to_string())
+}
@@ -0,0 +1,9 @@
+use namu_macros::workflow;
+
+#[workflow]
+fn with_inputs_workflow(mut a: i32, b: i32) -> i32 {
+    while is_less(a, b) {
+        a = add(a, b);
+    }
+    a
+}
@@ -1,3 +1,4 @@
+pub use namu_core::{TaskError, TaskErrorKind};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use uuid::Uuid;
@@ -43,6 +44,98 @@ pub struct TaskManifest {
     pub abi_version: String,
     pub build_toolchain: String,
     pub created_at: String,
+    #[serde(default)]
+    pub limits: TaskLimits,
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    #[serde(default)]
+    pub batch: BatchPolicy,
+}
+
+/// Per-call limits the worker enforces; unset fields are unlimited.
+///
+/// `fuel` and `max_memory_bytes` only apply to the wasm runtime.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TaskLimits {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fuel: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_memory_bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_output_bytes: Option<u64>,
+}
+
+/// How the orchestrator retries a failed or lease-expired call.
+///
+/// `max_attempts` counts the first try, so the default of 1 never retries.
+/// Retry `n` waits `backoff_ms * 2^(n-1)`, capped at `max_backoff_ms`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    /// Error kinds that fail the call immediately even though they are
+    /// retryable by default (e.g. `timeout`).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fatal_errors: Vec<TaskErrorKind>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            backoff_ms: 1_000,
+            max_backoff_ms: 60_000,
+            fatal_errors: Vec::new(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Whether a call that failed on `attempt` (starting at 1) with an error
+    /// of `kind` gets another attempt.
+    pub fn should_retry(&self, attempt: u32, kind: TaskErrorKind) -> bool {
+        attempt < self.max_attempts && kind.is_retryable() && !self.fatal_errors.contains(&kind)
+    }
+
+    /// Delay before the attempt following `attempt`.
+    pub fn backoff(&self, attempt: u32) -> std::time::Duration {
+        let factor = 1u64
+            .checked_shl(attempt.saturating_sub(1))
+            .unwrap_or(u64::MAX);
+        let delay = self
+            .backoff_ms
+            .saturating_mul(factor)
+            .min(self.max_backoff_ms);
+        std::time::Duration::from_millis(delay)
+    }
+}
+
+/// How a worker groups calls of a `batch` task into one artifact call.
+///
+/// After taking a call the worker waits up to `linger_ms` for more calls of
+/// the same task version, and calls the artifact once `max_size` are in.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct BatchPolicy {
+    pub max_size: usize,
+    pub linger_ms: u64,
+}
+
+impl Default for BatchPolicy {
+    fn default() -> Self {
+        Self {
+            max_size: 16,
+            linger_ms: 10,
+        }
+    }
+}
+
+fn first_attempt() -> u32 {
+    1
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -57,6 +150,9 @@ pub struct WorkflowUploadRequest {
 pub struct RunCreateRequest {
     pub workflow_id: String,
     pub version: String,
+    /// Workflow parameters keyed by name.
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub inputs: serde_json::Map<String, JsonValue>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -70,6 +166,31 @@ pub struct RunStatusResponse {
     pub progress: Progress,
 }
 
+/// Outcome of every leaf context of a run.
+///
+/// Each context that reached a `Return` contributes one entry to `results`
+/// (`null` when the workflow returns nothing); each call that failed for good
+/// contributes one entry to `failures`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunResultResponse {
+    pub status: String,
+    pub results: Vec<LeafResult>,
+    pub failures: Vec<LeafFailure>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeafResult {
+    pub ctx_id: usize,
+    pub value: JsonValue,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeafFailure {
+    pub ctx_id: usize,
+    pub op_id: usize,
+    pub error: Option<TaskError>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Progress {
     pub done: usize,
@@ -81,6 +202,8 @@ pub struct TaskStartRequest {
     pub op_id: usize,
     pub ctx_id: usize,
     pub lease_ms: u64,
+    #[serde(default = "first_attempt")]
+    pub attempt: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -89,7 +212,9 @@ pub struct TaskCompleteRequest {
     pub ctx_id: usize,
     pub success: bool,
     pub output_json: Option<JsonValue>,
-    pub error: Option<String>,
+    pub error: Option<TaskError>,
+    #[serde(default = "first_attempt")]
+    pub attempt: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -101,6 +226,9 @@ pub struct QueueMessage {
     pub task_version: String,
     pub input_ids: Vec<usize>,
     pub lease_ms: u64,
+    /// 1 for the first try, incremented on every retry.
+    #[serde(default = "first_attempt")]
+    pub attempt: u32,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub input_values: Option<Vec<JsonValue>>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
@@ -117,3 +245,73 @@ pub struct ValueRef {
     pub size: Option<u64>,
     pub codec: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            ..RetryPolicy::default()
+        }
+    }
+
+    #[test]
+    fn retries_retryable_kinds_until_max_attempts() {
+        let policy = policy(3);
+        assert!(policy.should_retry(1, TaskErrorKind::Retryable));
+        assert!(policy.should_retry(2, TaskErrorKind::Timeout));
+        assert!(!policy.should_retry(3, TaskErrorKind::Retryable));
+        assert!(!RetryPolicy::default().should_retry(1, TaskErrorKind::Retryable));
+    }
+
+    #[test]
+    fn never_retries_non_retryable_kinds() {
+        let policy = policy(3);
+        for kind in [
+            TaskErrorKind::Fatal,
+            TaskErrorKind::InvalidInput,
+            TaskErrorKind::Cancelled,
+        ] {
+            assert!(!policy.should_retry(1, kind), "{kind} was retried");
+        }
+    }
+
+    #[test]
+    fn fatal_errors_are_not_retried() {
+        let policy = RetryPolicy {
+            fatal_errors: vec![TaskErrorKind::Timeout],
+            ..policy(3)
+        };
+        assert!(!policy.should_retry(1, TaskErrorKind::Timeout));
+        assert!(policy.should_retry(1, TaskErrorKind::Retryable));
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max_backoff() {
+        let policy = RetryPolicy {
+            backoff_ms: 100,
+            max_backoff_ms: 500,
+            ..policy(10)
+        };
+        assert_eq!(policy.backoff(1), Duration::from_millis(100));
+        assert_eq!(policy.backoff(2), Duration::from_millis(200));
+        assert_eq!(policy.backoff(3), Duration::from_millis(400));
+        assert_eq!(policy.backoff(4), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn backoff_saturates_at_high_attempts() {
+        let policy = RetryPolicy {
+            max_backoff_ms: u64::MAX,
+            ..policy(u32::MAX)
+        };
+        assert_eq!(policy.backoff(64), Duration::from_millis(u64::MAX));
+        assert_eq!(policy.backoff(65), Duration::from_millis(u64::MAX));
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_millis(u64::MAX));
+        assert_eq!(RetryPolicy::default().backoff(200), Duration::from_secs(60));
+    }
+}
@@ -13,10 +13,10 @@ fn main() {
     runtime.block_on(async {
         let engine = SimpleEngine::with_registered();
         let wf_id = engine.create_workflow(serialized).await;
-        let run_id = engine.create_run(wf_id).await;
+        let run_id = engine.create_run(wf_id, Vec::new()).await;
         let engine_clone = engine.clone();
         let handle = tokio::spawn(async move { engine_clone.run(run_id).await });
-        let rx = engine.get_result(run_id);
+        let rx = engine.get_result(run_id).expect("run was created");
         let rx = rx.as_async();
         let result = rx.recv().await.unwrap();
         println!("result: {:?}", result.downcast_ref::<i32>().unwrap());
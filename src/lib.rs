@@ -1,5 +1,6 @@
 pub mod prelude {
     pub use anyhow::Result;
+    pub use namu_core::{TaskError, TaskErrorKind};
 
     pub use crate::{register_task, task, r#type, workflow};
 }
@@ -15,7 +16,7 @@ pub mod export {
         fs::create_dir_all(out_dir).context("create workflow export dir")?;
 
         for entry in namu_core::registry::get_workflows().values() {
-            let workflow = (entry.build)();
+            let workflow = (entry.build)()?;
             let json = serde_json::to_string_pretty(&workflow)?;
             let file_path = out_dir.join(format!("{}.workflow.ir.json", entry.id));
             fs::write(&file_path, json)?;
@@ -25,19 +26,28 @@ pub mod export {
     }
 }
 
+pub mod testing;
+
 #[doc(hidden)]
 pub mod __macro_exports {
-    pub use anyhow::Result;
+    pub use anyhow::{Result, anyhow};
+    pub use async_trait::async_trait;
+    pub use futures;
     pub use inventory;
     pub use namu_core::ir::Workflow;
+    pub use namu_core::literal::collect_as;
     pub use namu_core::registry::{
-        DeserializeFn, PackFn, TaskEntry, TaskImpl, TypeEntry, UnpackFn, WorkflowEntry,
+        AsyncTaskImpl, DeserializeFn, PackFn, TaskCreate, TaskEntry, TaskImpl, TypeEntry, UnpackFn,
+        WorkflowEntry,
+    };
+    pub use namu_core::{
+        AsyncBatchedTask, AsyncSingleTask, AsyncStreamTask, AsyncTask, BatchedTask, SingleTask,
+        StreamTask, Task, TaskContext, Value, ffi,
     };
-    pub use namu_core::{BatchedTask, SingleTask, StreamTask, Task, TaskContext, Value};
     pub use namu_flow::{
-        Builder, Graph, Node, NodeKind, Terminator, TracedValue, branch, call, call0, call1, call2,
-        call3, call4, call5, call6, call7, call8, call9, jump, literal, phi, return_unit,
-        return_value,
+        Builder, Graph, Node, NodeKind, Terminator, TracedValue, branch, call, call_stream, call0,
+        call1, call2, call3, call4, call5, call6, call7, call8, call9, collect, input, jump,
+        literal, phi, return_unit, return_value,
     };
 }
 
@@ -0,0 +1,439 @@
+//! Unit-test workflows against stubbed tasks.
+//!
+//! A [`TestEngine`] runs a workflow on a [`SimpleEngine`] whose tasks are
+//! stubs: closures, canned responses or injected failures, given per task
+//! id. A workflow's control flow can be tested without linking or running
+//! the tasks it calls, and every stubbed call is logged so tests can assert
+//! which `(task, inputs)` calls happened, and in what order.
+//!
+//! ```ignore
+//! let engine = TestEngine::new()
+//!     .returns_each("less_than", [Ok(true), Ok(false)])
+//!     .stub("add", |(a, b): (i32, i32)| Ok(a + b));
+//! let results = engine.run_blocking_as::<i32>(workflow, vec![Value::new(1), Value::new(8)])?;
+//! engine.assert_called("add", (1, 3));
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use anyhow::anyhow;
+use namu_core::ir::Workflow;
+use namu_core::registry::{TaskCreate, TaskEntry, TaskImpl};
+use namu_core::{ContextId, DynamicTaskContext, Task, TaskContext, Value};
+use namu_engine::engine::{Engine, TaskRegistry};
+use namu_engine::simple_engine::SimpleEngine;
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de, ser};
+use serde_json::Value as JsonValue;
+
+/// A call of a stubbed task.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestCall {
+    pub task_id: String,
+    /// The arguments of the call, as a JSON array.
+    pub inputs: JsonValue,
+}
+
+impl fmt::Display for TestCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.task_id, self.inputs)
+    }
+}
+
+/// Arguments a stub takes, as a tuple of the task's input types.
+pub trait StubArgs: Sized {
+    fn from_values(values: Vec<Value>) -> anyhow::Result<Self>;
+}
+
+fn take_arg<T: 'static>(value: Value, index: usize) -> anyhow::Result<T> {
+    value
+        .take::<T>()
+        .ok_or_else(|| anyhow!("argument {index} is not a {}", std::any::type_name::<T>()))
+}
+
+macro_rules! impl_stub_args {
+    ($($arg:ident),*) => {
+        impl<$($arg: 'static),*> StubArgs for ($($arg,)*) {
+            #[allow(unused_mut, unused_variables)]
+            fn from_values(values: Vec<Value>) -> anyhow::Result<Self> {
+                let arity = 0 $(+ { let _ = stringify!($arg); 1 })*;
+                if values.len() != arity {
+                    return Err(anyhow!(
+                        "stub takes {arity} arguments, the call passed {}",
+                        values.len()
+                    ));
+                }
+                let mut values = values.into_iter().enumerate();
+                Ok(($({
+                    let (index, value) = values.next().expect("arity checked");
+                    take_arg::<$arg>(value, index)?
+                },)*))
+            }
+        }
+    };
+}
+
+impl_stub_args!();
+impl_stub_args!(A);
+impl_stub_args!(A, B);
+impl_stub_args!(A, B, C);
+impl_stub_args!(A, B, C, D);
+impl_stub_args!(A, B, C, D, E);
+impl_stub_args!(A, B, C, D, E, F);
+impl_stub_args!(A, B, C, D, E, F, G);
+impl_stub_args!(A, B, C, D, E, F, G, H);
+
+/// The inputs of a call of a stub, packed as they were passed.
+#[derive(Clone)]
+struct Args(Vec<Value>);
+
+impl Args {
+    /// The inputs as a JSON array; needs no `#[type]` registration.
+    fn to_json(&self) -> anyhow::Result<JsonValue> {
+        self.0
+            .iter()
+            .map(|value| {
+                let mut json = Vec::new();
+                value.serialize(&mut serde_json::Serializer::new(&mut json))?;
+                Ok(serde_json::from_slice(&json)?)
+            })
+            .collect::<anyhow::Result<_>>()
+            .map(JsonValue::Array)
+    }
+}
+
+impl Serialize for Args {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_json()
+            .map_err(ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Args {
+    fn deserialize<D: Deserializer<'de>>(_: D) -> Result<Self, D::Error> {
+        Err(de::Error::custom(
+            "stub arguments only pass within the process",
+        ))
+    }
+}
+
+fn pack_args(inputs: Vec<Value>) -> Value {
+    Value::new(Args(inputs))
+}
+
+/// What a stub sends for one call: its outputs, then the end of the call.
+type Respond = Arc<dyn Fn(&DynamicTaskContext, ContextId, Vec<Value>) + Send + Sync>;
+
+fn send<T: Serialize + Clone + Send + Sync + 'static>(
+    context: &DynamicTaskContext,
+    id: ContextId,
+    output: anyhow::Result<T>,
+) {
+    let _ = context.send(id, output);
+}
+
+#[derive(Clone)]
+struct StubTask {
+    task_id: String,
+    respond: Respond,
+    calls: Arc<Mutex<Vec<TestCall>>>,
+}
+
+impl Task<DynamicTaskContext> for StubTask {
+    fn prepare(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn clone_boxed(&self) -> TaskImpl {
+        Box::new(self.clone())
+    }
+
+    fn run(&mut self, context: DynamicTaskContext) -> anyhow::Result<()> {
+        while let Ok((id, args)) = context.recv::<Args>() {
+            if !context.is_cancelled(id) {
+                let inputs = args.to_json().unwrap_or(JsonValue::Null);
+                self.calls
+                    .lock()
+                    .expect("calls lock poisoned")
+                    .push(TestCall {
+                        task_id: self.task_id.clone(),
+                        inputs,
+                    });
+                (self.respond)(&context, id, args.0);
+            }
+            let _ = context.send_end(id);
+        }
+        Ok(())
+    }
+}
+
+/// Runs workflows against stubbed tasks, see the [module docs](self).
+///
+/// Stubs take their arguments as a tuple of the task's input types, so a
+/// task `add(a: i32, b: i32)` is stubbed by a closure taking `(i32, i32)`
+/// and `double(v: i32)` by one taking `(i32,)`. A stub that replaces a task
+/// with several outputs returns the task's output tuple.
+#[derive(Default)]
+pub struct TestEngine {
+    stubs: HashMap<String, Respond>,
+    registered: bool,
+    calls: Arc<Mutex<Vec<TestCall>>>,
+}
+
+impl TestEngine {
+    /// An engine with no tasks but the stubs it is given.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An engine that also runs the tasks collected via `inventory`, for
+    /// the tasks that are not stubbed.
+    pub fn with_registered() -> Self {
+        Self {
+            registered: true,
+            ..Self::default()
+        }
+    }
+
+    fn respond(mut self, task_id: &str, respond: Respond) -> Self {
+        self.stubs.insert(task_id.to_string(), respond);
+        self
+    }
+
+    /// Answer each call of `task_id` with what `stub` returns for its
+    /// arguments.
+    pub fn stub<A, T, F>(self, task_id: &str, stub: F) -> Self
+    where
+        A: StubArgs,
+        T: Serialize + Clone + Send + Sync + 'static,
+        F: Fn(A) -> anyhow::Result<T> + Send + Sync + 'static,
+    {
+        self.respond(
+            task_id,
+            Arc::new(move |context, id, args| {
+                send(context, id, A::from_values(args).and_then(&stub));
+            }),
+        )
+    }
+
+    /// Answer each call of the stream task `task_id` with the items `stub`
+    /// returns for its arguments, one output per item.
+    pub fn stub_stream<A, T, I, F>(self, task_id: &str, stub: F) -> Self
+    where
+        A: StubArgs,
+        T: Serialize + Clone + Send + Sync + 'static,
+        I: IntoIterator<Item = T>,
+        F: Fn(A) -> anyhow::Result<I> + Send + Sync + 'static,
+    {
+        self.respond(
+            task_id,
+            Arc::new(
+                move |context, id, args| match A::from_values(args).and_then(&stub) {
+                    Ok(items) => items
+                        .into_iter()
+                        .for_each(|item| send(context, id, Ok(item))),
+                    Err(err) => send::<()>(context, id, Err(err)),
+                },
+            ),
+        )
+    }
+
+    /// Answer every call of `task_id` with `value`, whatever its arguments.
+    pub fn returns<T>(self, task_id: &str, value: T) -> Self
+    where
+        T: Serialize + Clone + Send + Sync + 'static,
+    {
+        self.respond(
+            task_id,
+            Arc::new(move |context, id, _| send(context, id, Ok(value.clone()))),
+        )
+    }
+
+    /// Answer the calls of `task_id` with `responses`, one per call in the
+    /// order the calls arrive; calls past the last response fail.
+    pub fn returns_each<T, I>(self, task_id: &str, responses: I) -> Self
+    where
+        T: Serialize + Clone + Send + Sync + 'static,
+        I: IntoIterator<Item = anyhow::Result<T>>,
+    {
+        let responses = Mutex::new(responses.into_iter().collect::<VecDeque<_>>());
+        let name = task_id.to_string();
+        self.respond(
+            task_id,
+            Arc::new(move |context, id, _| {
+                let response = responses
+                    .lock()
+                    .expect("responses lock poisoned")
+                    .pop_front()
+                    .unwrap_or_else(|| Err(anyhow!("stub of {name} ran out of responses")));
+                send(context, id, response);
+            }),
+        )
+    }
+
+    /// Fail every call of `task_id` with `message`.
+    pub fn fails(self, task_id: &str, message: impl Into<String>) -> Self {
+        let message = message.into();
+        self.respond(
+            task_id,
+            Arc::new(move |context, id, _| {
+                send::<()>(context, id, Err(anyhow!(message.clone())));
+            }),
+        )
+    }
+
+    /// Run `workflow` on `inputs` to its end; what each context that
+    /// returned returned.
+    ///
+    /// Fails without running if the workflow calls a task that is neither
+    /// stubbed nor, for [`TestEngine::with_registered`], registered.
+    pub async fn run(&self, workflow: Workflow, inputs: Vec<Value>) -> anyhow::Result<Vec<Value>> {
+        let entries = inventory::iter::<TaskEntry>
+            .into_iter()
+            .map(|entry| (entry.name, entry))
+            .collect::<HashMap<_, _>>();
+        for call in workflow.operations.iter().filter_map(|op| op.call.as_ref()) {
+            let task_id = call.task_id.as_str();
+            let known = self.stubs.contains_key(task_id)
+                || (self.registered && entries.contains_key(task_id));
+            if !known {
+                return Err(anyhow!("task {task_id} is neither stubbed nor registered"));
+            }
+        }
+
+        let engine = SimpleEngine::new();
+        for (task_id, respond) in &self.stubs {
+            let task = StubTask {
+                task_id: task_id.clone(),
+                respond: respond.clone(),
+                calls: self.calls.clone(),
+            };
+            // The task's own unpack splits a stubbed output tuple.
+            let unpack = entries.get(task_id.as_str()).and_then(|entry| entry.unpack);
+            engine
+                .add_task(task_id, Box::new(task), Some(pack_args), unpack)
+                .await;
+        }
+        if self.registered {
+            for entry in entries.values() {
+                if !self.stubs.contains_key(entry.name) {
+                    match entry.create {
+                        TaskCreate::Sync(create) => {
+                            engine
+                                .add_task(entry.name, create(), entry.pack, entry.unpack)
+                                .await
+                        }
+                        TaskCreate::Async(create) => {
+                            engine
+                                .add_async_task(entry.name, create(), entry.pack, entry.unpack)
+                                .await
+                        }
+                    }
+                    engine.set_concurrency(entry.name, entry.concurrency);
+                }
+            }
+        }
+
+        let workflow_id = engine.create_workflow(workflow).await;
+        let run_id = engine.create_run(workflow_id, inputs).await;
+        let rx = engine.get_result(run_id).expect("run was created");
+        engine.run(run_id).await?;
+
+        let mut results = Vec::new();
+        while let Ok(Some(value)) = rx.try_recv() {
+            results.push(value);
+        }
+        Ok(results)
+    }
+
+    /// [`TestEngine::run`] on a runtime of its own, for plain `#[test]`s.
+    pub fn run_blocking(
+        &self,
+        workflow: Workflow,
+        inputs: Vec<Value>,
+    ) -> anyhow::Result<Vec<Value>> {
+        tokio::runtime::Runtime::new()?.block_on(self.run(workflow, inputs))
+    }
+
+    /// [`TestEngine::run_blocking`] with the results taken as `T`s.
+    pub fn run_blocking_as<T: 'static>(
+        &self,
+        workflow: Workflow,
+        inputs: Vec<Value>,
+    ) -> anyhow::Result<Vec<T>> {
+        self.run_blocking(workflow, inputs)?
+            .into_iter()
+            .map(|value| {
+                value
+                    .take::<T>()
+                    .ok_or_else(|| anyhow!("result is not a {}", std::any::type_name::<T>()))
+            })
+            .collect()
+    }
+
+    /// The calls of stubbed tasks so far, in the order they arrived.
+    ///
+    /// Calls of different tasks run on different threads, so the order of
+    /// calls that do not depend on each other, like the items of a stream,
+    /// is only the order they happened to arrive in.
+    pub fn calls(&self) -> Vec<TestCall> {
+        self.calls.lock().expect("calls lock poisoned").clone()
+    }
+
+    /// The inputs of each call of `task_id` so far, in order.
+    pub fn calls_to(&self, task_id: &str) -> Vec<JsonValue> {
+        self.calls()
+            .into_iter()
+            .filter(|call| call.task_id == task_id)
+            .map(|call| call.inputs)
+            .collect()
+    }
+
+    /// Forget the calls so far.
+    pub fn clear_calls(&self) {
+        self.calls.lock().expect("calls lock poisoned").clear();
+    }
+
+    /// Assert `task_id` was called with `inputs`, a tuple of its arguments.
+    #[track_caller]
+    pub fn assert_called(&self, task_id: &str, inputs: impl Serialize) {
+        let inputs = serde_json::to_value(inputs).expect("inputs serialize");
+        let calls = self.calls_to(task_id);
+        assert!(
+            calls.contains(&inputs),
+            "expected a call {task_id}{inputs}, calls of {task_id}: {calls:?}"
+        );
+    }
+
+    /// Assert `task_id` was never called.
+    #[track_caller]
+    pub fn assert_not_called(&self, task_id: &str) {
+        let calls = self.calls_to(task_id);
+        assert!(
+            calls.is_empty(),
+            "expected no call of {task_id}, got {calls:?}"
+        );
+    }
+
+    /// Assert the calls so far are exactly `expected`, in order, each a task
+    /// id and a JSON array of its arguments.
+    #[track_caller]
+    pub fn assert_calls(&self, expected: &[(&str, JsonValue)]) {
+        let calls = self.calls();
+        let matches = calls.len() == expected.len()
+            && calls.iter().zip(expected).all(|(call, (task_id, inputs))| {
+                call.task_id == *task_id && call.inputs == *inputs
+            });
+        assert!(
+            matches,
+            "expected calls {expected:?}, got [{}]",
+            calls
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+}
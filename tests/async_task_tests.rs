@@ -0,0 +1,118 @@
+mod common;
+
+use std::time::Duration;
+
+use anyhow::Result;
+use futures::Stream;
+use itertools::Itertools;
+use namu::{register_task, task, workflow};
+use namu_core::registry::{TaskCreate, get_tasks};
+use namu_engine::engine::Engine;
+use namu_engine::simple_engine::SimpleEngine;
+
+use crate::common::*;
+
+#[task(single)]
+async fn delayed_add(a: i32, b: i32) -> Result<i32> {
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    Ok(a + b)
+}
+
+register_task! { method = delayed_add, name = "delayed_add", author = "test", version = "0.1" }
+
+#[task(stream)]
+async fn countdown(n: i32) -> Result<impl Stream<Item = Result<i32>>> {
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    if n < 0 {
+        anyhow::bail!("negative countdown");
+    }
+    Ok(futures::stream::iter((0..n).rev().map(Ok)))
+}
+
+register_task! { method = countdown, name = "countdown", author = "test", version = "0.1" }
+
+#[task(batch, batch_size = 4, max_wait_ms = 10)]
+async fn delayed_square(values: Vec<i32>) -> Vec<Result<i32>> {
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    values.into_iter().map(|v| Ok(v * v)).collect()
+}
+
+register_task! { method = delayed_square, name = "delayed_square", author = "test", version = "0.1" }
+
+fn sorted_i32(values: Vec<namu_core::Value>) -> Vec<i32> {
+    values
+        .iter()
+        .map(|v| *v.downcast_ref::<i32>().unwrap())
+        .sorted()
+        .collect()
+}
+
+#[test]
+fn async_tasks_register_as_async_entries() {
+    let tasks = get_tasks();
+    assert!(matches!(tasks["delayed_add"].create, TaskCreate::Async(_)));
+    assert!(matches!(tasks["add"].create, TaskCreate::Sync(_)));
+}
+
+#[test]
+fn async_single_task_runs_in_a_loop() {
+    #[workflow]
+    fn async_loop(mut a: i32, b: i32) -> i32 {
+        while less_than(a, b) {
+            a = delayed_add(a, 3);
+        }
+        a
+    }
+
+    let wf_ir = async_loop().to_serializable("async_loop".to_string());
+    let results = run_workflow_with_inputs(
+        wf_ir,
+        vec![namu_core::Value::new(1), namu_core::Value::new(8)],
+    );
+    assert_eq!(sorted_i32(results), vec![10]);
+}
+
+#[test]
+fn async_stream_and_batch_tasks_fan_out() {
+    #[workflow]
+    fn async_fan_out() -> i32 {
+        let n = countdown(4);
+        delayed_square(n)
+    }
+
+    let wf_ir = async_fan_out().to_serializable("async_fan_out".to_string());
+    let results = run_workflow(wf_ir);
+    assert_eq!(sorted_i32(results), vec![0, 1, 4, 9]);
+}
+
+#[test]
+fn failed_async_stream_call_yields_no_items() {
+    #[workflow]
+    fn failing_countdown() -> i32 {
+        let n = countdown(-1);
+        add(n, 1)
+    }
+
+    let wf_ir = failing_countdown().to_serializable("failing_countdown".to_string());
+    assert!(run_workflow(wf_ir).is_empty());
+}
+
+#[tokio::test]
+async fn async_tasks_run_on_a_current_thread_runtime() {
+    #[workflow]
+    fn chained() -> i32 {
+        let a = delayed_add(1, 2);
+        delayed_add(a, 4)
+    }
+
+    let engine = SimpleEngine::with_registered();
+    let wf_id = engine
+        .create_workflow(chained().to_serializable("chained".to_string()))
+        .await;
+    let run_id = engine.create_run(wf_id, Vec::new()).await;
+    let rx = engine.get_result(run_id).expect("run was created");
+    engine.run(run_id).await.unwrap();
+
+    let result = rx.try_recv().unwrap().unwrap();
+    assert_eq!(*result.downcast_ref::<i32>().unwrap(), 7);
+}
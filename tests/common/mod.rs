@@ -95,6 +95,13 @@ pub fn maybe_fail(v: i32) -> Result<i32> {
 
 register_task! { method = maybe_fail, name = "maybe_fail", author = "test", version = "0.1" }
 
+#[task(single)]
+pub fn sum(values: Vec<i32>) -> Result<i32> {
+    Ok(values.iter().sum())
+}
+
+register_task! { method = sum, name = "sum", author = "test", version = "0.1" }
+
 /// Convenience helper to execute a workflow IR with a freshly instantiated
 /// in-process [`SimpleEngine`].
 ///
@@ -112,17 +119,23 @@ register_task! { method = maybe_fail, name = "maybe_fail", author = "test", vers
 /// assert_eq!(result, 3);
 /// ```
 pub fn run_workflow(workflow: Workflow) -> Vec<Value> {
+    run_workflow_with_inputs(workflow, Vec::new())
+}
+
+/// Like [`run_workflow`], but binds `inputs` to the workflow parameters.
+pub fn run_workflow_with_inputs(workflow: Workflow, inputs: Vec<Value>) -> Vec<Value> {
     let runtime = tokio::runtime::Runtime::new().expect("tokio runtime");
     runtime.block_on(async {
         let engine = SimpleEngine::with_registered();
 
         let wf_id = engine.create_workflow(workflow).await;
-        let run_id = engine.create_run(wf_id).await;
+        let run_id = engine.create_run(wf_id, inputs).await;
+
+        let rx = engine.get_result(run_id).expect("run was created");
 
         let engine_clone = engine.clone();
         let handle = tokio::spawn(async move { engine_clone.run(run_id).await });
 
-        let rx = engine.get_result(run_id);
         let rx = rx.as_async();
         let mut values = Vec::new();
         while let Ok(value) = rx.recv().await {
@@ -0,0 +1,14 @@
+use graph::workflow;
+use namu as graph;
+
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+#[workflow]
+fn non_constant_struct_literal(a: i32) -> Point {
+    Point { x: 1, y: a }
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use graph::workflow;
+use namu as graph;
+
+#[workflow]
+fn non_constant_vec_literal(a: i32) -> Vec<i32> {
+    vec![1, a, 3]
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use graph::workflow;
+use namu as graph;
+
+#[workflow]
+fn non_constant_constructor(a: i32) -> Option<i32> {
+    Some(a)
+}
+
+fn main() {}
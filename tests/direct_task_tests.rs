@@ -48,10 +48,10 @@ async fn direct_task_workflow_builds() {
     let graph = wf().to_serializable("workflow".to_string());
     let engine = SimpleEngine::with_registered();
     let workflow_id = engine.create_workflow(graph).await;
-    let run_id = engine.create_run(workflow_id).await;
+    let run_id = engine.create_run(workflow_id, Vec::new()).await;
     let engine_clone = engine.clone();
     let handle = tokio::spawn(async move { engine_clone.run(run_id).await });
-    let rx = engine.get_result(run_id);
+    let rx = engine.get_result(run_id).expect("run was created");
     let rx = rx.as_async();
     let result = *rx.recv().await.unwrap().downcast_ref::<i32>().unwrap();
     handle.await.unwrap().unwrap();
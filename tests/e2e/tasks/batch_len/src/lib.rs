@@ -0,0 +1,8 @@
+use namu::prelude::*;
+
+/// Answers every input with the size of the batch it arrived in.
+#[task(batch, batch_size = 2, max_wait_ms = 5, export)]
+pub fn batch_len(inputs: Vec<i64>) -> Vec<Result<usize>> {
+    let len = inputs.len();
+    inputs.iter().map(|_| Ok(len)).collect()
+}
@@ -2,8 +2,17 @@
 
 mod common;
 
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
 use itertools::Itertools;
-use namu::workflow;
+use namu::{register_task, task, workflow};
+use namu_core::{TaskError, TaskErrorKind, Value};
+use namu_engine::engine::Engine;
+use namu_engine::simple_engine::SimpleEngine;
+use namu_engine::traits::observer::{RunObserver, RunOutcome};
 
 use crate::common::*;
 
@@ -101,3 +110,481 @@ fn engine_stream_failure_only_stops_leaf() {
         .collect::<Vec<_>>();
     assert_eq!(vals, vec![10, 30]);
 }
+
+#[test]
+fn engine_seeds_workflow_inputs() {
+    #[workflow]
+    fn input_workflow(mut a: i32, b: i32) -> i32 {
+        while less_than(a, b) {
+            a = add(a, 3);
+        }
+        a
+    }
+
+    let graph = input_workflow();
+    let wf_ir = graph.to_serializable("inputs".to_string());
+
+    let result_val = run_workflow_with_inputs(wf_ir, vec![Value::new(1), Value::new(8)]);
+
+    let val = *result_val[0].downcast_ref::<i32>().unwrap();
+    assert_eq!(val, 10);
+}
+
+#[test]
+fn engine_rejects_mistyped_inputs() {
+    #[workflow]
+    fn typed_input(a: i32) -> i32 {
+        add(a, 1)
+    }
+
+    let wf_ir = typed_input().to_serializable("typed_input".to_string());
+
+    let runtime = tokio::runtime::Runtime::new().expect("tokio runtime");
+    let err = runtime.block_on(async {
+        let engine = SimpleEngine::with_registered();
+        let wf_id = engine.create_workflow(wf_ir).await;
+        let run_id = engine.create_run(wf_id, vec![Value::new(1i64)]).await;
+        engine.run(run_id).await.unwrap_err()
+    });
+    let message = format!("{err:#}");
+    assert!(message.contains("invalid workflow input a"), "{message}");
+    assert!(message.contains("expected i32, got i64"), "{message}");
+}
+
+// ---- Independent calls ------------------------------------------------------
+
+#[test]
+fn engine_joins_independent_streams() {
+    #[workflow]
+    fn join_workflow() -> i32 {
+        let a = range(1, 3);
+        let b = split(100, 2);
+        add(a, b)
+    }
+
+    let graph = join_workflow();
+    let wf_ir = graph.to_serializable("join".to_string());
+
+    let result_val = run_workflow(wf_ir);
+
+    let vals = result_val
+        .iter()
+        .map(|v| *v.downcast_ref::<i32>().unwrap())
+        .sorted()
+        .collect::<Vec<_>>();
+    assert_eq!(vals, vec![110, 111, 120, 121]);
+}
+
+#[test]
+fn engine_does_not_rerun_joined_calls() {
+    #[workflow]
+    fn resume_workflow() -> i32 {
+        let a = range(1, 3);
+        let b = add(a, 1);
+        let c = split(100, 2);
+        add(b, c)
+    }
+
+    let graph = resume_workflow();
+    let wf_ir = graph.to_serializable("resume".to_string());
+
+    let result_val = run_workflow(wf_ir);
+
+    // `split` runs once per `range` item; running it again after `add(a, 1)`
+    // would double every result.
+    let vals = result_val
+        .iter()
+        .map(|v| *v.downcast_ref::<i32>().unwrap())
+        .sorted()
+        .collect::<Vec<_>>();
+    assert_eq!(vals, vec![111, 112, 121, 122]);
+}
+
+#[test]
+fn engine_join_stops_on_failed_member() {
+    #[workflow]
+    fn failed_join_workflow() -> i32 {
+        let a = range(1, 4);
+        let b = maybe_fail(a);
+        let c = split(100, 2);
+        add(b, c)
+    }
+
+    let graph = failed_join_workflow();
+    let wf_ir = graph.to_serializable("failed_join".to_string());
+
+    let result_val = run_workflow(wf_ir);
+
+    let vals = result_val
+        .iter()
+        .map(|v| *v.downcast_ref::<i32>().unwrap())
+        .sorted()
+        .collect::<Vec<_>>();
+    assert_eq!(vals, vec![110, 111, 130, 131]);
+}
+
+static SLOW_ITEMS: AtomicUsize = AtomicUsize::new(0);
+
+#[task(stream)]
+fn slow_count(n: i32) -> Result<impl Iterator<Item = Result<i32>>> {
+    Ok((0..n).map(|x| {
+        std::thread::sleep(Duration::from_millis(20));
+        SLOW_ITEMS.fetch_add(1, Ordering::SeqCst);
+        Ok(x)
+    }))
+}
+
+register_task! { method = slow_count, name = "slow_count", author = "test", version = "0.1" }
+
+#[test]
+fn engine_join_cancels_members_of_failed_group() {
+    #[workflow]
+    fn cancelled_join_workflow() -> i32 {
+        let a = maybe_fail(20);
+        let b = slow_count(50);
+        add(a, b)
+    }
+
+    let graph = cancelled_join_workflow();
+    let wf_ir = graph.to_serializable("cancelled_join".to_string());
+
+    let result_val = run_workflow(wf_ir);
+
+    assert!(result_val.is_empty());
+    // The stream is stopped once the other member failed, not run to its end.
+    assert!(SLOW_ITEMS.load(Ordering::SeqCst) < 50);
+}
+
+static ARRIVED: AtomicUsize = AtomicUsize::new(0);
+
+/// Wait until both sides arrived; only succeeds if they run at the same time.
+fn meet() -> bool {
+    ARRIVED.fetch_add(1, Ordering::SeqCst);
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while Instant::now() < deadline {
+        if ARRIVED.load(Ordering::SeqCst) >= 2 {
+            return true;
+        }
+        std::thread::yield_now();
+    }
+    false
+}
+
+#[task(single)]
+fn meet_left() -> Result<bool> {
+    Ok(meet())
+}
+
+register_task! { method = meet_left, name = "meet_left", author = "test", version = "0.1" }
+
+#[task(single)]
+fn meet_right() -> Result<bool> {
+    Ok(meet())
+}
+
+register_task! { method = meet_right, name = "meet_right", author = "test", version = "0.1" }
+
+#[task(single)]
+fn both(a: bool, b: bool) -> Result<bool> {
+    Ok(a && b)
+}
+
+register_task! { method = both, name = "both", author = "test", version = "0.1" }
+
+#[test]
+fn engine_runs_independent_calls_concurrently() {
+    #[workflow]
+    fn rendezvous_workflow() -> bool {
+        let a = meet_left();
+        let b = meet_right();
+        both(a, b)
+    }
+
+    let graph = rendezvous_workflow();
+    let wf_ir = graph.to_serializable("rendezvous".to_string());
+
+    let result_val = run_workflow(wf_ir);
+
+    assert!(*result_val[0].downcast_ref::<bool>().unwrap());
+}
+
+static STALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[task(single)]
+fn stall(n: i32) -> Result<i32> {
+    STALLS.fetch_add(1, Ordering::SeqCst);
+    std::thread::sleep(Duration::from_millis(200));
+    Ok(n + 1)
+}
+
+register_task! { method = stall, name = "stall", author = "test", version = "0.1" }
+
+#[test]
+fn engine_cancel_stops_run() {
+    #[workflow]
+    fn stall_workflow() -> i32 {
+        let a = stall(1);
+        stall(a)
+    }
+
+    let wf_ir = stall_workflow().to_serializable("stall".to_string());
+
+    let runtime = tokio::runtime::Runtime::new().expect("tokio runtime");
+    runtime.block_on(async {
+        let engine = SimpleEngine::with_registered();
+        let wf_id = engine.create_workflow(wf_ir).await;
+        let run_id = engine.create_run(wf_id, Vec::new()).await;
+        let rx = engine.get_result(run_id).expect("run was created");
+
+        let engine_clone = engine.clone();
+        let handle = tokio::spawn(async move { engine_clone.run(run_id).await });
+
+        while STALLS.load(Ordering::SeqCst) == 0 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        engine.cancel(run_id).await.unwrap();
+
+        tokio::time::timeout(Duration::from_secs(1), handle)
+            .await
+            .expect("cancelled run should return")
+            .expect("engine task panicked")
+            .expect("engine run failed");
+        assert!(rx.as_async().recv().await.is_err());
+        assert!(engine.cancel(run_id).await.is_err());
+
+        // The second call is never made once the run is cancelled.
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(STALLS.load(Ordering::SeqCst), 1);
+    });
+}
+
+#[test]
+fn engine_keeps_results_until_taken() {
+    #[workflow]
+    fn late_workflow() -> i32 {
+        add(1, 2)
+    }
+
+    let wf_ir = late_workflow().to_serializable("late".to_string());
+
+    let runtime = tokio::runtime::Runtime::new().expect("tokio runtime");
+    runtime.block_on(async {
+        let engine = SimpleEngine::with_registered();
+        let wf_id = engine.create_workflow(wf_ir).await;
+        let run_id = engine.create_run(wf_id, Vec::new()).await;
+        engine.run(run_id).await.unwrap();
+
+        let rx = engine.get_result(run_id).expect("results are kept");
+        assert_eq!(*rx.recv().unwrap().downcast_ref::<i32>().unwrap(), 3);
+        assert!(rx.recv().is_err());
+        assert!(engine.get_result(run_id).is_none());
+    });
+}
+
+// ---- Batching -------------------------------------------------------------
+
+#[task(batch, batch_size = 8, max_wait_ms = 10)]
+fn batch_double(values: Vec<i32>) -> Vec<Result<i32>> {
+    values.into_iter().map(|v| Ok(v * 2)).collect()
+}
+
+register_task! { method = batch_double, name = "batch_double", author = "test", version = "0.1" }
+
+#[test]
+fn engine_flushes_partial_batch_after_max_wait() {
+    // Three items never fill a batch of eight, and the engine keeps the
+    // task's input open, so only `max_wait_ms` lets the batch run.
+    #[workflow]
+    fn partial_batch_workflow() -> i32 {
+        let a = range(1, 4);
+        batch_double(a)
+    }
+
+    let wf_ir = partial_batch_workflow().to_serializable("partial_batch".to_string());
+
+    let result_val = run_workflow(wf_ir);
+
+    let results = result_val
+        .iter()
+        .map(|v| *v.downcast_ref::<i32>().unwrap())
+        .sorted()
+        .collect::<Vec<_>>();
+    assert_eq!(results, vec![20, 40, 60]);
+}
+
+// ---- Concurrency ------------------------------------------------------------
+
+static SLOW_ACTIVE: AtomicUsize = AtomicUsize::new(0);
+static SLOW_PEAK: AtomicUsize = AtomicUsize::new(0);
+
+#[task(single, concurrency = 4)]
+fn slow_double(v: i32) -> Result<i32> {
+    let active = SLOW_ACTIVE.fetch_add(1, Ordering::SeqCst) + 1;
+    SLOW_PEAK.fetch_max(active, Ordering::SeqCst);
+    std::thread::sleep(Duration::from_millis(30));
+    SLOW_ACTIVE.fetch_sub(1, Ordering::SeqCst);
+    Ok(v * 2)
+}
+
+register_task! { method = slow_double, name = "slow_double", author = "test", version = "0.1" }
+
+#[test]
+fn engine_runs_task_replicas_concurrently() {
+    #[workflow]
+    fn replicas_workflow() -> i32 {
+        let a = range(0, 8);
+        slow_double(a)
+    }
+
+    let entry = namu_core::registry::get_tasks()["slow_double"];
+    assert_eq!(entry.concurrency, 4);
+
+    let wf_ir = replicas_workflow().to_serializable("replicas".to_string());
+    let result_val = run_workflow(wf_ir);
+
+    let results = result_val
+        .iter()
+        .map(|v| *v.downcast_ref::<i32>().unwrap())
+        .sorted()
+        .collect::<Vec<_>>();
+    assert_eq!(results, (0..8).map(|x| x * 20).collect::<Vec<_>>());
+    let peak = SLOW_PEAK.load(Ordering::SeqCst);
+    assert!((2..=4).contains(&peak), "peak concurrency {peak}");
+}
+
+// ---- Fan-in -----------------------------------------------------------------
+
+#[test]
+fn engine_collects_stream_items_in_order() {
+    #[workflow]
+    fn collect_workflow() -> Vec<i32> {
+        let a = range(1, 4);
+        let b = add(a, 1);
+        collect(b)
+    }
+
+    let wf_ir = collect_workflow().to_serializable("collect".to_string());
+    assert_eq!(wf_ir.validate(), Ok(()));
+
+    let result_val = run_workflow(wf_ir);
+
+    assert_eq!(result_val.len(), 1);
+    assert_eq!(
+        result_val[0].downcast_ref::<Vec<i32>>().unwrap(),
+        &vec![11, 21, 31]
+    );
+}
+
+#[test]
+fn engine_continues_after_collect() {
+    #[workflow]
+    fn reduce_workflow(offset: i32) -> i32 {
+        let a = range(1, 4);
+        let b = add(a, offset);
+        let all = collect(b);
+        let total = sum(all);
+        add(total, offset)
+    }
+
+    let wf_ir = reduce_workflow().to_serializable("reduce".to_string());
+
+    let result_val = run_workflow_with_inputs(wf_ir, vec![Value::new(2)]);
+
+    assert_eq!(result_val.len(), 1);
+    assert_eq!(*result_val[0].downcast_ref::<i32>().unwrap(), 68);
+}
+
+#[test]
+fn engine_collects_empty_stream() {
+    #[workflow]
+    fn empty_workflow() -> i32 {
+        let a = range(4, 1);
+        let all = collect(a);
+        sum(all)
+    }
+
+    let wf_ir = empty_workflow().to_serializable("empty".to_string());
+
+    let result_val = run_workflow(wf_ir);
+
+    assert_eq!(result_val.len(), 1);
+    assert_eq!(*result_val[0].downcast_ref::<i32>().unwrap(), 0);
+}
+
+// ---- Observers --------------------------------------------------------------
+
+#[derive(Default)]
+struct CountingObserver {
+    dispatches: AtomicUsize,
+    outputs: AtomicUsize,
+    errors: AtomicUsize,
+    created: AtomicUsize,
+    finished: AtomicUsize,
+    runs: Mutex<Vec<RunOutcome>>,
+}
+
+impl RunObserver for CountingObserver {
+    fn on_dispatch(&self, _run_id: usize, _op_id: usize, _ctx_id: usize, _task_id: &str) {
+        self.dispatches.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn on_output(&self, _run_id: usize, _op_id: usize, _ctx_id: usize, _task_id: &str) {
+        self.outputs.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn on_error(
+        &self,
+        _run_id: usize,
+        _op_id: usize,
+        _ctx_id: usize,
+        task_id: &str,
+        error: &TaskError,
+    ) {
+        assert_eq!(task_id, "maybe_fail");
+        assert_eq!(error.kind, TaskErrorKind::Retryable);
+        assert_eq!(error.message, "intentional failure");
+        self.errors.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn on_context_created(&self, _run_id: usize, _ctx_id: usize, _parent: Option<usize>) {
+        self.created.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn on_context_finished(&self, _run_id: usize, _ctx_id: usize) {
+        self.finished.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn on_run_finished(&self, _run_id: usize, outcome: RunOutcome) {
+        self.runs.lock().unwrap().push(outcome);
+    }
+}
+
+#[test]
+fn engine_notifies_observers() {
+    #[workflow]
+    fn observed_workflow() -> i32 {
+        let a = range(1, 4);
+        maybe_fail(a)
+    }
+
+    let wf_ir = observed_workflow().to_serializable("observed".to_string());
+    let observer = Arc::new(CountingObserver::default());
+
+    let runtime = tokio::runtime::Runtime::new().expect("tokio runtime");
+    runtime.block_on(async {
+        let engine = SimpleEngine::with_registered();
+        engine.add_observer(observer.clone());
+        let wf_id = engine.create_workflow(wf_ir).await;
+        let run_id = engine.create_run(wf_id, Vec::new()).await;
+        engine.run(run_id).await.unwrap();
+    });
+
+    // One range call and a maybe_fail call per item, one of which fails.
+    assert_eq!(observer.dispatches.load(Ordering::SeqCst), 4);
+    assert_eq!(observer.outputs.load(Ordering::SeqCst), 5);
+    assert_eq!(observer.errors.load(Ordering::SeqCst), 1);
+    let created = observer.created.load(Ordering::SeqCst);
+    assert!(created > 1);
+    assert_eq!(observer.finished.load(Ordering::SeqCst), created);
+    assert_eq!(*observer.runs.lock().unwrap(), vec![RunOutcome::Finished]);
+}
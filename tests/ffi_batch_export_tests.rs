@@ -0,0 +1,65 @@
+use anyhow::Result;
+use namu::task;
+use namu_core::ffi::{OK, TASK_ERROR};
+use serde_json::{Value as JsonValue, json};
+
+#[task(batch, export)]
+pub fn halve(values: Vec<i32>) -> Vec<Result<i32>> {
+    values
+        .into_iter()
+        .map(|v| {
+            if v % 2 != 0 {
+                anyhow::bail!("{v} is odd");
+            }
+            Ok(v / 2)
+        })
+        .collect()
+}
+
+/// Call the exported ABI the way `namu-worker` does.
+fn call(input: &JsonValue) -> (i32, JsonValue) {
+    let input = serde_json::to_vec(input).unwrap();
+    let mut output = vec![0u8; 4096];
+    let mut output_len = output.len();
+    unsafe {
+        let handle = halve::namu_task_create();
+        let code = halve::namu_task_call(
+            handle,
+            input.as_ptr(),
+            input.len(),
+            output.as_mut_ptr(),
+            &mut output_len,
+        );
+        halve::namu_task_destroy(handle);
+        (code, serde_json::from_slice(&output[..output_len]).unwrap())
+    }
+}
+
+#[test]
+fn exported_batch_task_returns_one_entry_per_input() {
+    let (code, output) = call(&json!([4, 3, 10]));
+
+    assert_eq!(code, OK);
+    assert_eq!(
+        output,
+        json!([
+            { "ok": 2 },
+            { "error": { "kind": "retryable", "message": "3 is odd" } },
+            { "ok": 5 },
+        ])
+    );
+}
+
+#[test]
+fn exported_batch_task_rejects_single_input() {
+    let (code, output) = call(&json!(4));
+
+    assert_eq!(code, TASK_ERROR);
+    let message = output["error"]["message"].as_str().unwrap();
+    assert!(message.starts_with("invalid task input"), "{message}");
+}
+
+#[test]
+fn exported_batch_task_declares_its_batch_size() {
+    assert_eq!(halve::namu_task_batch_size(), 16);
+}
@@ -0,0 +1,119 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::Result;
+use namu::task;
+use namu_core::TaskError;
+use namu_core::ffi::{BUFFER_TOO_SMALL, OK, TASK_ERROR};
+use serde_json::{Value as JsonValue, json};
+
+/// Runs of `divide` with `a == 9`, used only by the retry test.
+static RETRY_RUNS: AtomicUsize = AtomicUsize::new(0);
+
+#[task(single, export)]
+pub fn divide(a: i32, b: i32) -> Result<Vec<i32>> {
+    if a == 9 {
+        RETRY_RUNS.fetch_add(1, Ordering::SeqCst);
+    }
+    if a < 0 {
+        return Err(TaskError::invalid_input("a must not be negative")
+            .with_details(json!({ "a": a }))
+            .into());
+    }
+    if b == 0 {
+        anyhow::bail!("division by zero");
+    }
+    Ok(vec![a / b; 8])
+}
+
+/// Call the exported ABI the way `namu-worker` does, with a fixed buffer.
+fn call(input: &JsonValue, capacity: usize) -> (i32, usize, Vec<u8>) {
+    let input = serde_json::to_vec(input).unwrap();
+    let mut output = vec![0u8; capacity];
+    let mut output_len = capacity;
+    unsafe {
+        let handle = divide::namu_task_create();
+        let code = divide::namu_task_call(
+            handle,
+            input.as_ptr(),
+            input.len(),
+            output.as_mut_ptr(),
+            &mut output_len,
+        );
+        divide::namu_task_destroy(handle);
+        output.truncate(output_len.min(capacity));
+        (code, output_len, output)
+    }
+}
+
+#[test]
+fn exported_task_encodes_output() {
+    let (code, _, output) = call(&json!([7, 2]), 4096);
+
+    assert_eq!(code, OK);
+    let output: JsonValue = serde_json::from_slice(&output).unwrap();
+    assert_eq!(output, json!([3, 3, 3, 3, 3, 3, 3, 3]));
+}
+
+#[test]
+fn exported_task_reports_errors() {
+    let (code, _, output) = call(&json!([7, 0]), 4096);
+    assert_eq!(code, TASK_ERROR);
+    let output: JsonValue = serde_json::from_slice(&output).unwrap();
+    assert_eq!(
+        output,
+        json!({ "error": { "kind": "retryable", "message": "division by zero" } })
+    );
+
+    let (code, _, output) = call(&json!([-1, 1]), 4096);
+    assert_eq!(code, TASK_ERROR);
+    let output: JsonValue = serde_json::from_slice(&output).unwrap();
+    assert_eq!(
+        output,
+        json!({ "error": {
+            "kind": "invalid_input",
+            "message": "a must not be negative",
+            "details": { "a": -1 },
+        } })
+    );
+
+    let (code, _, output) = call(&json!({ "a": 1 }), 4096);
+    assert_eq!(code, TASK_ERROR);
+    let output: JsonValue = serde_json::from_slice(&output).unwrap();
+    let message = output["error"]["message"].as_str().unwrap();
+    assert!(message.starts_with("invalid task input"), "{message}");
+    assert_eq!(output["error"]["kind"], "invalid_input");
+}
+
+#[test]
+fn exported_task_retries_without_rerunning() {
+    let input = serde_json::to_vec(&json!([9, 3])).unwrap();
+    let mut output_len = 4usize;
+    let mut output = vec![0u8; output_len];
+    unsafe {
+        let handle = divide::namu_task_create();
+
+        let code = divide::namu_task_call(
+            handle,
+            input.as_ptr(),
+            input.len(),
+            output.as_mut_ptr(),
+            &mut output_len,
+        );
+        assert_eq!(code, BUFFER_TOO_SMALL);
+        assert_eq!(output_len, "[3,3,3,3,3,3,3,3]".len());
+
+        output.resize(output_len, 0);
+        let code = divide::namu_task_call(
+            handle,
+            input.as_ptr(),
+            input.len(),
+            output.as_mut_ptr(),
+            &mut output_len,
+        );
+        divide::namu_task_destroy(handle);
+
+        assert_eq!(code, OK);
+        assert_eq!(output, b"[3,3,3,3,3,3,3,3]");
+        assert_eq!(RETRY_RUNS.load(Ordering::SeqCst), 1);
+    }
+}
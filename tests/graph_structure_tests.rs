@@ -167,3 +167,20 @@ Block 6:
   jump -> Block 4";
     assert_eq!(graph.graph_string().trim(), expected.trim());
 }
+
+#[test]
+fn workflow_inputs_graph_structure() {
+    #[workflow]
+    fn test_workflow(a: i32) -> i32 {
+        let b = 2;
+        add(a, b)
+    }
+
+    let graph = test_workflow();
+    let expected = "Block 0:
+  let var0 = input a: i32;
+  let var1 = 2;
+  let var2 = add(var0, var1);
+  return var2";
+    assert_eq!(graph.graph_string().trim(), expected.trim());
+}
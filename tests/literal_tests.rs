@@ -0,0 +1,225 @@
+mod common;
+
+use namu::prelude::*;
+use namu_core::Value;
+use namu_core::ir::Literal;
+use namu_engine::kernel::{CoreValueRuntime, JsonRuntime, ValueRuntime};
+use serde::{Deserialize, Serialize};
+
+use crate::common::{add, run_workflow, sum};
+
+#[namu::r#type]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+fn literal_of<T: Serialize + 'static>(value: T) -> Literal {
+    let (ty, value) = namu_core::literal::encode(&value).unwrap();
+    Literal {
+        output: 0,
+        ty,
+        value,
+    }
+}
+
+#[test]
+fn literal_type_tags() {
+    assert_eq!(literal_of(3_000_000_000i64).ty, "i64");
+    assert_eq!(literal_of(1.5f64).ty, "f64");
+    assert_eq!(literal_of('c').ty, "char");
+    assert_eq!(literal_of("hi").ty, "String");
+    assert_eq!(literal_of(vec![1u8, 2]).ty, "Vec<u8>");
+    assert_eq!(literal_of(Some(-1i16)).ty, "Option<i16>");
+    assert_eq!(literal_of(Point { x: 1, y: 2 }).ty, "Point");
+}
+
+#[test]
+fn core_runtime_rebuilds_exact_values() {
+    let runtime = CoreValueRuntime;
+    let parse = |lit: Literal| -> Value { runtime.parse_literal(&lit).unwrap() };
+
+    assert_eq!(
+        parse(literal_of(3_000_000_000i64)).downcast_ref::<i64>(),
+        Some(&3_000_000_000)
+    );
+    assert_eq!(parse(literal_of(1.5f64)).downcast_ref::<f64>(), Some(&1.5));
+    assert_eq!(parse(literal_of('c')).downcast_ref::<char>(), Some(&'c'));
+    assert_eq!(
+        parse(literal_of("hi")).downcast_ref::<String>(),
+        Some(&"hi".to_string())
+    );
+    assert_eq!(
+        parse(literal_of(vec![1, 2])).downcast_ref::<Vec<i32>>(),
+        Some(&vec![1, 2])
+    );
+    assert_eq!(
+        parse(literal_of(Point { x: 1, y: 2 })).downcast_ref::<Point>(),
+        Some(&Point { x: 1, y: 2 })
+    );
+}
+
+#[test]
+fn unknown_literal_type_is_rejected() {
+    #[derive(Serialize)]
+    struct Unregistered;
+
+    let err = namu_core::literal::encode(&Unregistered).unwrap_err();
+    assert!(err.to_string().contains("Unregistered"), "{err}");
+
+    let literal = Literal {
+        output: 0,
+        ty: "Unregistered".to_string(),
+        value: serde_json::Value::Null,
+    };
+    let err = CoreValueRuntime.parse_literal(&literal).unwrap_err();
+    assert!(err.to_string().contains("unknown literal type"));
+}
+
+#[test]
+fn workflow_with_unknown_literal_type_fails_to_build() {
+    #[derive(Debug, Serialize)]
+    struct Unregistered {}
+
+    #[workflow]
+    fn unregistered() -> Unregistered {
+        Unregistered {}
+    }
+
+    let err = unregistered()
+        .try_to_serializable("unregistered".to_string())
+        .unwrap_err();
+    let message = err.to_string();
+    assert!(
+        message.contains("workflow unregistered could not be built"),
+        "{message}"
+    );
+    assert!(message.contains("Unregistered"), "{message}");
+}
+
+#[test]
+fn encoded_values_decode_to_the_same_type() {
+    let roundtrip = |value: Value| -> Value {
+        let (ty, json) = namu_core::literal::encode_value(&value).unwrap();
+        namu_core::literal::decode(&ty, &json).unwrap()
+    };
+
+    assert_eq!(
+        roundtrip(Value::new(3_000_000_000i64)).downcast_ref::<i64>(),
+        Some(&3_000_000_000)
+    );
+    assert_eq!(
+        roundtrip(Value::new(vec![1u8, 2])).downcast_ref::<Vec<u8>>(),
+        Some(&vec![1, 2])
+    );
+    assert_eq!(
+        roundtrip(Value::new(Point { x: 1, y: 2 })).downcast_ref::<Point>(),
+        Some(&Point { x: 1, y: 2 })
+    );
+}
+
+#[test]
+fn unknown_value_type_is_not_encoded() {
+    #[derive(Clone, Serialize)]
+    struct Unregistered;
+
+    let err = namu_core::literal::encode_value(&Value::new(Unregistered)).unwrap_err();
+    assert!(err.to_string().contains("unknown type"));
+}
+
+#[test]
+fn json_runtime_keeps_literal_json() {
+    let value = JsonRuntime
+        .parse_literal(&literal_of(Point { x: 1, y: 2 }))
+        .unwrap();
+    assert_eq!(value, serde_json::json!({"x": 1, "y": 2}));
+}
+
+#[test]
+fn workflow_returns_typed_literals() {
+    #[workflow]
+    fn big_int() -> i64 {
+        3_000_000_000i64
+    }
+
+    #[workflow]
+    fn negative() -> f64 {
+        -1.5
+    }
+
+    #[workflow]
+    fn list() -> Vec<i32> {
+        vec![1, 2]
+    }
+
+    #[workflow]
+    fn point() -> Point {
+        Point { x: 3, y: 4 }
+    }
+
+    #[workflow]
+    fn some() -> Option<i32> {
+        Some(1)
+    }
+
+    #[allow(non_upper_case_globals)]
+    const offset: i64 = 7;
+
+    #[workflow]
+    fn lowercase_const() -> i64 {
+        offset
+    }
+
+    let out = run_workflow(big_int().to_serializable("big_int".to_string()));
+    assert_eq!(out[0].downcast_ref::<i64>(), Some(&3_000_000_000));
+
+    let out = run_workflow(negative().to_serializable("negative".to_string()));
+    assert_eq!(out[0].downcast_ref::<f64>(), Some(&-1.5));
+
+    let out = run_workflow(list().to_serializable("list".to_string()));
+    assert_eq!(out[0].downcast_ref::<Vec<i32>>(), Some(&vec![1, 2]));
+
+    let out = run_workflow(point().to_serializable("point".to_string()));
+    assert_eq!(out[0].downcast_ref::<Point>(), Some(&Point { x: 3, y: 4 }));
+
+    let out = run_workflow(some().to_serializable("some".to_string()));
+    assert_eq!(out[0].downcast_ref::<Option<i32>>(), Some(&Some(1)));
+
+    let out = run_workflow(lowercase_const().to_serializable("lowercase_const".to_string()));
+    assert_eq!(out[0].downcast_ref::<i64>(), Some(&7));
+}
+
+#[test]
+fn json_inputs_are_checked_against_declared_types() {
+    #[workflow]
+    fn typed_inputs(a: i32, values: Vec<i32>) -> i32 {
+        add(a, sum(values))
+    }
+
+    let wf_ir = typed_inputs().to_serializable("typed_inputs".to_string());
+    assert_eq!(wf_ir.inputs[1].ty, "Vec<i32>");
+    let bind =
+        |inputs: serde_json::Value| JsonRuntime::bind_inputs(&wf_ir, inputs.as_object().unwrap());
+
+    let bound = bind(serde_json::json!({"a": 1, "values": [2, 3]})).unwrap();
+    assert_eq!(bound, vec![serde_json::json!(1), serde_json::json!([2, 3])]);
+
+    let err = bind(serde_json::json!({"a": "1", "values": [2]})).unwrap_err();
+    assert!(
+        err.to_string().contains("invalid workflow input a"),
+        "{err}"
+    );
+
+    let err = bind(serde_json::json!({"a": 1, "values": [2.5]})).unwrap_err();
+    assert!(
+        err.to_string().contains("invalid workflow input values"),
+        "{err}"
+    );
+}
+
+#[test]
+fn json_inputs_of_unknown_types_pass_unchecked() {
+    assert!(namu_core::literal::check("NotLinked", &serde_json::json!({"x": 1})).is_ok());
+    assert!(namu_core::literal::check("Point", &serde_json::json!({"x": 1})).is_err());
+}
@@ -0,0 +1,156 @@
+mod common;
+
+use std::path::PathBuf;
+
+use itertools::Itertools;
+use namu::workflow;
+use namu_core::Value;
+use namu_core::ir::Workflow;
+use namu_engine::adapters::replay::replay;
+use namu_engine::engine::Engine;
+use namu_engine::runtime::replay::Recording;
+use namu_engine::simple_engine::SimpleEngine;
+
+use crate::common::*;
+
+/// A fresh directory for the recordings of `name`, removed when dropped.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("namu-replay-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        TempDir(dir)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Run `workflow` on a recording engine; its sorted results and recording.
+fn record(name: &str, workflow: Workflow, inputs: Vec<Value>) -> (Vec<i64>, Recording) {
+    let dir = TempDir::new(name);
+    let runtime = tokio::runtime::Runtime::new().expect("tokio runtime");
+    runtime.block_on(async {
+        let engine = SimpleEngine::with_registered();
+        engine.record_to(&dir.0);
+
+        let wf_id = engine.create_workflow(workflow).await;
+        let run_id = engine.create_run(wf_id, inputs).await;
+        let rx = engine.get_result(run_id).expect("run was created");
+        engine.run(run_id).await.expect("engine run failed");
+
+        let mut results = Vec::new();
+        while let Ok(Some(value)) = rx.try_recv() {
+            results.push(*value.downcast_ref::<i32>().unwrap() as i64);
+        }
+        results.sort();
+
+        let recording = Recording::open(dir.0.join(format!("{run_id}.jsonl"))).unwrap();
+        (results, recording)
+    })
+}
+
+/// Replay `recording`; its sorted results, or why it stopped.
+fn replay_results(recording: &Recording) -> anyhow::Result<Vec<i64>> {
+    let runtime = tokio::runtime::Runtime::new().expect("tokio runtime");
+    let outcome = runtime.block_on(replay(recording))?;
+    assert!(outcome.unused.is_empty(), "unused: {:?}", outcome.unused);
+    assert_eq!(outcome.calls.len(), recording.calls.len());
+    Ok(outcome
+        .results
+        .iter()
+        .map(|value| value.as_i64().unwrap())
+        .sorted()
+        .collect())
+}
+
+#[workflow]
+fn loop_workflow(mut a: i32, b: i32) -> i32 {
+    while less_than(a, b) {
+        a = add(a, 3);
+    }
+    a
+}
+
+#[test]
+fn replay_follows_a_recorded_loop() {
+    let wf_ir = loop_workflow().to_serializable("loop".to_string());
+    let (results, recording) = record("loop", wf_ir, vec![Value::new(1), Value::new(8)]);
+
+    assert_eq!(results, vec![10]);
+    // Four checks of the condition and three additions.
+    assert_eq!(recording.calls.len(), 7);
+    assert_eq!(replay_results(&recording).unwrap(), results);
+}
+
+#[test]
+fn replay_reports_where_control_flow_parts() {
+    #[workflow]
+    fn smaller_steps(mut a: i32, b: i32) -> i32 {
+        while less_than(a, b) {
+            a = add(a, 2);
+        }
+        a
+    }
+
+    let wf_ir = loop_workflow().to_serializable("loop".to_string());
+    let (_, mut recording) = record("parts", wf_ir, vec![Value::new(1), Value::new(8)]);
+
+    recording.workflow = smaller_steps().to_serializable("loop".to_string());
+    let err = replay_results(&recording).unwrap_err();
+    assert!(
+        err.to_string().contains("no recorded output for op"),
+        "{err}"
+    );
+}
+
+#[test]
+fn replay_covers_fan_out_and_collect() {
+    #[workflow]
+    fn reduce_workflow(offset: i32) -> i32 {
+        let a = range(1, 4);
+        let b = add(a, offset);
+        let all = collect(b);
+        let total = sum(all);
+        add(total, offset)
+    }
+
+    let wf_ir = reduce_workflow().to_serializable("reduce".to_string());
+    let (results, recording) = record("reduce", wf_ir, vec![Value::new(2)]);
+
+    assert_eq!(results, vec![68]);
+    assert_eq!(replay_results(&recording).unwrap(), results);
+}
+
+#[test]
+fn replay_covers_joins_and_failed_outputs() {
+    #[workflow]
+    fn join_workflow() -> i32 {
+        let a = range(1, 3);
+        let b = split(100, 2);
+        let c = add(a, b);
+        maybe_fail(c)
+    }
+
+    let wf_ir = join_workflow().to_serializable("join".to_string());
+    let (results, recording) = record("join", wf_ir, Vec::new());
+
+    assert_eq!(results, vec![110, 111, 120, 121]);
+    assert_eq!(replay_results(&recording).unwrap(), results);
+
+    #[workflow]
+    fn failing_workflow() -> i32 {
+        let a = range(1, 4);
+        maybe_fail(a)
+    }
+
+    let wf_ir = failing_workflow().to_serializable("failing".to_string());
+    let (results, recording) = record("failing", wf_ir, Vec::new());
+
+    assert_eq!(results, vec![10, 30]);
+    assert_eq!(replay_results(&recording).unwrap(), results);
+}
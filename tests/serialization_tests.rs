@@ -26,7 +26,7 @@ fn serializable_conditional_workflow() {
   "name": "conditional",
   "operations": [
     {
-      "literals": [ { "output": 0, "value": "10" } ],
+      "literals": [ { "output": 0, "ty": "i32", "value": 10 } ],
       "phis": [],
       "call": {
         "task_id": "is_positive",
@@ -88,13 +88,13 @@ fn serializable_while_loop_workflow() {
   "name": "while_loop",
   "operations": [
     {
-      "literals": [ { "output": 0, "value": "0" } ],
+      "literals": [ { "output": 0, "ty": "i32", "value": 0 } ],
       "phis": [],
       "call": null,
       "next": { "Jump": { "next": 1 } }
     },
     {
-      "literals": [ { "output": 2, "value": "3" } ],
+      "literals": [ { "output": 2, "ty": "i32", "value": 3 } ],
       "phis": [ { "output": 1, "from": [[0,0],[2,5]] } ],
       "call": {
         "task_id": "less_than",
@@ -104,7 +104,7 @@ fn serializable_while_loop_workflow() {
       "next": { "Branch": { "var": 3, "true_next": 2, "false_next": 3 } }
     },
     {
-      "literals": [ { "output": 4, "value": "1" } ],
+      "literals": [ { "output": 4, "ty": "i32", "value": 1 } ],
       "phis": [],
       "call": {
         "task_id": "add",
@@ -114,7 +114,7 @@ fn serializable_while_loop_workflow() {
       "next": { "Jump": { "next": 1 } }
     },
     {
-      "literals": [ { "output": 6, "value": "()" } ],
+      "literals": [ { "output": 6, "ty": "()", "value": null } ],
       "phis": [],
       "call": null,
       "next": { "Return": { "var": 1 } }
@@ -125,3 +125,37 @@ fn serializable_while_loop_workflow() {
     let expected: Workflow = serde_json::from_str(expected).unwrap();
     assert_eq!(serializable, expected);
 }
+
+#[test]
+fn serializable_workflow_inputs() {
+    #[workflow]
+    fn test_workflow(a: i32, b: i32) -> i32 {
+        add(a, b)
+    }
+
+    let graph = test_workflow();
+    let serializable = graph.to_serializable("inputs".to_string());
+
+    let expected = r#"{
+  "name": "inputs",
+  "inputs": [
+    { "name": "a", "ty": "i32", "output": 0 },
+    { "name": "b", "ty": "i32", "output": 1 }
+  ],
+  "operations": [
+    {
+      "literals": [],
+      "phis": [],
+      "call": {
+        "task_id": "add",
+        "inputs": [0, 1],
+        "outputs": [2]
+      },
+      "next": { "Return": { "var": 2 } }
+    }
+  ]
+}"#;
+
+    let expected: Workflow = serde_json::from_str(expected).unwrap();
+    assert_eq!(serializable, expected);
+}
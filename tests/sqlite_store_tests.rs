@@ -0,0 +1,198 @@
+mod common;
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use anyhow::Result;
+use namu::{register_task, task, workflow};
+use namu_core::Value;
+use namu_core::ir::Workflow;
+use namu_engine::engine::Engine;
+use namu_engine::simple_engine::SimpleEngine;
+use namu_engine::store::SqliteStore;
+use serde::Serialize;
+
+use crate::common::*;
+
+/// A fresh database file for `name`, removed when dropped.
+struct TempDb(PathBuf);
+
+impl TempDb {
+    fn new(name: &str) -> Self {
+        let db =
+            TempDb(std::env::temp_dir().join(format!("namu-{name}-{}.sqlite", std::process::id())));
+        db.remove();
+        db
+    }
+
+    fn engine(&self) -> SimpleEngine<SqliteStore> {
+        let engine = SimpleEngine::with_store(SqliteStore::open(&self.0).unwrap());
+        engine.register_all_tasks();
+        engine
+    }
+
+    fn remove(&self) {
+        for suffix in ["", "-wal", "-shm"] {
+            let mut path = self.0.clone().into_os_string();
+            path.push(suffix);
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+impl Drop for TempDb {
+    fn drop(&mut self) {
+        self.remove();
+    }
+}
+
+async fn run_to_end(
+    engine: &SimpleEngine<SqliteStore>,
+    workflow: Workflow,
+    inputs: Vec<Value>,
+) -> Result<Vec<Value>> {
+    let wf_id = engine.create_workflow(workflow).await;
+    let run_id = engine.create_run(wf_id, inputs).await;
+    let rx = engine.get_result(run_id).expect("run was created");
+    engine.run(run_id).await?;
+
+    let mut values = Vec::new();
+    while let Ok(Some(value)) = rx.try_recv() {
+        values.push(value);
+    }
+    Ok(values)
+}
+
+#[test]
+fn sqlite_store_runs_fan_out_and_collect() {
+    #[workflow]
+    fn reduce_workflow(offset: i32) -> i32 {
+        let a = range(1, 4);
+        let b = add(a, offset);
+        let all = collect(b);
+        let total = sum(all);
+        add(total, offset)
+    }
+
+    let db = TempDb::new("collect");
+    let wf_ir = reduce_workflow().to_serializable("reduce".to_string());
+
+    let runtime = tokio::runtime::Runtime::new().expect("tokio runtime");
+    let out = runtime
+        .block_on(run_to_end(&db.engine(), wf_ir, vec![Value::new(2)]))
+        .unwrap();
+
+    assert_eq!(out.len(), 1);
+    assert_eq!(*out[0].downcast_ref::<i32>().unwrap(), 68);
+}
+
+static FIRST_CALLS: AtomicUsize = AtomicUsize::new(0);
+static SLOW_CALLS: AtomicUsize = AtomicUsize::new(0);
+
+#[task(single)]
+fn first(n: i32) -> Result<i32> {
+    FIRST_CALLS.fetch_add(1, Ordering::SeqCst);
+    Ok(n + 1)
+}
+
+register_task! { method = first, name = "first", author = "test", version = "0.1" }
+
+#[task(single)]
+fn slow(n: i32) -> Result<i32> {
+    SLOW_CALLS.fetch_add(1, Ordering::SeqCst);
+    std::thread::sleep(Duration::from_millis(200));
+    Ok(n * 10)
+}
+
+register_task! { method = slow, name = "slow", author = "test", version = "0.1" }
+
+#[workflow]
+fn resume_workflow(n: i32) -> i32 {
+    let a = first(n);
+    let b = add(a, 1);
+    slow(b)
+}
+
+/// Start a run of `resume_workflow` and drop the process's runtime while
+/// `slow` runs, as a crash would.
+fn crash_during_slow(db: &TempDb, n: i32) {
+    let slow_calls = SLOW_CALLS.load(Ordering::SeqCst);
+    let runtime = tokio::runtime::Runtime::new().expect("tokio runtime");
+    runtime.block_on(async {
+        let engine = db.engine();
+        let wf_id = engine
+            .create_workflow(resume_workflow().to_serializable("resume".to_string()))
+            .await;
+        let run_id = engine.create_run(wf_id, vec![Value::new(n)]).await;
+        tokio::spawn(async move { engine.run(run_id).await });
+
+        while SLOW_CALLS.load(Ordering::SeqCst) == slow_calls {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        // Let the end markers of the earlier calls be recorded.
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    });
+}
+
+fn restart(db: &TempDb, n: i32) -> i32 {
+    let runtime = tokio::runtime::Runtime::new().expect("tokio runtime");
+    let out = runtime
+        .block_on(run_to_end(
+            &db.engine(),
+            resume_workflow().to_serializable("resume".to_string()),
+            vec![Value::new(n)],
+        ))
+        .unwrap();
+    assert_eq!(out.len(), 1);
+    *out[0].downcast_ref::<i32>().unwrap()
+}
+
+#[test]
+fn sqlite_store_resumes_after_restart() {
+    let db = TempDb::new("resume");
+
+    crash_during_slow(&db, 1);
+    assert_eq!(FIRST_CALLS.load(Ordering::SeqCst), 1);
+
+    // Completed calls are replayed, the interrupted one runs again.
+    assert_eq!(restart(&db, 1), 30);
+    assert_eq!(FIRST_CALLS.load(Ordering::SeqCst), 1);
+    assert_eq!(SLOW_CALLS.load(Ordering::SeqCst), 2);
+
+    // A finished run is gone from the store.
+    crash_during_slow(&db, 1);
+    assert_eq!(FIRST_CALLS.load(Ordering::SeqCst), 2);
+
+    // A run with other inputs starts over.
+    assert_eq!(restart(&db, 2), 40);
+    assert_eq!(FIRST_CALLS.load(Ordering::SeqCst), 3);
+    assert_eq!(SLOW_CALLS.load(Ordering::SeqCst), 4);
+}
+
+#[derive(Clone, Serialize)]
+pub struct Unregistered(i32);
+
+#[task(single)]
+fn wrap(n: i32) -> Result<Unregistered> {
+    Ok(Unregistered(n))
+}
+
+register_task! { method = wrap, name = "wrap", author = "test", version = "0.1" }
+
+#[test]
+fn sqlite_store_rejects_unregistered_values() {
+    #[workflow]
+    fn wrap_workflow() -> Unregistered {
+        wrap(1)
+    }
+
+    let db = TempDb::new("unregistered");
+    let wf_ir = wrap_workflow().to_serializable("wrap".to_string());
+
+    let runtime = tokio::runtime::Runtime::new().expect("tokio runtime");
+    let err = runtime
+        .block_on(run_to_end(&db.engine(), wf_ir, Vec::new()))
+        .unwrap_err();
+    assert!(err.to_string().contains("register it with #[type]"));
+}
@@ -0,0 +1,152 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use namu::{register_task, task, workflow};
+use namu_core::{TaskError, TaskErrorKind};
+use namu_engine::engine::Engine;
+use namu_engine::simple_engine::SimpleEngine;
+use namu_engine::traits::observer::RunObserver;
+use serde_json::json;
+
+use crate::common::*;
+
+#[task(single)]
+fn checked_div(a: i32, b: i32) -> Result<i32> {
+    if b == 0 {
+        return Err(TaskError::invalid_input("division by zero")
+            .with_details(json!({ "a": a }))
+            .into());
+    }
+    Ok(a / b)
+}
+
+register_task! { method = checked_div, name = "checked_div", author = "test", version = "0.1" }
+
+#[test]
+fn kinds_round_trip_as_snake_case() {
+    for kind in [
+        TaskErrorKind::Retryable,
+        TaskErrorKind::Fatal,
+        TaskErrorKind::InvalidInput,
+        TaskErrorKind::Timeout,
+        TaskErrorKind::Cancelled,
+    ] {
+        assert_eq!(serde_json::to_value(kind).unwrap(), json!(kind.as_str()));
+        assert_eq!(kind.as_str().parse::<TaskErrorKind>().unwrap(), kind);
+    }
+    assert!("TaskError".parse::<TaskErrorKind>().is_err());
+    assert!(TaskErrorKind::Timeout.is_retryable());
+    assert!(!TaskErrorKind::InvalidInput.is_retryable());
+}
+
+#[test]
+fn task_errors_keep_their_kind_through_context() {
+    let err = Err::<(), _>(anyhow::Error::new(
+        TaskError::fatal("disk full").with_source("write failed"),
+    ))
+    .context("saving checkpoint")
+    .unwrap_err();
+
+    let error = TaskError::from_anyhow(&err);
+    assert_eq!(error.kind, TaskErrorKind::Fatal);
+    assert_eq!(error.message, "saving checkpoint: disk full");
+    assert_eq!(error.sources, vec!["write failed"]);
+    assert_eq!(
+        format!("{error:#}"),
+        "saving checkpoint: disk full: write failed"
+    );
+}
+
+#[test]
+fn other_errors_are_retryable_with_their_chain() {
+    let err = Err::<(), _>(std::io::Error::other("connection reset"))
+        .context("fetching page")
+        .unwrap_err();
+
+    let error = TaskError::from_anyhow(&err);
+    assert_eq!(error.kind, TaskErrorKind::Retryable);
+    assert_eq!(error.message, "fetching page");
+    assert_eq!(error.sources, vec!["connection reset"]);
+}
+
+#[test]
+fn envelopes_parse_into_task_errors() {
+    let error = TaskError::timeout("too slow").with_details(json!({ "ms": 10 }));
+    assert_eq!(
+        error.to_envelope(),
+        json!({ "error": { "kind": "timeout", "message": "too slow", "details": { "ms": 10 } } })
+    );
+    assert_eq!(TaskError::parse(&error.to_envelope().to_string()), error);
+    assert_eq!(
+        TaskError::parse(&serde_json::to_string(&error).unwrap()),
+        error
+    );
+
+    // Older builds reported every failure as `TaskError`, or as plain text.
+    let legacy = json!({ "error": { "message": "boom", "kind": "TaskError" } });
+    assert_eq!(
+        TaskError::parse(&legacy.to_string()),
+        TaskError::retryable("boom")
+    );
+    assert_eq!(
+        TaskError::parse("lease expired"),
+        TaskError::retryable("lease expired")
+    );
+}
+
+#[derive(Default)]
+struct ErrorLog(Mutex<Vec<(String, TaskError)>>);
+
+impl RunObserver for ErrorLog {
+    fn on_error(
+        &self,
+        _run_id: usize,
+        _op_id: usize,
+        _ctx_id: usize,
+        task_id: &str,
+        error: &TaskError,
+    ) {
+        self.0
+            .lock()
+            .unwrap()
+            .push((task_id.to_string(), error.clone()));
+    }
+}
+
+#[test]
+fn observers_see_the_kind_a_task_failed_with() {
+    #[workflow]
+    fn divide_all() -> i32 {
+        let a = range(0, 2);
+        checked_div(a, a)
+    }
+
+    let wf_ir = divide_all().to_serializable("divide_all".to_string());
+    let log = Arc::new(ErrorLog::default());
+
+    let runtime = tokio::runtime::Runtime::new().expect("tokio runtime");
+    let results = runtime.block_on(async {
+        let engine = SimpleEngine::with_registered();
+        engine.add_observer(log.clone());
+        let wf_id = engine.create_workflow(wf_ir).await;
+        let run_id = engine.create_run(wf_id, Vec::new()).await;
+        let rx = engine.get_result(run_id).expect("run was created");
+        engine.run(run_id).await.unwrap();
+        let mut results = Vec::new();
+        while let Ok(Some(value)) = rx.try_recv() {
+            results.push(value);
+        }
+        results
+    });
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(
+        *log.0.lock().unwrap(),
+        vec![(
+            "checked_div".to_string(),
+            TaskError::invalid_input("division by zero").with_details(json!({ "a": 0 })),
+        )]
+    );
+}
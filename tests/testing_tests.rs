@@ -0,0 +1,127 @@
+mod common;
+
+use itertools::Itertools;
+use namu::testing::TestEngine;
+use namu::workflow;
+use namu_core::Value;
+use serde_json::json;
+
+use crate::common::*;
+
+#[workflow]
+fn loop_workflow(mut a: i32, b: i32) -> i32 {
+    while less_than(a, b) {
+        a = add(a, 3);
+    }
+    a
+}
+
+#[test]
+fn stubs_drive_a_loop_in_call_order() {
+    let engine = TestEngine::new()
+        .returns_each("less_than", [Ok(true), Ok(true), Ok(false)])
+        .stub("add", |(a, b): (i32, i32)| Ok(a + b * 100));
+
+    let wf_ir = loop_workflow().to_serializable("loop".to_string());
+    let results = engine
+        .run_blocking_as::<i32>(wf_ir, vec![Value::new(1), Value::new(8)])
+        .unwrap();
+
+    assert_eq!(results, vec![601]);
+    engine.assert_calls(&[
+        ("less_than", json!([1, 8])),
+        ("add", json!([1, 3])),
+        ("less_than", json!([301, 8])),
+        ("add", json!([301, 3])),
+        ("less_than", json!([601, 8])),
+    ]);
+    engine.assert_called("add", (301, 3));
+}
+
+#[test]
+fn unstubbed_tasks_are_reported_before_running() {
+    let engine = TestEngine::new().returns("less_than", false);
+
+    let wf_ir = loop_workflow().to_serializable("loop".to_string());
+    let err = engine
+        .run_blocking(wf_ir, vec![Value::new(1), Value::new(8)])
+        .unwrap_err();
+
+    assert!(
+        err.to_string().contains("task add is neither stubbed"),
+        "{err}"
+    );
+    assert!(engine.calls().is_empty());
+}
+
+#[test]
+fn stubs_stand_in_for_tasks_that_are_not_registered() {
+    #[workflow]
+    fn guarded(v: i32) -> i32 {
+        if is_positive(v) {
+            double(v)
+        } else {
+            panicker()
+        }
+    }
+
+    let engine = TestEngine::new()
+        .stub("is_positive", |(v,): (i32,)| Ok(v > 0))
+        .stub("double", |(v,): (i32,)| Ok(v * 2))
+        .fails("panicker", "not this branch");
+
+    let wf_ir = guarded().to_serializable("guarded".to_string());
+    let results = engine
+        .run_blocking_as::<i32>(wf_ir, vec![Value::new(21)])
+        .unwrap();
+
+    assert_eq!(results, vec![42]);
+    engine.assert_not_called("panicker");
+}
+
+#[test]
+fn failures_are_injected_into_streams() {
+    #[workflow]
+    fn failing_workflow() -> i32 {
+        let a = range(1, 4);
+        maybe_fail(a)
+    }
+
+    let engine = TestEngine::new()
+        .stub_stream("range", |(start, end): (i32, i32)| Ok(start..end))
+        .returns_each("maybe_fail", [Ok(1), Err(anyhow::anyhow!("boom")), Ok(3)]);
+
+    let wf_ir = failing_workflow().to_serializable("failing".to_string());
+    let results = engine.run_blocking_as::<i32>(wf_ir, Vec::new()).unwrap();
+
+    assert_eq!(results.into_iter().sorted().collect_vec(), vec![1, 3]);
+    // The items reach `maybe_fail` only once `range` answered.
+    assert_eq!(engine.calls()[0].task_id, "range");
+    assert_eq!(
+        engine
+            .calls_to("maybe_fail")
+            .into_iter()
+            .sorted_by_key(|v| v.to_string())
+            .collect_vec(),
+        vec![json!([1]), json!([2]), json!([3])]
+    );
+}
+
+#[test]
+fn registered_tasks_run_beside_stubs() {
+    #[workflow]
+    fn failing_workflow() -> i32 {
+        let a = range(1, 4);
+        maybe_fail(a)
+    }
+
+    let engine = TestEngine::with_registered().fails("maybe_fail", "down");
+
+    let wf_ir = failing_workflow().to_serializable("failing".to_string());
+    let results = engine.run_blocking(wf_ir, Vec::new()).unwrap();
+
+    assert!(results.is_empty());
+    assert_eq!(engine.calls().len(), 3);
+    engine.assert_called("maybe_fail", (20,));
+    engine.assert_not_called("range");
+}
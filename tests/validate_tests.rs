@@ -0,0 +1,262 @@
+mod common;
+
+use namu::workflow;
+use namu_core::ir::{Call, Collect, Input, Literal, Next, Operation, Phi, Workflow};
+use namu_core::validate::ValidationError;
+
+use crate::common::*;
+
+fn literal(output: usize, ty: &str, value: serde_json::Value) -> Literal {
+    Literal {
+        output,
+        ty: ty.to_string(),
+        value,
+    }
+}
+
+fn call(task_id: &str, inputs: Vec<usize>, outputs: Vec<usize>) -> Option<Call> {
+    Some(Call {
+        task_id: task_id.to_string(),
+        inputs,
+        outputs,
+    })
+}
+
+#[test]
+fn built_workflows_are_valid() {
+    #[workflow]
+    fn conditional_in_while_loop(start: i32) -> i32 {
+        let mut n = start;
+        let mut count = 0;
+        while not_one(n) {
+            if is_even(n) {
+                n = divide_by_2(n);
+            } else {
+                n = multiply_by_3_and_add_1(n);
+            }
+            count = add(count, 1);
+        }
+        count
+    }
+
+    #[workflow]
+    fn else_if_workflow() -> i32 {
+        let input = 10;
+        if is_positive(input) {
+            double(input)
+        } else if is_negative(input) {
+            double(input)
+        } else {
+            identity(input)
+        }
+    }
+
+    #[workflow]
+    fn stream_workflow() -> i32 {
+        let a = range(1, 4);
+        let b = split(100, 2);
+        add(a, b)
+    }
+
+    #[workflow]
+    fn collect_workflow() -> i32 {
+        let a = range(1, 4);
+        let b = add(a, 1);
+        let all = collect(b);
+        sum(all)
+    }
+
+    for wf in [
+        conditional_in_while_loop().to_serializable("collatz".to_string()),
+        else_if_workflow().to_serializable("else_if".to_string()),
+        stream_workflow().to_serializable("stream".to_string()),
+        collect_workflow().to_serializable("collect".to_string()),
+    ] {
+        assert_eq!(wf.validate(), Ok(()), "{}", wf.name);
+    }
+}
+
+#[test]
+fn validate_rejects_invalid_target() {
+    let wf = Workflow::new(
+        "invalid_target".to_string(),
+        vec![Operation::new(vec![], vec![], None, Next::jump(3))],
+    );
+
+    assert_eq!(
+        wf.validate(),
+        Err(vec![ValidationError::InvalidTarget { op: 0, target: 3 }])
+    );
+}
+
+#[test]
+fn validate_rejects_value_undefined_on_one_path() {
+    // Value 2 is only defined on the true branch but read after the merge.
+    let wf = Workflow::new(
+        "undefined".to_string(),
+        vec![
+            Operation::new(
+                vec![literal(0, "bool", true.into())],
+                vec![],
+                None,
+                Next::branch(0, 1, 2),
+            ),
+            Operation::new(
+                vec![literal(2, "i32", 1.into())],
+                vec![],
+                None,
+                Next::jump(2),
+            ),
+            Operation::new(vec![], vec![], None, Next::return_value(2)),
+        ],
+    );
+
+    assert_eq!(
+        wf.validate(),
+        Err(vec![ValidationError::UndefinedValue { op: 2, value: 2 }])
+    );
+}
+
+#[test]
+fn validate_rejects_missing_phi_source() {
+    let wf = Workflow::new(
+        "missing_phi".to_string(),
+        vec![
+            Operation::new(
+                vec![literal(0, "bool", true.into())],
+                vec![],
+                None,
+                Next::branch(0, 1, 2),
+            ),
+            Operation::new(
+                vec![literal(1, "i32", 1.into())],
+                vec![],
+                None,
+                Next::jump(2),
+            ),
+            Operation::new(
+                vec![],
+                vec![Phi {
+                    output: 2,
+                    from: vec![(1, 1)],
+                }],
+                None,
+                Next::return_value(2),
+            ),
+        ],
+    );
+
+    assert_eq!(
+        wf.validate(),
+        Err(vec![ValidationError::MissingPhiSource {
+            op: 2,
+            phi: 2,
+            predecessor: 0,
+        }])
+    );
+}
+
+#[test]
+fn validate_rejects_non_bool_condition() {
+    let wf = Workflow::new(
+        "non_bool".to_string(),
+        vec![
+            Operation::new(
+                vec![],
+                vec![],
+                call("add", vec![0, 0], vec![1]),
+                Next::branch(0, 1, 1),
+            ),
+            Operation::new(vec![], vec![], None, Next::return_value(1)),
+        ],
+    )
+    .with_inputs(vec![Input {
+        name: "a".to_string(),
+        ty: "i32".to_string(),
+        output: 0,
+    }]);
+
+    assert_eq!(
+        wf.validate(),
+        Err(vec![ValidationError::NonBoolCondition {
+            op: 0,
+            value: 0,
+            ty: "i32".to_string(),
+        }])
+    );
+}
+
+#[test]
+fn validate_rejects_invalid_collect() {
+    // Op 0 has no call, so there is nothing for op 1 to gather.
+    let wf = Workflow::new(
+        "invalid_collect".to_string(),
+        vec![
+            Operation::new(
+                vec![literal(0, "i32", 1.into())],
+                vec![],
+                None,
+                Next::jump(1),
+            ),
+            Operation::new(vec![], vec![], None, Next::return_value(1)).with_collect(Collect {
+                from: 0,
+                input: 0,
+                ty: "i32".to_string(),
+                output: 1,
+            }),
+        ],
+    );
+
+    assert_eq!(
+        wf.validate(),
+        Err(vec![ValidationError::InvalidCollect { op: 1, from: 0 }])
+    );
+}
+
+#[test]
+fn validate_rejects_item_value_after_collect() {
+    // Value 1 is produced per stream item, so it is gone once op 1 gathers.
+    let wf = Workflow::new(
+        "item_after_collect".to_string(),
+        vec![
+            Operation::new(
+                vec![literal(0, "i32", 1.into())],
+                vec![],
+                call("range", vec![0, 0], vec![1]),
+                Next::jump(1),
+            ),
+            Operation::new(vec![], vec![], None, Next::return_value(1)).with_collect(Collect {
+                from: 0,
+                input: 1,
+                ty: "i32".to_string(),
+                output: 2,
+            }),
+        ],
+    );
+
+    assert_eq!(
+        wf.validate(),
+        Err(vec![ValidationError::UndefinedValue { op: 1, value: 1 }])
+    );
+}
+
+#[test]
+fn validate_rejects_missing_return() {
+    let wf = Workflow::new(
+        "loop".to_string(),
+        vec![Operation::new(vec![], vec![], None, Next::jump(0))],
+    );
+
+    assert_eq!(wf.validate(), Err(vec![ValidationError::NoReturn]));
+}
+
+#[test]
+fn validation_errors_serialize_with_kind() {
+    let err = ValidationError::UndefinedValue { op: 2, value: 5 };
+
+    assert_eq!(
+        serde_json::to_value(&err).unwrap(),
+        serde_json::json!({ "kind": "undefined_value", "op": 2, "value": 5 })
+    );
+    assert_eq!(err.to_string(), "op 2 reads value 5 before it is defined");
+}